@@ -4,7 +4,11 @@
 //! and hOCR layers for text accessibility.
 
 use anyhow::{Result, anyhow};
-use crate::fonts::FontContext;
+use crate::document::{Block, DocumentBuilder, LayoutMode};
+use crate::fonts::{FontContext, WritingMode};
+use crate::font_embedding::{self, EmbeddingPermission, FontUsage, VerticalMetrics};
+use crate::shaping::{self, ShapedGlyph};
+use crate::syllables;
 use crate::typography::{TextLayoutEngine, FormattedParagraph, FormattedLine};
 use crate::hocr_layer::{HocrGenerator, HocrPage};
 use lopdf::{
@@ -12,7 +16,26 @@ use lopdf::{
     content::{Content, Operation},
     ObjectId,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Glyphs drawn with one font in a single TJ operation
+struct TextRun {
+    font_name: String,
+    font_size: f32,
+    tj: Vec<Object>,
+    /// Source text for a glyph whose CID cannot map back to it through ToUnicode
+    actual_text: Option<String>,
+    /// Text rise (Ts) in points, for marks the shaper moved up or down
+    rise: f32,
+}
+
+/// A shaped font run of a line: (is_chinese, font size, chain slot, text, glyphs, first character)
+type ShapedRun = (bool, f32, usize, String, Arc<[ShapedGlyph]>, usize);
+
+/// Where each glyph of a line is drawn: (first character of its cluster, start x,
+/// end x), in points along the line from its origin
+type GlyphExtents = Vec<(usize, f32, f32)>;
 
 /// Bilingual PDF generator
 pub struct BilingualPdfGenerator {
@@ -21,6 +44,7 @@ pub struct BilingualPdfGenerator {
     hocr_generator: HocrGenerator,
     document: Document,
     font_objects: HashMap<String, ObjectId>,
+    font_usage: HashMap<String, FontUsage>,
     pages_id: ObjectId,
 }
 
@@ -49,6 +73,7 @@ impl BilingualPdfGenerator {
             hocr_generator,
             document: Document::new(),
             font_objects: HashMap::new(),
+            font_usage: HashMap::new(),
             pages_id: (0, 0), // Will be set properly in initialize_document
         }
     }
@@ -60,24 +85,67 @@ impl BilingualPdfGenerator {
         english_sections: &[String],
         output_path: &str,
     ) -> Result<()> {
-        if chinese_sections.len() != english_sections.len() {
-            return Err(anyhow!(
-                "Chinese and English sections must have the same length: {} vs {}",
-                chinese_sections.len(),
-                english_sections.len()
-            ));
+        let document = DocumentBuilder::from_sections(chinese_sections, english_sections)?;
+        self.generate_document(document.blocks(), LayoutMode::Alternating, output_path)
+    }
+
+    /// Generate a bilingual PDF with true side-by-side columns.
+    pub fn generate_bilingual_pdf_side_by_side(
+        &mut self,
+        chinese_sections: &[String],
+        english_sections: &[String],
+        output_path: &str,
+    ) -> Result<()> {
+        let document = DocumentBuilder::from_sections(chinese_sections, english_sections)?;
+        self.generate_document(document.blocks(), LayoutMode::SideBySide, output_path)
+    }
+    
+    /// Generate a PDF in vertical writing mode: Chinese in top-to-bottom columns
+    /// running right to left, English lines rotated into columns of their own,
+    /// and pages bound on the right.
+    pub fn generate_vertical_pdf(
+        &mut self,
+        chinese_sections: &[String],
+        english_sections: &[String],
+        output_path: &str,
+    ) -> Result<()> {
+        let document = DocumentBuilder::from_sections(chinese_sections, english_sections)?;
+        self.generate_document(document.blocks(), LayoutMode::Vertical, output_path)
+    }
+
+    /// Generate a PDF of a structured document in `layout`
+    pub fn generate_document(&mut self, blocks: &[Block], layout: LayoutMode, output_path: &str) -> Result<()> {
+        if layout == LayoutMode::Vertical {
+            // Lines are measured down the column from here on
+            self.font_context.writing_mode = WritingMode::Vertical;
+            self.layout_engine = crate::typography::create_layout_engine(self.font_context.clone());
         }
-        
+
         // Initialize PDF document
         self.initialize_document()?;
-        
-        // Create alternating paragraph layout
-        let all_paragraphs = self.create_alternating_layout(chinese_sections, english_sections)?;
-        
-        // Generate pages
-        println!("DEBUG: About to create pages from {} paragraphs", all_paragraphs.len());
-        let pages = self.create_pages_from_paragraphs(all_paragraphs)?;
-        println!("DEBUG: Created {} pages", pages.len());
+        for block in blocks {
+            if let Block::Metadata { key, value } = block {
+                self.set_metadata(key, value)?;
+            }
+        }
+
+        let pages = match layout {
+            LayoutMode::Alternating => {
+                // Create alternating paragraph layout
+                let all_paragraphs = self.create_alternating_layout(blocks)?;
+
+                // Generate pages
+                println!("DEBUG: About to create pages from {} paragraphs", all_paragraphs.len());
+                let pages = self.create_pages_from_paragraphs(all_paragraphs)?;
+                println!("DEBUG: Created {} pages", pages.len());
+                pages
+            }
+            LayoutMode::SideBySide => self.create_pages_side_by_side(blocks)?,
+            LayoutMode::Vertical => {
+                self.set_right_to_left_binding()?;
+                self.create_pages_vertical(blocks)?
+            }
+        };
 
         // Add hOCR layer
         self.add_hocr_layer(&pages)?;
@@ -92,33 +160,11 @@ impl BilingualPdfGenerator {
         Ok(())
     }
 
-    /// Generate a bilingual PDF with true side-by-side columns.
-    pub fn generate_bilingual_pdf_side_by_side(
-        &mut self,
-        chinese_sections: &[String],
-        english_sections: &[String],
-        output_path: &str,
-    ) -> Result<()> {
-        if chinese_sections.len() != english_sections.len() {
-            return Err(anyhow!(
-                "Chinese and English sections must have the same length: {} vs {}",
-                chinese_sections.len(),
-                english_sections.len()
-            ));
-        }
-
-        self.initialize_document()?;
-        let pages = self.create_pages_side_by_side(chinese_sections, english_sections)?;
-        self.add_hocr_layer(&pages)?;
-        self.save_document(output_path)?;
-        Ok(())
-    }
-    
     /// Initialize PDF document with fonts and metadata
     fn initialize_document(&mut self) -> Result<()> {
-        // Add fonts to document
-        let _chinese_id = self.add_font_to_document("chinese")?;
-        let _english_id = self.add_font_to_document("english")?;
+        // Reserve font objects; they are written once glyph usage is known
+        self.reserve_font_object("chinese");
+        self.reserve_font_object("english");
 
         // Create pages structure
         let mut pages_dict = Dictionary::new();
@@ -149,13 +195,81 @@ impl BilingualPdfGenerator {
 
         Ok(())
     }
-    
-    /// Create alternating layout: Chinese #1 → English #1 → Chinese #2 → English #2
-    fn create_alternating_layout(
+
+    /// Set an entry of the document information dictionary; `title`, `author`,
+    /// `subject` and `keywords` go to the standard entries
+    fn set_metadata(&mut self, key: &str, value: &str) -> Result<()> {
+        let key = match key.trim().to_ascii_lowercase().as_str() {
+            "title" => "Title".to_string(),
+            "author" => "Author".to_string(),
+            "subject" => "Subject".to_string(),
+            "keywords" => "Keywords".to_string(),
+            _ => key.trim().to_string(),
+        };
+        if key.is_empty() {
+            return Err(anyhow!("Document metadata needs a key"));
+        }
+
+        let info_id = self.document.trailer.get(b"Info")?.as_reference()?;
+        self.document
+            .get_object_mut(info_id)?
+            .as_dict_mut()?
+            .set(key, Object::String(utf16be_with_bom(value), StringFormat::Hexadecimal));
+        Ok(())
+    }
+
+    /// Lay out a block's text in one language: its paragraph, or a paragraph
+    /// for each row of a verse
+    fn layout_block(
         &mut self,
-        chinese_sections: &[String],
-        english_sections: &[String],
+        block: &Block,
+        is_chinese: bool,
+        x: f32,
+        y: f32,
+        max_width: f32,
     ) -> Result<Vec<FormattedParagraph>> {
+        if let Block::Verse { .. } = block {
+            let style = block.style(&self.font_context).unwrap_or_default();
+            return self.layout_engine.layout_verse(&block.texts(is_chinese), x, y, max_width, is_chinese, &style);
+        }
+        let mut paragraphs = Vec::new();
+        for text in block.texts(is_chinese) {
+            if !text.trim().is_empty() {
+                paragraphs.push(self.layout_block_paragraph(block, text, x, y, max_width, is_chinese)?);
+            }
+        }
+        Ok(paragraphs)
+    }
+
+    /// Lay out one paragraph of a block: pairs in the style their markup selects,
+    /// other blocks in their own, headings kept with what follows when the
+    /// document asks, and a line of verse whole
+    fn layout_block_paragraph(
+        &mut self,
+        block: &Block,
+        text: &str,
+        x: f32,
+        y: f32,
+        max_width: f32,
+        is_chinese: bool,
+    ) -> Result<FormattedParagraph> {
+        let style = block.style(&self.font_context);
+        let mut paragraph = match (block, style) {
+            (Block::Verse { .. }, Some(style)) => self
+                .layout_engine
+                .layout_verse(&[text], x, y, max_width, is_chinese, &style)?
+                .pop()
+                .ok_or_else(|| anyhow!("Empty line of verse"))?,
+            (_, Some(style)) => self.layout_engine.layout_styled_paragraph(text, x, y, max_width, is_chinese, &style)?,
+            (_, None) => self.layout_engine.layout_paragraph(text, x, y, max_width, is_chinese)?,
+        };
+        paragraph.keep_with_next = self.font_context.keep_headings_with_next && matches!(block, Block::Heading { .. });
+        Ok(paragraph)
+    }
+    
+    /// Create alternating layout: Chinese #1 → English #1 → Chinese #2 → English #2.
+    /// The lines of a verse follow each other without paragraph spacing.
+    fn create_alternating_layout(&mut self, blocks: &[Block]) -> Result<Vec<FormattedParagraph>> {
         let mut all_paragraphs = Vec::new();
         
         let (content_x, content_y, content_width, _content_height) = self.safe_content_area();
@@ -163,38 +277,47 @@ impl BilingualPdfGenerator {
         
         // Add spacing between alternating paragraphs
         let paragraph_spacing = self.font_context.get_line_height(true) * 0.5;
+        let last_text_block = blocks.iter().rposition(|block| !block.rows().is_empty());
+        let mut break_before = false;
         
-        for (index, (chinese_text, english_text)) in chinese_sections.iter().zip(english_sections.iter()).enumerate() {
-            // Add Chinese paragraph
-            if !chinese_text.trim().is_empty() {
-                let chinese_para = self.layout_engine.layout_paragraph(
-                    chinese_text,
-                    content_x,
-                    current_y,
-                    content_width,
-                    true, // is_chinese
-                )?;
-                
-                current_y += chinese_para.height + paragraph_spacing;
-                all_paragraphs.push(chinese_para);
+        for (index, block) in blocks.iter().enumerate() {
+            if matches!(block, Block::PageBreak) {
+                break_before = true;
+                continue;
             }
-            
-            // Add English paragraph
-            if !english_text.trim().is_empty() {
-                let english_para = self.layout_engine.layout_paragraph(
-                    english_text,
-                    content_x,
-                    current_y,
-                    content_width,
-                    false, // is_chinese
-                )?;
-                
-                current_y += english_para.height + paragraph_spacing;
-                all_paragraphs.push(english_para);
+
+            // Chinese, then English
+            let first_of_block = all_paragraphs.len();
+            for is_chinese in [true, false] {
+                let paragraphs = self.layout_block(block, is_chinese, content_x, current_y, content_width)?;
+                let count = paragraphs.len();
+                for (i, mut paragraph) in paragraphs.into_iter().enumerate() {
+                    paragraph.y = current_y;
+
+                    // A verse is spaced as one paragraph
+                    let mut gap = paragraph_spacing;
+                    if i > 0 {
+                        paragraph.space_before = 0.0;
+                    }
+                    if i + 1 < count {
+                        paragraph.space_after = 0.0;
+                        gap = 0.0;
+                    }
+                    paragraph.break_before = std::mem::take(&mut break_before);
+
+                    // Style spacing is dropped at the top of a page when pages are made
+                    paragraph.y += paragraph.space_before;
+                    current_y = paragraph.y + paragraph.height + gap + paragraph.space_after;
+                    all_paragraphs.push(paragraph);
+                }
             }
             
+            if let [chinese, english] = &mut all_paragraphs[first_of_block..] {
+                self.keep_pair(chinese, english);
+            }
+
             // Add section separator (optional)
-            if index < chinese_sections.len() - 1 {
+            if last_text_block.is_some_and(|last| index < last) {
                 current_y += paragraph_spacing * 2.0; // Extra space between sections
             }
         }
@@ -202,45 +325,250 @@ impl BilingualPdfGenerator {
         Ok(all_paragraphs)
     }
     
-    /// Create PDF pages from formatted paragraphs
+    /// Keep a Chinese paragraph on a page with the first lines of its English,
+    /// and a short pair whole
+    fn keep_pair(&self, chinese: &mut FormattedParagraph, english: &mut FormattedParagraph) {
+        if !chinese.is_chinese || english.is_chinese {
+            return;
+        }
+        if self.font_context.keep_pairs_together {
+            chinese.keep_with_next = true;
+        }
+        if chinese.lines.len() + english.lines.len() <= self.font_context.short_pair_lines {
+            chinese.keep_with_next = true;
+            chinese.keep_together = true;
+            english.keep_together = true;
+        }
+    }
+
+    /// Create PDF pages from formatted paragraphs. A paragraph that does not fit
+    /// is broken between lines, leaving at least `orphan_lines` at the foot of the
+    /// page and carrying at least `widow_lines` to the next; when that cannot be
+    /// done the whole paragraph moves on. The spacing laid out between paragraphs
+    /// is kept except at the top of a page. A paragraph kept with the next (a
+    /// heading, or Chinese with its English) moves on rather than end a page
+    /// without the first lines of what follows it, one kept together moves on
+    /// whole, and a paragraph breaking before starts a new page.
     fn create_pages_from_paragraphs(&mut self, paragraphs: Vec<FormattedParagraph>) -> Result<Vec<HocrPage>> {
         let mut pages = Vec::new();
-        let (_content_x, _content_y, _content_width, content_height) = self.safe_content_area();
-        
+        let (_content_x, content_y, _content_width, content_height) = self.safe_content_area();
+        let orphans = self.font_context.orphan_lines.max(1);
+        let widows = self.font_context.widow_lines.max(1);
+
+        // Extent below each paragraph that must share its page: for one kept
+        // with the next, the gap and the fewest lines the next can start a page
+        // foot with; all of them, and what they are kept with in turn, when the
+        // next is not to be broken
+        let mut kept_below = vec![0.0_f32; paragraphs.len()];
+        for i in (0..paragraphs.len().saturating_sub(1)).rev() {
+            let (paragraph, next) = (&paragraphs[i], &paragraphs[i + 1]);
+            if paragraph.keep_with_next && !next.break_before {
+                let gap = (next.y - paragraph.y - paragraph.height).max(0.0);
+                let first_lines = if next.keep_together || next.lines.len() < orphans + widows {
+                    next.height + kept_below[i + 1]
+                } else {
+                    next.leading * orphans as f32
+                };
+                kept_below[i] = gap + first_lines;
+            }
+        }
+
         // Group paragraphs into pages
-        let mut current_page_paragraphs = Vec::new();
+        let mut current_page_paragraphs: Vec<FormattedParagraph> = Vec::new();
         let mut current_page_height = 0.0;
-        
-        for paragraph in &paragraphs {
-            if current_page_height + paragraph.height > content_height && !current_page_paragraphs.is_empty() {
+        let mut previous_bottom: Option<f32> = None;
+
+        for (mut paragraph, kept) in paragraphs.into_iter().zip(kept_below) {
+            let mut spacing = previous_bottom.map_or(0.0, |bottom| (paragraph.y - bottom).max(0.0));
+            previous_bottom = Some(paragraph.y + paragraph.height);
+
+            if paragraph.break_before && !current_page_paragraphs.is_empty() {
+                let page = self.create_single_page(&current_page_paragraphs)?;
+                pages.push(page);
+                current_page_paragraphs.clear();
+                current_page_height = 0.0;
+                spacing = 0.0;
+            }
+
+            loop {
+                let top = if current_page_paragraphs.is_empty() { 0.0 } else { current_page_height + spacing };
+                let available = content_height - top;
+                // A single line taller than the page is placed anyway
+                let unbreakable = current_page_paragraphs.is_empty() && paragraph.lines.len() <= 1;
+                // Fits, but would end the page without what it is kept with
+                let parted = !current_page_paragraphs.is_empty()
+                    && paragraph.height <= available
+                    && paragraph.height + kept > available
+                    && paragraph.height + kept <= content_height;
+                if (paragraph.height <= available && !parted) || unbreakable {
+                    paragraph.y = content_y + top;
+                    current_page_height = top + paragraph.height;
+                    current_page_paragraphs.push(paragraph);
+                    break;
+                }
+
+                // Lines that fit in the space left, then the widow and orphan limits
+                let total = paragraph.lines.len();
+                let fit = (available / paragraph.leading).floor().max(0.0) as usize;
+                let split = fit.min(total.saturating_sub(widows));
+                let whole = paragraph.keep_together && paragraph.height <= content_height;
+                let split = if parted || (whole && !current_page_paragraphs.is_empty()) {
+                    None
+                } else if split >= orphans {
+                    Some(split)
+                } else if current_page_paragraphs.is_empty() {
+                    // Too long for an empty page: break wherever the page ends
+                    Some(fit.clamp(1, total - 1))
+                } else {
+                    None
+                };
+
+                if let Some(split) = split {
+                    let rest = paragraph.split_off(split);
+                    paragraph.y = content_y + top;
+                    current_page_paragraphs.push(paragraph);
+                    paragraph = rest;
+                }
+
                 // Current page is full, create it
                 let page = self.create_single_page(&current_page_paragraphs)?;
                 pages.push(page);
-                
+
                 // Start new page
                 current_page_paragraphs.clear();
                 current_page_height = 0.0;
+                spacing = 0.0;
             }
-            
-            current_page_paragraphs.push(paragraph.clone());
-            current_page_height += paragraph.height;
         }
-        
+
         // Create the last page if there are remaining paragraphs
         if !current_page_paragraphs.is_empty() {
             let page = self.create_single_page(&current_page_paragraphs)?;
             pages.push(page);
         }
-        
+
+        Ok(pages)
+    }
+
+    /// Mark the document as read right to left so viewers show facing pages
+    /// bound on the right.
+    fn set_right_to_left_binding(&mut self) -> Result<()> {
+        let catalog_id = self.document.trailer.get(b"Root")?.as_reference()?;
+        let mut preferences = Dictionary::new();
+        preferences.set("Direction", Object::Name(b"R2L".to_vec()));
+        self.document
+            .get_object_mut(catalog_id)?
+            .as_dict_mut()?
+            .set("ViewerPreferences", Object::Dictionary(preferences));
+        Ok(())
+    }
+
+    /// Create pages for vertical layout: each line of a paragraph is a column, columns
+    /// run from the right edge of the page to the left, and a paragraph that does not
+    /// fit continues at the right edge of the next page. A heading moves on
+    /// rather than end a page without a column of what follows it.
+    fn create_pages_vertical(&mut self, blocks: &[Block]) -> Result<Vec<HocrPage>> {
+        let mut pages = Vec::new();
+        let (content_x, content_y, content_width, content_height) = self.safe_content_area();
+        let right_edge = content_x + content_width;
+        let paragraph_gap = self.font_context.get_line_height(true) * self.font_context.paragraph_spacing.max(0.2);
+
+        let mut column_right = right_edge;
+        let mut current_page_paragraphs: Vec<FormattedParagraph> = Vec::new();
+        let mut space_after = 0.0;
+
+        for block in blocks {
+            if matches!(block, Block::PageBreak) {
+                if !current_page_paragraphs.is_empty() {
+                    let page = self.create_single_page(&current_page_paragraphs)?;
+                    pages.push(page);
+                    current_page_paragraphs.clear();
+                }
+                column_right = right_edge;
+                continue;
+            }
+
+            for is_chinese in [true, false] {
+                // The column length plays the part of the line width.
+                let paragraphs = self.layout_block(block, is_chinese, content_x, content_y, content_height)?;
+                let count = paragraphs.len();
+                for (i, mut paragraph) in paragraphs.into_iter().enumerate() {
+                    let pitch = paragraph.leading;
+                    let mut remaining = std::mem::take(&mut paragraph.lines);
+
+                    // A verse is spaced as one paragraph
+                    if i + 1 < count {
+                        paragraph.space_after = 0.0;
+                    }
+                    if !current_page_paragraphs.is_empty() {
+                        let gap = if i > 0 { 0.0 } else { paragraph_gap + paragraph.space_before };
+                        column_right -= gap + space_after;
+                    }
+                    space_after = paragraph.space_after;
+
+                    // A heading starts a new page unless a column of what follows fits beside it
+                    if paragraph.keep_with_next && !current_page_paragraphs.is_empty() {
+                        let kept = pitch * remaining.len() as f32 + paragraph_gap + space_after + pitch;
+                        if kept > column_right - content_x && kept <= content_width {
+                            let page = self.create_single_page(&current_page_paragraphs)?;
+                            pages.push(page);
+                            current_page_paragraphs.clear();
+                            column_right = right_edge;
+                        }
+                    }
+
+                    while !remaining.is_empty() {
+                        let mut fit = ((column_right - content_x) / pitch).floor().max(0.0) as usize;
+                        if fit == 0 {
+                            if !current_page_paragraphs.is_empty() {
+                                let page = self.create_single_page(&current_page_paragraphs)?;
+                                pages.push(page);
+                                current_page_paragraphs.clear();
+                                column_right = right_edge;
+                                continue;
+                            }
+                            // A column wider than the page still gets a page of its own.
+                            fit = 1;
+                        }
+
+                        let columns: Vec<FormattedLine> = remaining.drain(..fit.min(remaining.len())).collect();
+                        let block_width = pitch * columns.len() as f32;
+                        let block_x = column_right - block_width;
+                        let count = columns.len();
+                        let mut segment = paragraph.clone();
+                        segment.lines = columns
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, mut line)| {
+                                // First column at the right; x is the column centre.
+                                line.x = pitch * (count - 1 - i) as f32 + pitch / 2.0;
+                                line.baseline = 0.0;
+                                line
+                            })
+                            .collect();
+                        segment.x = block_x;
+                        segment.y = content_y;
+                        segment.width = block_width;
+                        segment.height = content_height;
+                        current_page_paragraphs.push(segment);
+                        column_right = block_x;
+                    }
+                }
+            }
+        }
+
+        if !current_page_paragraphs.is_empty() {
+            let page = self.create_single_page(&current_page_paragraphs)?;
+            pages.push(page);
+        }
+
         Ok(pages)
     }
 
     /// Create pages for side-by-side layout (left column Chinese, right column English).
-    fn create_pages_side_by_side(
-        &mut self,
-        chinese_sections: &[String],
-        english_sections: &[String],
-    ) -> Result<Vec<HocrPage>> {
+    /// Verse is set a line to a row; a heading row moves on rather than end a
+    /// page without a line of what follows it.
+    fn create_pages_side_by_side(&mut self, blocks: &[Block]) -> Result<Vec<HocrPage>> {
         let mut pages = Vec::new();
         let (content_x, content_y, content_width, content_height) = self.safe_content_area();
         let page_bottom = content_y + content_height;
@@ -254,43 +582,103 @@ impl BilingualPdfGenerator {
         let mut current_y = content_y;
         let mut current_page_paragraphs: Vec<FormattedParagraph> = Vec::new();
 
-        for (zh_text, en_text) in chinese_sections.iter().zip(english_sections.iter()) {
-            let (mut zh_para, mut en_para, mut row_height) = self.layout_side_by_side_row(
-                zh_text,
-                en_text,
-                content_x,
-                right_column_x,
-                current_y,
-                column_width,
-            )?;
-
-            if current_y + row_height > page_bottom && !current_page_paragraphs.is_empty() {
-                let page = self.create_single_page(&current_page_paragraphs)?;
-                pages.push(page);
-                current_page_paragraphs.clear();
+        for block in blocks {
+            if matches!(block, Block::PageBreak) {
+                if !current_page_paragraphs.is_empty() {
+                    let page = self.create_single_page(&current_page_paragraphs)?;
+                    pages.push(page);
+                    current_page_paragraphs.clear();
+                }
                 current_y = content_y;
+                continue;
+            }
 
-                let relaid = self.layout_side_by_side_row(
-                    zh_text,
-                    en_text,
+            let rows = block.rows();
+            let count = rows.len();
+            for (i, texts) in rows.into_iter().enumerate() {
+                let (zh_para, en_para, mut row_height) = self.layout_side_by_side_row(
+                    block,
+                    texts,
                     content_x,
                     right_column_x,
                     current_y,
                     column_width,
                 )?;
-                zh_para = relaid.0;
-                en_para = relaid.1;
-                row_height = relaid.2;
-            }
+                let mut row: Vec<FormattedParagraph> = zh_para.into_iter().chain(en_para).collect();
 
-            if let Some(p) = zh_para {
-                current_page_paragraphs.push(p);
-            }
-            if let Some(p) = en_para {
-                current_page_paragraphs.push(p);
-            }
+                // A verse is spaced as one paragraph
+                for column in &mut row {
+                    if i > 0 {
+                        column.space_before = 0.0;
+                    }
+                    if i + 1 < count {
+                        column.space_after = 0.0;
+                    }
+                }
+                let gap = if i + 1 < count { 0.0 } else { row_spacing };
 
-            current_y += row_height + row_spacing;
+                // Rows take the larger of their columns' style spacing, none at the top of a page
+                let space_after = row.iter().map(|column| column.space_after).fold(0.0, f32::max);
+                if !current_page_paragraphs.is_empty() {
+                    current_y += row.iter().map(|column| column.space_before).fold(0.0, f32::max);
+                }
+
+                let kept = if row.iter().any(|column| column.keep_with_next) {
+                    gap + space_after + self.font_context.get_line_height(true)
+                } else {
+                    0.0
+                };
+                if !current_page_paragraphs.is_empty()
+                    && current_y + row_height <= page_bottom
+                    && current_y + row_height + kept > page_bottom
+                {
+                    let page = self.create_single_page(&current_page_paragraphs)?;
+                    pages.push(page);
+                    current_page_paragraphs.clear();
+                    current_y = content_y;
+                }
+
+                // A row too tall for the space left is broken between lines, both
+                // columns continuing at the top of the next page.
+                while current_y + row_height > page_bottom {
+                    let page_empty = current_page_paragraphs.is_empty();
+                    let breaks = self.side_by_side_row_breaks(&row, page_bottom - current_y, page_empty);
+
+                    let mut rest = Vec::new();
+                    for (mut column, lines) in row.into_iter().zip(breaks) {
+                        if lines == 0 {
+                            rest.push(column);
+                            continue;
+                        }
+                        column.y = current_y;
+                        if lines < column.lines.len() {
+                            rest.push(column.split_off(lines));
+                            if let Some(marker) = self.continued_marker(&column)? {
+                                current_page_paragraphs.push(column);
+                                current_page_paragraphs.push(marker);
+                                continue;
+                            }
+                        }
+                        current_page_paragraphs.push(column);
+                    }
+
+                    if !current_page_paragraphs.is_empty() {
+                        let page = self.create_single_page(&current_page_paragraphs)?;
+                        pages.push(page);
+                        current_page_paragraphs.clear();
+                    }
+                    current_y = content_y;
+                    row = rest;
+                    row_height = row.iter().map(|column| column.height).fold(0.0, f32::max);
+                }
+
+                for mut column in row {
+                    column.y = current_y;
+                    current_page_paragraphs.push(column);
+                }
+
+                current_y += row_height + gap + space_after;
+            }
         }
 
         if !current_page_paragraphs.is_empty() {
@@ -301,10 +689,81 @@ impl BilingualPdfGenerator {
         Ok(pages)
     }
 
+    /// Lines of each column of a side-by-side row to set in the `available`
+    /// space before a page break. The column with the least room decides what
+    /// share of the row fits, and every column breaks at that share of its lines
+    /// so the Chinese and English stay level; widow and orphan limits apply to
+    /// each column. All zeros move the row to the next page, which `page_empty`
+    /// rules out.
+    fn side_by_side_row_breaks(&self, row: &[FormattedParagraph], available: f32, page_empty: bool) -> Vec<usize> {
+        let orphans = self.font_context.orphan_lines.max(1);
+        let widows = self.font_context.widow_lines.max(1);
+        // Room for the "continued" marker under a broken column
+        let marker_lines = if self.font_context.continued_marker.is_some() { 1.0 } else { 0.0 };
+        let fits: Vec<usize> = row
+            .iter()
+            .map(|column| ((available / column.leading).floor() - marker_lines).max(0.0) as usize)
+            .collect();
+
+        let share = row
+            .iter()
+            .zip(&fits)
+            .map(|(column, &fit)| fit as f32 / column.lines.len().max(1) as f32)
+            .fold(1.0, f32::min);
+
+        let breaks: Vec<usize> = row
+            .iter()
+            .zip(&fits)
+            .map(|(column, &fit)| {
+                let total = column.lines.len();
+                let ideal = share * total as f32;
+                let lines = (ideal.round() as usize).min(fit).min(total.saturating_sub(widows));
+                if lines >= orphans {
+                    lines
+                } else if total <= fit + marker_lines as usize && ideal * 2.0 >= total as f32 {
+                    // Too short to break: kept whole on the side it mostly belongs to
+                    total
+                } else {
+                    0
+                }
+            })
+            .collect();
+
+        if page_empty && breaks.iter().all(|&lines| lines == 0) {
+            // Taller than a page: break each column wherever the page ends
+            row.iter().zip(&fits).map(|(column, &fit)| fit.max(1).min(column.lines.len())).collect()
+        } else {
+            breaks
+        }
+    }
+
+    /// The "continued" marker set flush right under a column broken by a page
+    fn continued_marker(&mut self, column: &FormattedParagraph) -> Result<Option<FormattedParagraph>> {
+        let Some((chinese, english)) = self.font_context.continued_marker.clone() else {
+            return Ok(None);
+        };
+        let text = if column.is_chinese { chinese } else { english };
+        if text.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let mut marker = self.layout_engine.layout_paragraph(
+            &text,
+            column.x,
+            column.y + column.height,
+            column.width,
+            column.is_chinese,
+        )?;
+        for line in &mut marker.lines {
+            line.x = (column.width - line.width).max(0.0);
+        }
+        Ok(Some(marker))
+    }
+
     fn layout_side_by_side_row(
         &mut self,
-        zh_text: &str,
-        en_text: &str,
+        block: &Block,
+        (zh_text, en_text): (&str, &str),
         left_x: f32,
         right_x: f32,
         row_y: f32,
@@ -313,7 +772,8 @@ impl BilingualPdfGenerator {
         let zh_para = if zh_text.trim().is_empty() {
             None
         } else {
-            Some(self.layout_engine.layout_paragraph(
+            Some(self.layout_block_paragraph(
+                block,
                 zh_text,
                 left_x,
                 row_y,
@@ -325,7 +785,8 @@ impl BilingualPdfGenerator {
         let en_para = if en_text.trim().is_empty() {
             None
         } else {
-            Some(self.layout_engine.layout_paragraph(
+            Some(self.layout_block_paragraph(
+                block,
                 en_text,
                 right_x,
                 row_y,
@@ -385,40 +846,55 @@ impl BilingualPdfGenerator {
     
     /// Add a paragraph to the page content with professional typography
     fn add_paragraph_to_content(&mut self, content: &mut Content, paragraph: &FormattedParagraph) -> Result<()> {
-        let font_name = if paragraph.is_chinese { "chinese" } else { "english" };
         let font_size = paragraph.font_size;
 
         // Clip only by column width (full page height), so text never bleeds across columns
         // while avoiding vertical clipping artifacts on glyph ascenders/descenders.
+        // Punctuation hanging past horizontal Chinese lines gets one more character.
+        let hanging = if paragraph.is_chinese && !paragraph.vertical && self.font_context.hanging_punctuation {
+            font_size
+        } else {
+            0.0
+        };
         content.operations.push(Operation::new("q", vec![]));
         content.operations.push(Operation::new("re", vec![
             Object::Real(paragraph.x),
             Object::Real(0.0),
-            Object::Real(paragraph.width),
+            Object::Real(paragraph.width + hanging),
             Object::Real(self.font_context.page_height),
         ]));
         content.operations.push(Operation::new("W", vec![]));
         content.operations.push(Operation::new("n", vec![]));
 
         content.operations.push(Operation::new("BT", vec![]));
-        content.operations.push(Operation::new("Tf", vec![
-            Object::Name(font_name.as_bytes().to_vec()),
-            Object::Real(font_size),
-        ]));
 
+        let mut rise = 0.0;
         for line in &paragraph.lines {
             // Use baseline positioning for proper leading
             let pdf_y = self.font_context.page_height - (paragraph.y + line.baseline);
 
-            content.operations.push(Operation::new("Tm", vec![
-                Object::Real(1.0), Object::Real(0.0),
-                Object::Real(0.0), Object::Real(1.0),
-                Object::Real(paragraph.x + line.x), Object::Real(pdf_y),
-            ]));
+            // One TJ per font run; the text position carries over between runs
+            let vertical = paragraph.vertical && line.is_chinese;
+            let (runs, extents) = self.build_tj_runs(line, vertical)?;
+
+            // In vertical columns Identity-V puts each glyph's vertical origin (top
+            // centre) on the column centre, and Latin lines are turned 90° clockwise
+            // and centred on the column, by the faces and sizes their runs are set in.
+            let matrix = if paragraph.vertical && !line.is_chinese {
+                let (ascent, descent) = self
+                    .run_line_metrics(&runs)
+                    .unwrap_or((font_size * 0.8, -font_size * 0.2));
+                let baseline_x = paragraph.x + line.x - (ascent + descent) / 2.0;
+                [0.0, -1.0, 1.0, 0.0, baseline_x, pdf_y]
+            } else {
+                [1.0, 0.0, 0.0, 1.0, paragraph.x + line.x, pdf_y]
+            };
+            content.operations.push(Operation::new("Tm", matrix.iter().map(|&v| Object::Real(v)).collect()));
+            Self::push_tj_runs(content, runs, &mut rise);
 
-            // Build TJ array with kerning + tracking
-            let tj = self.build_tj_array(line)?;
-            content.operations.push(Operation::new("TJ", vec![Object::Array(tj)]));
+            if !paragraph.vertical {
+                self.add_ruby_to_content(content, line, &extents, paragraph.x + line.x, pdf_y, &mut rise)?;
+            }
         }
 
         content.operations.push(Operation::new("ET", vec![]));
@@ -426,65 +902,286 @@ impl BilingualPdfGenerator {
         Ok(())
     }
 
-    /// Build TJ array with proper Chinese character handling
-    fn build_tj_array(&mut self, line: &FormattedLine) -> Result<Vec<Object>> {
-        let font = if line.is_chinese { 
-            self.font_context.chinese_font.clone() 
-        } else { 
-            self.font_context.english_font.clone() 
-        };
-        let size = line.font_size;
-        let tracking = if line.is_chinese { self.font_context.tracking_chinese } else { self.font_context.tracking_english };
-        let _scale = size / font.units_per_em() as f32;
+    /// Highest ascent and lowest descent in points of the faces a line's runs are
+    /// set in, or None if no run's face has horizontal line metrics
+    fn run_line_metrics(&self, runs: &[TextRun]) -> Option<(f32, f32)> {
+        runs.iter()
+            .filter_map(|run| {
+                let (is_chinese, slot) = FontContext::parse_resource_name(&run.font_name)?;
+                let metrics = self.font_context.font_for_slot(is_chinese, slot).horizontal_line_metrics(run.font_size)?;
+                Some((metrics.ascent, metrics.descent))
+            })
+            .reduce(|(ascent, descent), (a, d)| (ascent.max(a), descent.min(d)))
+    }
+
+    /// Write the Tf, Ts and TJ operators of a line's font runs
+    fn push_tj_runs(content: &mut Content, runs: Vec<TextRun>, rise: &mut f32) {
+        for run in runs {
+            content.operations.push(Operation::new("Tf", vec![
+                Object::Name(run.font_name.into_bytes()),
+                Object::Real(run.font_size),
+            ]));
+            if run.rise != *rise {
+                content.operations.push(Operation::new("Ts", vec![Object::Real(run.rise)]));
+                *rise = run.rise;
+            }
+            match run.actual_text {
+                Some(text) => {
+                    let mut properties = Dictionary::new();
+                    properties.set("ActualText", Object::String(utf16be_with_bom(&text), StringFormat::Hexadecimal));
+                    content.operations.push(Operation::new("BDC", vec![
+                        Object::Name(b"Span".to_vec()),
+                        Object::Dictionary(properties),
+                    ]));
+                    content.operations.push(Operation::new("TJ", vec![Object::Array(run.tj)]));
+                    content.operations.push(Operation::new("EMC", vec![]));
+                }
+                None => content.operations.push(Operation::new("TJ", vec![Object::Array(run.tj)])),
+            }
+        }
+    }
+
+    /// Set the ruby annotations of a horizontal line, each centred over the glyphs
+    /// of its base and sitting on the top of the ideographic em box (0.88 em above
+    /// the baseline in CJK fonts), clear of its own descenders.
+    fn add_ruby_to_content(
+        &mut self,
+        content: &mut Content,
+        line: &FormattedLine,
+        extents: &GlyphExtents,
+        line_x: f32,
+        line_y: f32,
+        rise: &mut f32,
+    ) -> Result<()> {
+        for annotation in &line.ruby {
+            let base = extents
+                .iter()
+                .filter(|(position, _, _)| (annotation.start..annotation.end).contains(position))
+                .fold(None, |span: Option<(f32, f32)>, &(_, start, end)| match span {
+                    Some((left, right)) => Some((left.min(start), right.max(end))),
+                    None => Some((start, end)),
+                });
+            let Some((left, right)) = base else {
+                continue;
+            };
+            let size = annotation.runs.first().map_or(line.font_size / 2.0, |run| run.font_size);
+            let x = line_x + (left + right - annotation.width) / 2.0;
+            let y = line_y + line.font_size * 0.88 + size * 0.2;
+
+            let ruby_line = FormattedLine {
+                text: annotation.text.clone(),
+                x: 0.0,
+                y: 0.0,
+                width: annotation.width,
+                height: size,
+                is_chinese: false,
+                font_size: size,
+                baseline: 0.0,
+                indent: 0.0,
+                tokens: Vec::new(),
+                space_adjustments: Vec::new(),
+                is_justified: false,
+                hyphenated: false,
+                runs: annotation.runs.clone(),
+                ruby: Vec::new(),
+            };
+            content.operations.push(Operation::new("Tm", [1.0, 0.0, 0.0, 1.0, x, y].iter().map(|&v| Object::Real(v)).collect()));
+            let (runs, _) = self.build_tj_runs(&ruby_line, false)?;
+            Self::push_tj_runs(content, runs, rise);
+        }
+        Ok(())
+    }
 
+    /// Build TJ arrays from the shaped glyphs of each font run: the line's script
+    /// runs, each split by the first font in its fallback chain that covers each
+    /// character. The shaped advances are made exact against the `/W` widths the
+    /// viewer applies, then tracking, justification and CJK–Latin spacing are
+    /// added between glyphs.
+    ///
+    /// `vertical` lines use the Identity-V resources and are shaped top to bottom.
+    /// Returns the runs in drawing order and where each glyph is drawn.
+    fn build_tj_runs(&mut self, line: &FormattedLine, vertical: bool) -> Result<(Vec<TextRun>, GlyphExtents)> {
         let chars: Vec<char> = line.text.chars().collect();
-        let mut tj = Vec::with_capacity(chars.len() * 2);
 
-        // Create a map of space positions to adjustments
+        // Extra advance (in points) after the character at each index in the line
         let mut space_adjustments_map = std::collections::HashMap::new();
         for adj in &line.space_adjustments {
-            space_adjustments_map.insert(adj.position, adj.adjustment_ratio);
+            *space_adjustments_map.entry(adj.position).or_insert(0.0) += adj.adjusted_width - adj.base_width;
         }
 
-        let mut char_position = 0;
-        for (i, &ch) in chars.iter().enumerate() {
-            // Use UTF-16BE hex strings for both Chinese and English Type0 fonts.
-            let mut utf16be = Vec::new();
-            for unit in ch.encode_utf16(&mut [0; 2]).iter().copied() {
-                utf16be.extend_from_slice(&unit.to_be_bytes());
+        // Font runs within each script run
+        let mut font_runs: Vec<ShapedRun> = Vec::new();
+        for script_run in &line.runs {
+            let run_text: String = chars[script_run.start..script_run.end].iter().collect();
+            let mut char_offset = script_run.start;
+            for (slot, text) in self.font_context.split_font_runs(&run_text, script_run.is_chinese) {
+                let glyphs = self.font_context.shape_run(&text, script_run.is_chinese, slot, vertical);
+                let length = text.chars().count();
+                font_runs.push((script_run.is_chinese, script_run.font_size, slot, text, glyphs, char_offset));
+                char_offset += length;
             }
-            tj.push(Object::String(utf16be, StringFormat::Hexadecimal));
-
-            if i < chars.len() - 1 {
-                let mut adjust: f32 = 0.0;
+        }
+        let glyph_total: usize = font_runs.iter().map(|run| run.4.len()).sum();
+
+        // Character index each glyph's cluster starts at, and the grapheme clusters of
+        // the line: spacing goes between clusters, never between a letter and its marks.
+        let positions: Vec<usize> = font_runs
+            .iter()
+            .flat_map(|(_, _, _, text, glyphs, char_offset)| {
+                glyphs.iter().map(move |glyph| char_offset + text[..glyph.cluster].chars().count())
+            })
+            .collect();
+        let mut cluster_starts = vec![false; chars.len() + 1];
+        let mut cluster_bases = vec![0; chars.len()];
+        let mut char_index = 0;
+        for cluster in syllables::clusters(&line.text) {
+            cluster_starts[char_index] = true;
+            let length = cluster.chars().count();
+            cluster_bases[char_index..char_index + length].fill(char_index);
+            char_index += length;
+        }
+        cluster_starts[chars.len()] = true;
+
+        let mut runs: Vec<TextRun> = Vec::new();
+        let mut extents: GlyphExtents = Vec::with_capacity(glyph_total);
+        // Pen position along the line in points, as the viewer applies the TJ arrays
+        let mut pen = 0.0;
+        let mut glyph_index = 0;
+        let mut size = font_runs.first().map(|run| run.1).unwrap_or(line.font_size);
+        // Adjustment in 1/1000 em along the line, written before the next glyph
+        let mut pending: f32 = line.indent * 1000.0 / size;
+        for (is_chinese, font_size, slot, text, glyphs, _) in font_runs {
+            // The pending adjustment is written in the new run's size.
+            if font_size != size {
+                pending *= size / font_size;
+                size = font_size;
+            }
+            let tracking = if is_chinese { self.font_context.tracking_chinese } else { self.font_context.tracking_english };
+            let font_name = if vertical {
+                FontContext::vertical_resource_name(is_chinese, slot)
+            } else {
+                FontContext::resource_name(is_chinese, slot)
+            };
+            if !self.font_objects.contains_key(&font_name) {
+                self.reserve_font_object(&font_name);
+            }
+            if !self.font_usage.contains_key(&font_name) {
+                let usage = self.new_font_usage(is_chinese, slot);
+                self.font_usage.insert(font_name.clone(), usage);
+            }
+            let units_per_em = self.font_context.font_for_slot(is_chinese, slot).units_per_em();
+            let to_pdf = 1000.0 / units_per_em;
 
-                // Kerning
-                if let Some(kern) = font.horizontal_kern(ch, chars[i + 1], size) {
-                    adjust += kern * 1000.0 / font.units_per_em() as f32;
+            for (glyph, glyph_text) in glyphs.iter().zip(shaping::cluster_texts(&text, &glyphs)) {
+                let mut gid = glyph.glyph_id;
+                if vertical {
+                    gid = self.vertical_form_glyph(is_chinese, slot, glyph_text, gid);
                 }
 
-                // Tracking
-                adjust += tracking;
+                // Identity-H/V with CID == glyph ID; the usage records the text of
+                // each glyph's cluster for the ToUnicode CMap.
+                let code = self
+                    .font_usage
+                    .get_mut(&font_name)
+                    .expect("usage inserted above")
+                    .record_glyph(gid, glyph_text);
+
+                // Offsets of vertical glyphs only move them to their vertical origin,
+                // which Identity-V does through /W2. Horizontal marks are raised with Ts.
+                let (x_offset, rise) = if vertical {
+                    (0.0, 0.0)
+                } else {
+                    (glyph.x_offset as f32 * to_pdf, glyph.y_offset as f32 * size / units_per_em)
+                };
+
+                // Glyphs whose text cannot be carried by the CMap get a run of their own.
+                let continues_run = matches!(
+                    runs.last(),
+                    Some(run) if run.font_name == font_name
+                        && run.font_size == font_size
+                        && run.rise == rise
+                        && run.actual_text.is_none()
+                        && code.actual_text.is_none()
+                );
+                if !continues_run {
+                    runs.push(TextRun {
+                        font_name: font_name.clone(),
+                        font_size,
+                        tj: Vec::with_capacity(glyphs.len() * 2),
+                        actual_text: code.actual_text,
+                        rise,
+                    });
+                }
+                let tj = &mut runs.last_mut().expect("run started above").tj;
 
-                // Apply space adjustment if this is a space character
-                if ch == ' ' {
-                    if let Some(&ratio) = space_adjustments_map.get(&char_position) {
-                        // Convert adjustment ratio to thousandths of em
-                        let space_width = self.font_context.calculate_text_width(" ", line.is_chinese);
-                        let extra_space = space_width * ratio;
-                        adjust += extra_space * 1000.0 / size;
+                // TJ numbers move the next glyph left (horizontal) or down (vertical),
+                // so horizontal adjustments are written negated.
+                pending += x_offset;
+                if pending != 0.0 {
+                    tj.push(Object::Real(if vertical { pending } else { -pending }));
+                }
+                tj.push(Object::String(code.cid.to_be_bytes().to_vec(), StringFormat::Hexadecimal));
+
+                // Difference between the shaped advance and the one the viewer applies
+                let shaped_advance = if vertical { -glyph.y_advance } else { glyph.x_advance } as f32 * to_pdf;
+                let pdf_advance = self.font_context.pdf_advance(is_chinese, slot, gid, vertical);
+                let start = pen + (pending - x_offset) * font_size / 1000.0;
+                extents.push((positions[glyph_index], start, start + shaped_advance * font_size / 1000.0));
+                pen += (pending + pdf_advance) * font_size / 1000.0;
+                pending = shaped_advance - pdf_advance - x_offset;
+
+                glyph_index += 1;
+                let (here, next) = (positions[glyph_index - 1], positions.get(glyph_index).copied().unwrap_or(chars.len()));
+                if glyph_index < glyph_total && next > here {
+                    // Tracking, once for each cluster boundary crossed (a ligature covers several)
+                    let clusters = (here + 1..=next).filter(|&i| cluster_starts[i]).count();
+                    pending += tracking * clusters as f32;
+
+                    // Justified spaces, squeezed punctuation; convert points to thousandths of em
+                    let extra: f32 = (here..next).filter_map(|i| space_adjustments_map.get(&i)).sum();
+                    pending += extra * 1000.0 / size;
+
+                    // CJK–Latin spacing between the clusters' base characters
+                    if clusters > 0 {
+                        let before = chars[cluster_bases[next - 1]];
+                        pending += self.font_context.script_gap(before, chars[next]) * 1000.0 / size;
                     }
                 }
-
-                tj.push(Object::Real(adjust));
             }
+        }
+        Ok((runs, extents))
+    }
 
-            // Update character position for space tracking
-            if ch != ' ' {
-                char_position += 1;
-            }
+    /// Glyph drawn in vertical text when the shaper found no `vert` substitute:
+    /// the Unicode vertical presentation form if the font has one, else `gid` itself.
+    fn vertical_form_glyph(&self, is_chinese: bool, slot: usize, text: &str, gid: u16) -> u16 {
+        let font = self.font_context.font_for_slot(is_chinese, slot);
+        let mut chars = text.chars();
+        let (Some(ch), None) = (chars.next(), chars.next()) else {
+            return gid;
+        };
+        if font.lookup_glyph_index(ch) != gid {
+            return gid;
+        }
+        match vertical_presentation_form(ch).map(|form| font.lookup_glyph_index(form)) {
+            Some(form_gid) if form_gid != 0 => form_gid,
+            _ => gid,
+        }
+    }
+
+    /// Empty usage for a font resource; TrueType outlines go through a CIDToGIDMap,
+    /// so glyphs shared by several characters can get extra CIDs.
+    fn new_font_usage(&self, is_chinese: bool, slot: usize) -> FontUsage {
+        let Some(face) = self.font_context.face(is_chinese, slot) else {
+            return FontUsage::default();
+        };
+        if font_embedding::is_cff_font(face.data, face.face_index) {
+            return FontUsage::default();
+        }
+        match font_embedding::glyph_count(face.data, face.face_index) {
+            Some(num_glyphs) => FontUsage::with_cid_aliases(num_glyphs),
+            None => FontUsage::default(),
         }
-        Ok(tj)
     }
 
     /// Create resources dictionary for fonts
@@ -500,51 +1197,100 @@ impl BilingualPdfGenerator {
         Ok(Object::Dictionary(resources))
     }
     
+    /// Reserve an object ID for a font resource so pages can reference it
+    /// before the font dictionary is written.
+    fn reserve_font_object(&mut self, name: &str) -> ObjectId {
+        let font_id = self.document.new_object_id();
+        self.font_objects.insert(name.to_string(), font_id);
+        font_id
+    }
+
+    /// Write every reserved font once all pages have recorded their glyph usage.
+    fn write_font_objects(&mut self) -> Result<()> {
+        let mut names: Vec<String> = self.font_objects.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            self.add_font_to_document(&name)?;
+        }
+        Ok(())
+    }
+
     /// Add font to document with composite Type0 + CIDFont support.
     fn add_font_to_document(&mut self, name: &str) -> Result<ObjectId> {
-        let (base_font_name, font_path, font_data, is_chinese_font) = if name == "chinese" {
-            (
-                self.chinese_pdf_font_name(),
-                self.font_context.chinese_font_path.clone(),
-                self.font_context.chinese_font_data.clone(),
-                true,
-            )
-        } else {
-            (
-                self.english_pdf_font_name(),
-                self.font_context.english_font_path.clone(),
-                self.font_context.english_font_data.clone(),
-                false,
-            )
+        let font_id = *self
+            .font_objects
+            .get(name)
+            .ok_or_else(|| anyhow!("Font resource '{}' was never reserved", name))?;
+        let usage = self.font_usage.get(name).cloned().unwrap_or_default();
+
+        let (is_chinese_font, slot) = FontContext::parse_resource_name(name)
+            .ok_or_else(|| anyhow!("Unknown font resource '{}'", name))?;
+        let (base_font_name, font_path, font_data, face_index) = {
+            let face = self
+                .font_context
+                .face(is_chinese_font, slot)
+                .ok_or_else(|| anyhow!("No font in slot {} for resource '{}'", slot, name))?;
+            let base_font_name = match (slot, is_chinese_font) {
+                (0, true) => self.chinese_pdf_font_name(),
+                (0, false) => self.english_pdf_font_name(),
+                _ => self.sanitize_pdf_font_name(face.name),
+            };
+            (base_font_name, face.path.to_string(), face.data.to_vec(), face.face_index)
         };
 
+        let glyphs = usage.glyph_ids();
+        let embedded = self.create_embeddable_font_stream(&font_path, &font_data, face_index, &glyphs);
+        let base_font_name = match &embedded {
+            Some((_, _, true)) => format!("{}+{}", font_embedding::subset_tag(&glyphs), base_font_name),
+            _ => base_font_name,
+        };
+
+        let metrics = font_embedding::font_metrics(&font_data, face_index).ok();
         let mut font_descriptor = Dictionary::new();
         font_descriptor.set("Type", Object::Name(b"FontDescriptor".to_vec()));
         font_descriptor.set("FontName", Object::Name(base_font_name.clone().into_bytes()));
-        font_descriptor.set("Flags", Object::Integer(if is_chinese_font { 4 } else { 32 }));
-        font_descriptor.set("FontBBox", Object::Array(vec![
-            Object::Integer(-200),
-            Object::Integer(-300),
-            Object::Integer(1400),
-            Object::Integer(1100),
-        ]));
-        font_descriptor.set("ItalicAngle", Object::Integer(0));
-        font_descriptor.set("Ascent", Object::Integer(880));
-        font_descriptor.set("Descent", Object::Integer(-220));
-        font_descriptor.set("CapHeight", Object::Integer(700));
-        font_descriptor.set("StemV", Object::Integer(80));
-
-        let mut is_embedded = false;
-        if let Some((font_file_key, font_file_obj)) = self.create_embeddable_font_stream(&font_path, &font_data) {
+        match &metrics {
+            Some(metrics) => {
+                font_descriptor.set("Flags", Object::Integer(metrics.flags(is_chinese_font)));
+                font_descriptor.set("FontBBox", Object::Array(
+                    metrics.bbox.iter().map(|&v| Object::Integer(v)).collect(),
+                ));
+                font_descriptor.set("ItalicAngle", Object::Real(metrics.italic_angle));
+                font_descriptor.set("Ascent", Object::Integer(metrics.ascent));
+                font_descriptor.set("Descent", Object::Integer(metrics.descent));
+                font_descriptor.set("CapHeight", Object::Integer(metrics.cap_height));
+                font_descriptor.set("StemV", Object::Integer(metrics.stem_v));
+            }
+            None => {
+                eprintln!("⚠️ Could not read metrics for {}, using generic FontDescriptor values", font_path);
+                font_descriptor.set("Flags", Object::Integer(if is_chinese_font { 4 } else { 32 }));
+                font_descriptor.set("FontBBox", Object::Array(vec![
+                    Object::Integer(-200),
+                    Object::Integer(-300),
+                    Object::Integer(1400),
+                    Object::Integer(1100),
+                ]));
+                font_descriptor.set("ItalicAngle", Object::Integer(0));
+                font_descriptor.set("Ascent", Object::Integer(880));
+                font_descriptor.set("Descent", Object::Integer(-220));
+                font_descriptor.set("CapHeight", Object::Integer(700));
+                font_descriptor.set("StemV", Object::Integer(80));
+            }
+        }
+
+        let is_cff = font_embedding::is_cff_font(&font_data, face_index);
+        if let Some((font_file_key, font_file_obj, _)) = embedded {
             let font_stream_id = self.document.add_object(font_file_obj);
             font_descriptor.set(font_file_key.as_str(), Object::Reference(font_stream_id));
-            is_embedded = true;
         }
         let font_descriptor_id = self.document.add_object(Object::Dictionary(font_descriptor));
 
+        // CFF outlines are CIDFontType0 (CIDs select glyphs directly); TrueType outlines
+        // are CIDFontType2 and go through CIDToGIDMap, which also resolves alias CIDs.
+        let cid_font_subtype: &[u8] = if is_cff { b"CIDFontType0" } else { b"CIDFontType2" };
         let mut cidfont = Dictionary::new();
         cidfont.set("Type", Object::Name(b"Font".to_vec()));
-        cidfont.set("Subtype", Object::Name(b"CIDFontType2".to_vec()));
+        cidfont.set("Subtype", Object::Name(cid_font_subtype.to_vec()));
         cidfont.set("BaseFont", Object::Name(base_font_name.clone().into_bytes()));
         cidfont.set("CIDSystemInfo", Object::Dictionary({
             let mut d = Dictionary::new();
@@ -554,34 +1300,54 @@ impl BilingualPdfGenerator {
             d
         }));
         cidfont.set("FontDescriptor", Object::Reference(font_descriptor_id));
-        cidfont.set("DW", Object::Integer(1000));
-        if is_embedded {
-            let cid_to_gid_map_id = if is_chinese_font {
-                self.document.add_object(self.create_chinese_cid_to_gid_map_stream())
-            } else {
-                self.document.add_object(self.create_english_cid_to_gid_map_stream())
-            };
+        let widths = self.glyph_widths(name, &font_data, face_index, &usage);
+        let default_width = most_common_width(&widths);
+        cidfont.set("DW", Object::Integer(default_width));
+        cidfont.set("W", Object::Array(compact_width_array(&widths, default_width)));
+        if !is_cff {
+            let cid_to_gid_map_id = self.document.add_object(self.create_cid_to_gid_map_stream(&usage));
             cidfont.set("CIDToGIDMap", Object::Reference(cid_to_gid_map_id));
-        } else {
-            cidfont.set("CIDToGIDMap", Object::Name(b"Identity".to_vec()));
+        }
+        let vertical = FontContext::is_vertical_resource(name);
+        if vertical {
+            let vertical_metrics = font_embedding::vertical_metrics(&font_data, face_index, &glyphs).unwrap_or_default();
+            let metrics: BTreeMap<u16, VerticalMetrics> = usage
+                .cids
+                .iter()
+                .map(|(&cid, (gid, _))| {
+                    let default = VerticalMetrics { advance: 1000, origin_y: 880 };
+                    (cid, vertical_metrics.get(gid).copied().unwrap_or(default))
+                })
+                .collect();
+            let default_metrics = most_common_vertical_metrics(&metrics);
+            cidfont.set("DW2", Object::Array(vec![
+                Object::Integer(default_metrics.origin_y),
+                Object::Integer(-default_metrics.advance),
+            ]));
+            cidfont.set("W2", Object::Array(vertical_metrics_array(&metrics, &widths, default_metrics)));
         }
         let cidfont_id = self.document.add_object(Object::Dictionary(cidfont));
 
-        let tounicode_id = self.document.add_object(self.create_identity_tounicode_cmap_stream());
+        let tounicode_id = self.document.add_object(self.create_tounicode_cmap_stream(&usage));
 
         let mut type0 = Dictionary::new();
         type0.set("Type", Object::Name(b"Font".to_vec()));
         type0.set("Subtype", Object::Name(b"Type0".to_vec()));
         type0.set("BaseFont", Object::Name(base_font_name.into_bytes()));
-        type0.set("Encoding", Object::Name(b"Identity-H".to_vec()));
+        let encoding: &[u8] = if vertical { b"Identity-V" } else { b"Identity-H" };
+        type0.set("Encoding", Object::Name(encoding.to_vec()));
         type0.set("DescendantFonts", Object::Array(vec![Object::Reference(cidfont_id)]));
         type0.set("ToUnicode", Object::Reference(tounicode_id));
 
-        let font_id = self.document.add_object(Object::Dictionary(type0));
-        self.font_objects.insert(name.to_string(), font_id);
+        self.document.objects.insert(font_id, Object::Dictionary(type0));
         Ok(font_id)
     }
 
+    fn font_for_resource(&self, name: &str) -> &fontdue::Font {
+        let (is_chinese, slot) = FontContext::parse_resource_name(name).unwrap_or((false, 0));
+        self.font_context.font_for_slot(is_chinese, slot)
+    }
+
     fn sanitize_pdf_font_name(&self, raw: &str) -> String {
         let mut out = String::with_capacity(raw.len());
         for ch in raw.chars() {
@@ -620,8 +1386,9 @@ impl BilingualPdfGenerator {
         }
     }
 
-    fn create_identity_tounicode_cmap_stream(&self) -> Object {
-        let cmap = b"/CIDInit /ProcSet findresource begin
+    /// ToUnicode CMap mapping each CID back to the text it was drawn for.
+    fn create_tounicode_cmap_stream(&self, usage: &FontUsage) -> Object {
+        let mut cmap = String::from("/CIDInit /ProcSet findresource begin
 12 dict begin
 begincmap
 /CIDSystemInfo
@@ -634,71 +1401,124 @@ begincmap
 1 begincodespacerange
 <0000> <FFFF>
 endcodespacerange
-1 beginbfrange
-<0000> <FFFF> <0000>
-endbfrange
-endcmap
+");
+        let entries: Vec<(u16, &str)> = usage
+            .cids
+            .iter()
+            .filter(|(_, (_, text))| !text.is_empty())
+            .map(|(&cid, (_, text))| (cid, text.as_str()))
+            .collect();
+        // bfchar sections are limited to 100 entries each.
+        for chunk in entries.chunks(100) {
+            cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+            for (cid, text) in chunk {
+                // Destination is UTF-16BE: supplementary-plane text becomes a surrogate
+                // pair and ligatures map to all of their characters.
+                let target: String = text.encode_utf16().map(|unit| format!("{:04X}", unit)).collect();
+                cmap.push_str(&format!("<{:04X}> <{}>\n", cid, target));
+            }
+            cmap.push_str("endbfchar\n");
+        }
+        cmap.push_str("endcmap
 CMapName currentdict /CMap defineresource pop
 end
-end"
-        .to_vec();
-        Object::Stream(Stream::new(Dictionary::new(), cmap))
+end");
+        Object::Stream(Stream::new(Dictionary::new(), cmap.into_bytes()))
     }
 
-    fn create_chinese_cid_to_gid_map_stream(&self) -> Object {
-        // Build a full BMP CID -> glyph index map (2 bytes per CID).
-        // CID code equals UTF-16 BMP code unit in our content stream.
-        let mut map = vec![0u8; 65536 * 2];
-        for cid in 0u32..=0xFFFF {
-            if let Some(ch) = char::from_u32(cid) {
-                let gid = self.font_context.chinese_font.lookup_glyph_index(ch);
-                let offset = (cid as usize) * 2;
-                map[offset] = (gid >> 8) as u8;
-                map[offset + 1] = (gid & 0xFF) as u8;
-            }
-        }
-        Object::Stream(Stream::new(Dictionary::new(), map))
+    /// Advance widths (1/1000 em) per CID for the glyphs actually shown, read from `hmtx`.
+    fn glyph_widths(&self, name: &str, font_data: &[u8], face_index: u32, usage: &FontUsage) -> BTreeMap<u16, i64> {
+        let glyphs = usage.glyph_ids();
+        let advances = font_embedding::glyph_advances(font_data, face_index, &glyphs).unwrap_or_else(|_| {
+            let font = self.font_for_resource(name);
+            glyphs
+                .iter()
+                .map(|&gid| (gid, font.metrics_indexed(gid, 1000.0).advance_width.round() as i64))
+                .collect()
+        });
+        usage
+            .cids
+            .iter()
+            .map(|(&cid, (gid, _))| (cid, advances.get(gid).copied().unwrap_or(0)))
+            .collect()
     }
 
-    fn create_english_cid_to_gid_map_stream(&self) -> Object {
-        let mut map = vec![0u8; 65536 * 2];
-        for cid in 0u32..=0xFFFF {
-            if let Some(ch) = char::from_u32(cid) {
-                let gid = self.font_context.english_font.lookup_glyph_index(ch);
-                let offset = (cid as usize) * 2;
-                map[offset] = (gid >> 8) as u8;
-                map[offset + 1] = (gid & 0xFF) as u8;
-            }
+    /// CID -> glyph index map built from the glyphs actually shown (2 bytes per CID).
+    fn create_cid_to_gid_map_stream(&self, usage: &FontUsage) -> Object {
+        // Unused CIDs map to .notdef so stripped glyphs are never referenced.
+        let max_cid = usage.cids.keys().copied().max().unwrap_or(0) as usize;
+        let mut map = vec![0u8; (max_cid + 1) * 2];
+        for (&cid, (gid, _)) in &usage.cids {
+            let offset = (cid as usize) * 2;
+            map[offset..offset + 2].copy_from_slice(&gid.to_be_bytes());
         }
         Object::Stream(Stream::new(Dictionary::new(), map))
     }
 
-    fn create_embeddable_font_stream(&self, font_path: &str, font_data: &[u8]) -> Option<(String, Object)> {
+    /// Build the font program stream, subset to `glyphs` when the font's fsType allows it.
+    ///
+    /// Faces of .ttc/.otc collections are written out as standalone fonts.
+    /// Returns the FontDescriptor key, the stream object and whether the program is a subset.
+    fn create_embeddable_font_stream(
+        &self,
+        font_path: &str,
+        font_data: &[u8],
+        face_index: u32,
+        glyphs: &[u16],
+    ) -> Option<(String, Object, bool)> {
         if font_data.is_empty() {
             return None;
         }
 
-        let path = font_path.to_ascii_lowercase();
-        let mut stream_dict = Dictionary::new();
-        stream_dict.set("Length1", Object::Integer(font_data.len() as i64));
+        let permission = match font_embedding::embedding_permission(font_data, face_index) {
+            Ok(permission) => permission,
+            Err(e) => {
+                eprintln!("Cannot read embedding permissions of {}: {}", font_path, e);
+                return None;
+            }
+        };
 
-        if path.ends_with(".ttf") {
-            return Some((
-                "FontFile2".to_string(),
-                Object::Stream(Stream::new(stream_dict, font_data.to_vec())),
-            ));
-        }
+        let full_face = || match font_embedding::extract_collection_face(font_data, face_index) {
+            Ok(face) => Some((face, false)),
+            Err(e) => {
+                eprintln!("Cannot extract face {} of {}: {}", face_index, font_path, e);
+                None
+            }
+        };
 
-        if path.ends_with(".otf") {
+        let (program, is_subset) = match permission {
+            EmbeddingPermission::Forbidden => {
+                eprintln!("Font {} does not permit embedding (fsType); referencing it only", font_path);
+                return None;
+            }
+            EmbeddingPermission::FullOnly => full_face()?,
+            EmbeddingPermission::Subset => {
+                match font_embedding::subset_font_program(font_data, face_index, glyphs) {
+                    Ok(subset) => (subset, true),
+                    Err(e) => {
+                        eprintln!("Subsetting {} failed, embedding the full face: {}", font_path, e);
+                        full_face()?
+                    }
+                }
+            }
+        };
+
+        let mut stream_dict = Dictionary::new();
+        if font_embedding::is_cff_font(font_data, face_index) {
             stream_dict.set("Subtype", Object::Name(b"OpenType".to_vec()));
             return Some((
                 "FontFile3".to_string(),
-                Object::Stream(Stream::new(stream_dict, font_data.to_vec())),
+                Object::Stream(Stream::new(stream_dict, program)),
+                is_subset,
             ));
         }
 
-        // TTC collections are not embedded yet in this pipeline.
-        None
+        stream_dict.set("Length1", Object::Integer(program.len() as i64));
+        Some((
+            "FontFile2".to_string(),
+            Object::Stream(Stream::new(stream_dict, program)),
+            is_subset,
+        ))
     }
     
     /// Add page to pages tree
@@ -741,6 +1561,9 @@ end"
     
     /// Save the document to file
     fn save_document(&mut self, output_path: &str) -> Result<()> {
+        // Fonts are written last so they can be subset to the glyphs the pages used
+        self.write_font_objects()?;
+
         // Ensure the document is properly structured before saving
         self.document.compress();
         self.document.save(output_path)?;
@@ -759,6 +1582,167 @@ end"
     }
 }
 
+/// PDF text string in UTF-16BE with byte order mark
+fn utf16be_with_bom(text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xFE, 0xFF];
+    for unit in text.encode_utf16() {
+        bytes.extend_from_slice(&unit.to_be_bytes());
+    }
+    bytes
+}
+
+/// Width shared by the most glyphs, used as the CIDFont `/DW`
+fn most_common_width(widths: &BTreeMap<u16, i64>) -> i64 {
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for &width in widths.values() {
+        *counts.entry(width).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(width, count)| (count, std::cmp::Reverse(width)))
+        .map(|(width, _)| width)
+        .unwrap_or(1000)
+}
+
+/// Compact `/W` array: glyphs equal to `/DW` are omitted, runs of three or more
+/// consecutive glyphs with one width become `cfirst clast w`, and other
+/// consecutive glyphs are grouped as `cfirst [w1 w2 ...]`.
+fn compact_width_array(widths: &BTreeMap<u16, i64>, default_width: i64) -> Vec<Object> {
+    let entries: Vec<(u16, i64)> = widths
+        .iter()
+        .filter(|&(_, &width)| width != default_width)
+        .map(|(&gid, &width)| (gid, width))
+        .collect();
+
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < entries.len() {
+        // Extent of the run of consecutive glyph IDs starting at i
+        let mut end = i + 1;
+        while end < entries.len() && entries[end].0 == entries[end - 1].0 + 1 {
+            end += 1;
+        }
+
+        let mut group: Vec<Object> = Vec::new();
+        let mut group_start = entries[i].0;
+        let mut j = i;
+        while j < end {
+            let mut same = j + 1;
+            while same < end && entries[same].1 == entries[j].1 {
+                same += 1;
+            }
+            if same - j >= 3 {
+                if !group.is_empty() {
+                    out.push(Object::Integer(group_start as i64));
+                    out.push(Object::Array(std::mem::take(&mut group)));
+                }
+                out.push(Object::Integer(entries[j].0 as i64));
+                out.push(Object::Integer(entries[same - 1].0 as i64));
+                out.push(Object::Integer(entries[j].1));
+                j = same;
+                if j < end {
+                    group_start = entries[j].0;
+                }
+            } else {
+                if group.is_empty() {
+                    group_start = entries[j].0;
+                }
+                group.extend(entries[j..same].iter().map(|&(_, width)| Object::Integer(width)));
+                j = same;
+            }
+        }
+        if !group.is_empty() {
+            out.push(Object::Integer(group_start as i64));
+            out.push(Object::Array(group));
+        }
+        i = end;
+    }
+    out
+}
+
+/// Vertical metrics shared by the most glyphs, used as the CIDFont `/DW2`
+fn most_common_vertical_metrics(metrics: &BTreeMap<u16, VerticalMetrics>) -> VerticalMetrics {
+    let mut counts: HashMap<VerticalMetrics, usize> = HashMap::new();
+    for &entry in metrics.values() {
+        *counts.entry(entry).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(entry, count)| (count, std::cmp::Reverse((entry.advance, entry.origin_y))))
+        .map(|(entry, _)| entry)
+        .unwrap_or(VerticalMetrics { advance: 1000, origin_y: 880 })
+}
+
+/// `/W2` array for glyphs whose vertical metrics differ from `/DW2`, grouped by
+/// consecutive CIDs as `cfirst [w1y vx vy ...]`. The origin sits at half the
+/// horizontal width, as it does for glyphs covered by `/DW2`.
+fn vertical_metrics_array(
+    metrics: &BTreeMap<u16, VerticalMetrics>,
+    widths: &BTreeMap<u16, i64>,
+    default_metrics: VerticalMetrics,
+) -> Vec<Object> {
+    let mut out = Vec::new();
+    let mut group: Vec<Object> = Vec::new();
+    let mut group_start = 0u16;
+    let mut previous: Option<u16> = None;
+    for (&cid, &entry) in metrics.iter().filter(|&(_, &entry)| entry != default_metrics) {
+        if previous.is_none_or(|p| p + 1 != cid) && !group.is_empty() {
+            out.push(Object::Integer(group_start as i64));
+            out.push(Object::Array(std::mem::take(&mut group)));
+        }
+        if group.is_empty() {
+            group_start = cid;
+        }
+        let width = widths.get(&cid).copied().unwrap_or(1000);
+        group.push(Object::Integer(-entry.advance));
+        group.push(Object::Integer(width / 2));
+        group.push(Object::Integer(entry.origin_y));
+        previous = Some(cid);
+    }
+    if !group.is_empty() {
+        out.push(Object::Integer(group_start as i64));
+        out.push(Object::Array(group));
+    }
+    out
+}
+
+/// Unicode vertical presentation form (U+FE10–FE19, U+FE30–FE4F) of CJK punctuation,
+/// used when a font has the forms but no `vert` lookup
+fn vertical_presentation_form(ch: char) -> Option<char> {
+    Some(match ch {
+        '，' => '︐',
+        '、' => '︑',
+        '。' => '︒',
+        '：' => '︓',
+        '；' => '︔',
+        '！' => '︕',
+        '？' => '︖',
+        '〖' => '︗',
+        '〗' => '︘',
+        '…' => '︙',
+        '‥' => '︰',
+        '—' => '︱',
+        '–' => '︲',
+        '（' => '︵',
+        '）' => '︶',
+        '｛' => '︷',
+        '｝' => '︸',
+        '〔' => '︹',
+        '〕' => '︺',
+        '【' => '︻',
+        '】' => '︼',
+        '《' => '︽',
+        '》' => '︾',
+        '〈' => '︿',
+        '〉' => '﹀',
+        '「' => '﹁',
+        '」' => '﹂',
+        '『' => '﹃',
+        '』' => '﹄',
+        _ => return None,
+    })
+}
+
 /// Create a bilingual PDF generator
 pub fn create_bilingual_generator(font_context: FontContext) -> BilingualPdfGenerator {
     BilingualPdfGenerator::new(font_context)
@@ -788,6 +1772,17 @@ pub fn create_bilingual_pdf_with_context(
     generator.generate_bilingual_pdf(chinese_sections, english_sections, output_path)
 }
 
+/// Create a vertical (top-to-bottom, right-to-left) PDF with custom font context.
+pub fn create_vertical_pdf_with_context(
+    chinese_sections: &[String],
+    english_sections: &[String],
+    output_path: &str,
+    font_context: &crate::fonts::FontContext,
+) -> Result<()> {
+    let mut generator = create_bilingual_generator(font_context.clone());
+    generator.generate_vertical_pdf(chinese_sections, english_sections, output_path)
+}
+
 /// Create bilingual PDF with side-by-side columns and custom font context.
 pub fn create_bilingual_pdf_side_by_side_with_context(
     chinese_sections: &[String],
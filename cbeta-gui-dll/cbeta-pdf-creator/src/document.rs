@@ -0,0 +1,254 @@
+//! Structured documents
+//!
+//! A document is a list of typed blocks: headings, Chinese/English pairs,
+//! verse, notes, page breaks and metadata. Every layout mode sets the same
+//! blocks; parallel Chinese and English sections become one pair per section.
+//! Headings, verse and notes are set in the paragraph styles `heading1` to
+//! `heading6`, `verse` and `note` when those are defined, and in built-in
+//! styles scaled from the document's type sizes when not.
+
+use crate::bilingual_generator::BilingualPdfGenerator;
+use crate::fonts::{FontContext, Justification};
+use crate::styles::ParagraphStyle;
+use anyhow::{Result, anyhow};
+
+/// How the Chinese and English of each block are arranged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutMode {
+    /// Chinese paragraph, then its English, down the page
+    Alternating,
+    /// Chinese in the left column, English level with it on the right
+    SideBySide,
+    /// Chinese in top-to-bottom columns running right to left
+    Vertical,
+}
+
+/// A block of a document
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    /// Heading, level 1 the largest; kept on a page with what follows it
+    Heading { level: u8, chinese: String, english: String },
+    /// A Chinese paragraph and its translation
+    BilingualPair { chinese: String, english: String },
+    /// Verse, one line per entry, each kept whole and never justified;
+    /// English lines pair with the Chinese lines
+    Verse { chinese: Vec<String>, english: Vec<String> },
+    /// Note or commentary, set smaller and indented
+    Note { chinese: String, english: String },
+    /// Start a new page
+    PageBreak,
+    /// Document information entry, e.g. `title`, `author`, `subject` or `keywords`
+    Metadata { key: String, value: String },
+}
+
+impl Block {
+    /// Text of the block in one language: one paragraph, or the lines of verse
+    pub fn texts(&self, is_chinese: bool) -> Vec<&str> {
+        match self {
+            Block::Heading { chinese, english, .. }
+            | Block::BilingualPair { chinese, english }
+            | Block::Note { chinese, english } => vec![if is_chinese { chinese } else { english }],
+            Block::Verse { chinese, english } => {
+                let lines = if is_chinese { chinese } else { english };
+                lines.iter().map(String::as_str).collect()
+            }
+            Block::PageBreak | Block::Metadata { .. } => Vec::new(),
+        }
+    }
+
+    /// Chinese and English set level with each other in side-by-side layout:
+    /// the block's two paragraphs, or the verse line by line
+    pub fn rows(&self) -> Vec<(&str, &str)> {
+        let (chinese, english) = (self.texts(true), self.texts(false));
+        (0..chinese.len().max(english.len()))
+            .map(|i| (chinese.get(i).copied().unwrap_or(""), english.get(i).copied().unwrap_or("")))
+            .collect()
+    }
+
+    /// Name of the paragraph style the block is set in; pairs use their own markup
+    pub fn style_name(&self) -> Option<String> {
+        match self {
+            Block::Heading { level, .. } => Some(format!("heading{}", level.clamp(&1, &6))),
+            Block::Verse { .. } => Some("verse".to_string()),
+            Block::Note { .. } => Some("note".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Paragraph style of the block: the named style if the document defines it,
+    /// otherwise the built-in one. `None` for pairs.
+    pub fn style(&self, font_context: &FontContext) -> Option<ParagraphStyle> {
+        let name = self.style_name()?;
+        if let Some(style) = font_context.paragraph_styles.get(&name) {
+            return Some(style.clone());
+        }
+
+        let (chinese_size, english_size) = (font_context.font_size_chinese, font_context.font_size_english);
+        let scaled = |scale: f32| ParagraphStyle {
+            chinese_size: Some(chinese_size * scale),
+            english_size: Some(english_size * scale),
+            ..ParagraphStyle::default()
+        };
+        Some(match self {
+            Block::Heading { level, .. } => ParagraphStyle {
+                alignment: Some(if *level <= 1 { Justification::Center } else { Justification::Left }),
+                space_before: chinese_size * 0.75,
+                space_after: chinese_size * 0.25,
+                ..scaled(match level {
+                    0 | 1 => 1.5,
+                    2 => 1.3,
+                    3 => 1.15,
+                    _ => 1.0,
+                })
+            },
+            Block::Note { .. } => ParagraphStyle { left_indent: 2.0, ..scaled(0.85) },
+            _ => ParagraphStyle {
+                left_indent: 2.0,
+                hanging_indent: 2.0,
+                alignment: Some(Justification::Left),
+                ..ParagraphStyle::default()
+            },
+        })
+    }
+}
+
+/// Level and text of a section that is a whole `<h1>`…`<h6>` element
+pub fn parse_heading(text: &str) -> Option<(u8, &str)> {
+    let text = text.trim();
+    let rest = text.strip_prefix("<h")?;
+    let level = rest.chars().next()?.to_digit(10).filter(|level| (1..=6).contains(level))? as u8;
+    let body = rest[1..].strip_prefix('>')?;
+    let body = body.strip_suffix(&format!("</h{}>", level)).unwrap_or(body);
+    Some((level, body.trim()))
+}
+
+/// Lines of a section that is a whole `<lg>` verse element: its `<l>` elements,
+/// or the lines of its text when it has none
+pub fn parse_verse(text: &str) -> Option<Vec<&str>> {
+    let text = text.trim();
+    let body = text.strip_prefix("<lg")?;
+    let body = body[body.find('>')? + 1..].trim();
+    let body = body.strip_suffix("</lg>").unwrap_or(body);
+
+    let mut elements = Vec::new();
+    let mut rest = body;
+    while let Some(start) = [rest.find("<l>"), rest.find("<l ")].into_iter().flatten().min() {
+        let line = &rest[start..];
+        let line = &line[line.find('>')? + 1..];
+        let end = line.find("</l>").unwrap_or(line.len());
+        elements.push(line[..end].trim());
+        rest = &line[end..];
+    }
+    elements.retain(|line| !line.is_empty());
+    if elements.is_empty() {
+        return Some(lines(body));
+    }
+    Some(elements)
+}
+
+/// Lines of a section that are not empty
+fn lines(text: &str) -> Vec<&str> {
+    text.lines().map(str::trim).filter(|line| !line.is_empty()).collect()
+}
+
+/// Lines of a section as one paragraph, joined by spaces
+fn prose(text: &str) -> String {
+    if !text.contains('\n') {
+        return text.to_string();
+    }
+    lines(text).join(" ")
+}
+
+/// Builds a document block by block and writes it as a PDF
+#[derive(Debug, Clone, Default)]
+pub struct DocumentBuilder {
+    blocks: Vec<Block>,
+}
+
+impl DocumentBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// One pair per section: a heading for a section that is a whole `<hN>`
+    /// element on either side, verse for a whole `<lg>` element (the other
+    /// side's lines pair with its lines), and otherwise paragraphs with their
+    /// line breaks taken as spaces
+    pub fn from_sections(chinese_sections: &[String], english_sections: &[String]) -> Result<Self> {
+        if chinese_sections.len() != english_sections.len() {
+            return Err(anyhow!(
+                "Chinese and English sections must have the same length: {} vs {}",
+                chinese_sections.len(),
+                english_sections.len()
+            ));
+        }
+
+        let mut builder = Self::new();
+        for (chinese, english) in chinese_sections.iter().zip(english_sections) {
+            let verse = (parse_verse(chinese), parse_verse(english));
+            if verse.0.is_some() || verse.1.is_some() {
+                let chinese = verse.0.unwrap_or_else(|| lines(chinese));
+                let english = verse.1.unwrap_or_else(|| lines(english));
+                builder.verse(chinese, english);
+                continue;
+            }
+
+            match (parse_heading(chinese), parse_heading(english)) {
+                (None, None) => builder.pair(prose(chinese), prose(english)),
+                (zh, en) => {
+                    let level = zh.or(en).map_or(1, |(level, _)| level);
+                    let chinese = zh.map_or(chinese.as_str(), |(_, text)| text);
+                    let english = en.map_or(english.as_str(), |(_, text)| text);
+                    builder.heading(level, prose(chinese), prose(english))
+                }
+            };
+        }
+        Ok(builder)
+    }
+
+    pub fn push(&mut self, block: Block) -> &mut Self {
+        self.blocks.push(block);
+        self
+    }
+
+    pub fn heading(&mut self, level: u8, chinese: impl Into<String>, english: impl Into<String>) -> &mut Self {
+        self.push(Block::Heading { level, chinese: chinese.into(), english: english.into() })
+    }
+
+    pub fn pair(&mut self, chinese: impl Into<String>, english: impl Into<String>) -> &mut Self {
+        self.push(Block::BilingualPair { chinese: chinese.into(), english: english.into() })
+    }
+
+    pub fn verse<S: Into<String>>(
+        &mut self,
+        chinese: impl IntoIterator<Item = S>,
+        english: impl IntoIterator<Item = S>,
+    ) -> &mut Self {
+        self.push(Block::Verse {
+            chinese: chinese.into_iter().map(Into::into).collect(),
+            english: english.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    pub fn note(&mut self, chinese: impl Into<String>, english: impl Into<String>) -> &mut Self {
+        self.push(Block::Note { chinese: chinese.into(), english: english.into() })
+    }
+
+    pub fn page_break(&mut self) -> &mut Self {
+        self.push(Block::PageBreak)
+    }
+
+    pub fn metadata(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.push(Block::Metadata { key: key.into(), value: value.into() })
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Lay out the document in `layout` and save it to `output_path`
+    pub fn write_pdf(&self, layout: LayoutMode, output_path: &str, font_context: &FontContext) -> Result<()> {
+        let mut generator = BilingualPdfGenerator::new(font_context.clone());
+        generator.generate_document(&self.blocks, layout, output_path)
+    }
+}
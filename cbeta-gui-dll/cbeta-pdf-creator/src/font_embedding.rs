@@ -0,0 +1,401 @@
+//! Font embedding and subsetting for PDF output
+//!
+//! Collects the glyphs a document actually uses, honours the OS/2 `fsType`
+//! embedding bits, and produces subset font programs with `ABCDEF+Name`
+//! base font names so exported PDFs stay small. Also reads the metrics that
+//! go into FontDescriptor and `/W` entries straight from the font tables.
+
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeMap, HashMap};
+use ttf_parser::{Face, GlyphId, Permissions};
+
+/// Embedding rights declared by a font's OS/2 `fsType` field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingPermission {
+    /// Restricted License or bitmap-only embedding: reference the font without embedding it
+    Forbidden,
+    /// Embedding is allowed, but the "no subsetting" bit is set
+    FullOnly,
+    /// Embedding and subsetting are both allowed
+    Subset,
+}
+
+/// Glyphs drawn with one PDF font resource.
+///
+/// Content streams address glyphs by CID (Identity-H), and the CID of a glyph is
+/// its glyph ID, so every Unicode scalar value, including supplementary-plane
+/// ideographs, maps to a single two-byte code. The text each code stands for is
+/// recorded here and becomes the ToUnicode CMap.
+#[derive(Debug, Clone, Default)]
+pub struct FontUsage {
+    /// CID -> (glyph ID, source text it was drawn for)
+    pub cids: BTreeMap<u16, (u16, String)>,
+    /// Extra CIDs for glyphs drawn for more than one text, keyed by (glyph ID, text)
+    aliases: HashMap<(u16, String), u16>,
+    /// Next free alias CID; `None` when CIDs cannot be remapped (CFF outlines)
+    next_alias: Option<u16>,
+}
+
+/// Code to write for a recorded glyph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphCode {
+    pub cid: u16,
+    /// Source text to attach as `/ActualText` when the CID could not carry it
+    pub actual_text: Option<String>,
+}
+
+impl FontUsage {
+    /// Usage for a font with `num_glyphs` glyphs whose CIDs go through a
+    /// CIDToGIDMap, so a glyph shared by several texts can get alias CIDs
+    pub fn with_cid_aliases(num_glyphs: u16) -> Self {
+        Self { next_alias: Some(num_glyphs), ..Self::default() }
+    }
+
+    /// Record that `gid` was shown for `text` (one character, a ligature's
+    /// characters, or empty for the trailing glyphs of a cluster).
+    ///
+    /// The first text drawn with a glyph keeps CID == glyph ID. A later, different
+    /// text gets an alias CID when the font allows it; otherwise it is returned as
+    /// `actual_text` for the caller to mark up.
+    pub fn record_glyph(&mut self, gid: u16, text: &str) -> GlyphCode {
+        let same = |cid: u16| GlyphCode { cid, actual_text: None };
+
+        let existing = match self.cids.get_mut(&gid) {
+            None => {
+                self.cids.insert(gid, (gid, text.to_string()));
+                return same(gid);
+            }
+            Some(existing) => existing,
+        };
+        if existing.1 == text || text.is_empty() {
+            return same(gid);
+        }
+        if existing.1.is_empty() {
+            existing.1 = text.to_string();
+            return same(gid);
+        }
+
+        if let Some(&cid) = self.aliases.get(&(gid, text.to_string())) {
+            return same(cid);
+        }
+        match self.next_alias {
+            Some(cid) if !self.cids.contains_key(&cid) => {
+                self.cids.insert(cid, (gid, text.to_string()));
+                self.aliases.insert((gid, text.to_string()), cid);
+                self.next_alias = cid.checked_add(1);
+                same(cid)
+            }
+            _ => GlyphCode { cid: gid, actual_text: Some(text.to_string()) },
+        }
+    }
+
+    /// Sorted glyph IDs in use, always including .notdef
+    pub fn glyph_ids(&self) -> Vec<u16> {
+        let mut glyphs: Vec<u16> = self.cids.values().map(|(gid, _)| *gid).collect();
+        glyphs.push(0);
+        glyphs.sort_unstable();
+        glyphs.dedup();
+        glyphs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cids.is_empty()
+    }
+}
+
+/// FontDescriptor metrics read from a face's head, hhea, OS/2 and post tables.
+///
+/// Vertical values are in PDF glyph space (1/1000 em).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontMetrics {
+    pub units_per_em: u16,
+    pub bbox: [i64; 4],
+    pub ascent: i64,
+    pub descent: i64,
+    pub cap_height: i64,
+    pub italic_angle: f32,
+    pub stem_v: i64,
+    pub is_fixed_pitch: bool,
+    pub is_italic: bool,
+}
+
+impl FontMetrics {
+    /// FontDescriptor `/Flags` (PDF 32000-1, 9.8.2)
+    pub fn flags(&self, symbolic: bool) -> i64 {
+        let mut flags = if symbolic { 1 << 2 } else { 1 << 5 };
+        if self.is_fixed_pitch {
+            flags |= 1;
+        }
+        if self.is_italic {
+            flags |= 1 << 6;
+        }
+        flags
+    }
+}
+
+/// Read FontDescriptor metrics for a face
+pub fn font_metrics(font_data: &[u8], face_index: u32) -> Result<FontMetrics> {
+    let face = Face::parse(font_data, face_index)
+        .map_err(|e| anyhow!("Failed to parse font face {}: {}", face_index, e))?;
+    let scale = 1000.0 / face.units_per_em() as f32;
+    let to_pdf = |v: i16| (v as f32 * scale).round() as i64;
+
+    let bbox = face.global_bounding_box();
+    // Older OS/2 tables have no sCapHeight; fall back to the outline of 'H'.
+    let cap_height = face
+        .capital_height()
+        .filter(|&h| h > 0)
+        .or_else(|| {
+            face.glyph_index('H')
+                .and_then(|gid| face.glyph_bounding_box(gid))
+                .map(|rect| rect.y_max)
+        })
+        .unwrap_or(face.ascender());
+
+    // Fonts do not record stem widths; estimate StemV from the weight class.
+    let weight = face.weight().to_number() as f32;
+    let stem_v = (10.0 + 220.0 * ((weight - 50.0) / 900.0).powi(2)).round() as i64;
+
+    Ok(FontMetrics {
+        units_per_em: face.units_per_em(),
+        bbox: [to_pdf(bbox.x_min), to_pdf(bbox.y_min), to_pdf(bbox.x_max), to_pdf(bbox.y_max)],
+        ascent: to_pdf(face.ascender()),
+        descent: to_pdf(face.descender()),
+        cap_height: to_pdf(cap_height),
+        italic_angle: face.italic_angle(),
+        stem_v,
+        is_fixed_pitch: face.is_monospaced(),
+        is_italic: face.is_italic(),
+    })
+}
+
+/// Advance widths from `hmtx` in 1/1000 em for the given glyph IDs
+pub fn glyph_advances(font_data: &[u8], face_index: u32, glyphs: &[u16]) -> Result<BTreeMap<u16, i64>> {
+    let face = Face::parse(font_data, face_index)
+        .map_err(|e| anyhow!("Failed to parse font face {}: {}", face_index, e))?;
+    let scale = 1000.0 / face.units_per_em() as f32;
+
+    Ok(glyphs
+        .iter()
+        .map(|&gid| {
+            let advance = face.glyph_hor_advance(GlyphId(gid)).unwrap_or(0);
+            (gid, (advance as f32 * scale).round() as i64)
+        })
+        .collect())
+}
+
+/// Vertical metrics of a glyph in 1/1000 em, for the CIDFont `/W2` array
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VerticalMetrics {
+    /// Distance the pen moves down the column after the glyph
+    pub advance: i64,
+    /// Height of the vertical origin above the horizontal baseline
+    pub origin_y: i64,
+}
+
+/// Vertical advances and origins for the given glyph IDs, read from `vmtx` and `VORG`.
+///
+/// Without `VORG` the origin is the top side bearing above the glyph's outline;
+/// faces without `vmtx` advance by ascender minus descender, as the shaper does,
+/// with the origin at the ascender.
+pub fn vertical_metrics(font_data: &[u8], face_index: u32, glyphs: &[u16]) -> Result<BTreeMap<u16, VerticalMetrics>> {
+    let face = Face::parse(font_data, face_index)
+        .map_err(|e| anyhow!("Failed to parse font face {}: {}", face_index, e))?;
+    let scale = 1000.0 / face.units_per_em() as f32;
+    let to_pdf = |v: i32| (v as f32 * scale).round() as i64;
+
+    Ok(glyphs
+        .iter()
+        .map(|&gid| {
+            let glyph = GlyphId(gid);
+            let advance = face
+                .glyph_ver_advance(glyph)
+                .map(i32::from)
+                .unwrap_or(face.ascender() as i32 - face.descender() as i32);
+            let origin_y = face
+                .glyph_y_origin(glyph)
+                .map(i32::from)
+                .or_else(|| {
+                    let bearing = face.glyph_ver_side_bearing(glyph)?;
+                    let bbox = face.glyph_bounding_box(glyph)?;
+                    Some(bearing as i32 + bbox.y_max as i32)
+                })
+                .unwrap_or(face.ascender() as i32);
+            (gid, VerticalMetrics { advance: to_pdf(advance), origin_y: to_pdf(origin_y) })
+        })
+        .collect())
+}
+
+/// Read the OS/2 `fsType` bits of a font face
+pub fn embedding_permission(font_data: &[u8], face_index: u32) -> Result<EmbeddingPermission> {
+    let face = Face::parse(font_data, face_index)
+        .map_err(|e| anyhow!("Failed to parse font face {}: {}", face_index, e))?;
+
+    if face.permissions() == Some(Permissions::Restricted) || !face.is_outline_embedding_allowed() {
+        return Ok(EmbeddingPermission::Forbidden);
+    }
+
+    if !face.is_subsetting_allowed() {
+        return Ok(EmbeddingPermission::FullOnly);
+    }
+
+    Ok(EmbeddingPermission::Subset)
+}
+
+/// Write a subset font program keeping only the given glyph IDs.
+///
+/// Glyph IDs are preserved, so the glyph-ID CIDs written to content streams stay
+/// valid for the subset.
+pub fn subset_font_program(font_data: &[u8], face_index: u32, glyphs: &[u16]) -> Result<Vec<u8>> {
+    let mut keep: Vec<u16> = glyphs.to_vec();
+    // .notdef must always survive subsetting.
+    keep.push(0);
+    keep.sort_unstable();
+    keep.dedup();
+
+    subsetter::subset(font_data, face_index, subsetter::Profile::pdf(&keep))
+        .map_err(|e| anyhow!("Font subsetting failed: {}", e))
+}
+
+/// Six-letter subset tag derived from the glyph set (PDF 32000-1, 9.6.4)
+pub fn subset_tag(glyphs: &[u16]) -> String {
+    // FNV-1a over the glyph IDs keeps the tag stable for identical subsets.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &gid in glyphs {
+        for byte in gid.to_be_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    let mut tag = String::with_capacity(6);
+    for _ in 0..6 {
+        tag.push((b'A' + (hash % 26) as u8) as char);
+        hash /= 26;
+    }
+    tag
+}
+
+/// Number of glyphs in a face (`maxp.numGlyphs`)
+pub fn glyph_count(font_data: &[u8], face_index: u32) -> Option<u16> {
+    Face::parse(font_data, face_index)
+        .ok()
+        .map(|face| face.number_of_glyphs())
+}
+
+/// Whether the face stores CFF outlines (embedded as `FontFile3`/`OpenType`)
+pub fn is_cff_font(font_data: &[u8], face_index: u32) -> bool {
+    Face::parse(font_data, face_index)
+        .map(|face| face.tables().cff.is_some())
+        .unwrap_or(false)
+}
+
+/// Copy one face out of a .ttc/.otc collection as a standalone sfnt.
+///
+/// Plain font files are returned unchanged. Tables shared between faces of the
+/// collection are duplicated into the extracted font.
+pub fn extract_collection_face(font_data: &[u8], face_index: u32) -> Result<Vec<u8>> {
+    if font_data.get(0..4) != Some(b"ttcf".as_slice()) {
+        return Ok(font_data.to_vec());
+    }
+
+    let num_fonts = read_u32(font_data, 8)?;
+    if face_index >= num_fonts {
+        return Err(anyhow!("Collection has {} faces, requested face {}", num_fonts, face_index));
+    }
+    let face_offset = read_u32(font_data, (face_index as usize).saturating_mul(4).saturating_add(12))? as usize;
+
+    let sfnt_version = read_u32(font_data, face_offset)?;
+    let num_tables = read_u16(font_data, face_offset.saturating_add(4))? as usize;
+    // The offset table's search hints are u16 multiples of 16
+    if num_tables == 0 || num_tables > 4095 {
+        return Err(anyhow!("Face {} has an invalid table count {}", face_index, num_tables));
+    }
+    let directory = face_offset
+        .checked_add(12)
+        .filter(|start| font_data.len().saturating_sub(*start) >= num_tables * 16)
+        .ok_or_else(|| anyhow!("Truncated table directory"))?;
+
+    let mut records = Vec::with_capacity(num_tables);
+    for i in 0..num_tables {
+        let record = directory + i * 16;
+        let tag = &font_data[record..record + 4];
+        let checksum = read_u32(font_data, record + 4)?;
+        let offset = read_u32(font_data, record + 8)? as usize;
+        let length = read_u32(font_data, record + 12)? as usize;
+        let table = offset
+            .checked_add(length)
+            .and_then(|end| font_data.get(offset..end))
+            .ok_or_else(|| anyhow!("Table {} lies outside the collection", String::from_utf8_lossy(tag)))?;
+        records.push(([tag[0], tag[1], tag[2], tag[3]], checksum, table));
+    }
+    Ok(write_sfnt(sfnt_version, records))
+}
+
+/// Write a standalone sfnt from (tag, checksum, data) table records, with the
+/// tables in tag order and `head.checkSumAdjustment` computed for the file
+pub fn write_sfnt(sfnt_version: u32, mut records: Vec<([u8; 4], u32, &[u8])>) -> Vec<u8> {
+    records.sort_by_key(|(tag, _, _)| *tag);
+    let num_tables = records.len();
+
+    // Offset table with binary-search hints
+    let mut out = Vec::new();
+    let entry_selector = (num_tables.max(1) as f32).log2().floor() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+    let range_shift = (num_tables as u16) * 16 - search_range;
+    out.extend_from_slice(&sfnt_version.to_be_bytes());
+    out.extend_from_slice(&(num_tables as u16).to_be_bytes());
+    out.extend_from_slice(&search_range.to_be_bytes());
+    out.extend_from_slice(&entry_selector.to_be_bytes());
+    out.extend_from_slice(&range_shift.to_be_bytes());
+
+    let mut offset = 12 + num_tables * 16;
+    let mut head_offset = None;
+    for (tag, checksum, table) in &records {
+        if tag == b"head" {
+            head_offset = Some(offset);
+        }
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&checksum.to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += (table.len() + 3) & !3;
+    }
+
+    for (_, _, table) in &records {
+        out.extend_from_slice(table);
+        while out.len() % 4 != 0 {
+            out.push(0);
+        }
+    }
+
+    // Recompute head.checkSumAdjustment for the standalone file
+    if let Some(head) = head_offset {
+        if out.len() >= head + 12 {
+            out[head + 8..head + 12].fill(0);
+            let sum = out
+                .chunks(4)
+                .fold(0u32, |acc, chunk| {
+                    let mut word = [0u8; 4];
+                    word[..chunk.len()].copy_from_slice(chunk);
+                    acc.wrapping_add(u32::from_be_bytes(word))
+                });
+            let adjustment = 0xB1B0_AFBA_u32.wrapping_sub(sum);
+            out[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+        }
+    }
+
+    out
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset.saturating_add(4))
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| anyhow!("Unexpected end of font data at offset {}", offset))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset.saturating_add(2))
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| anyhow!("Unexpected end of font data at offset {}", offset))
+}
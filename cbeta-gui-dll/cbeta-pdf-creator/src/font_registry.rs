@@ -0,0 +1,553 @@
+//! Font registry
+//!
+//! Indexes font faces from scanned directories, individual files and in-memory
+//! buffers by family name, style and script coverage, so callers can pick fonts
+//! by family instead of relying on fixed install paths.
+
+use anyhow::{Result, anyhow};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+pub use unicode_script::Script;
+
+use crate::font_embedding;
+use crate::fonts::face_names;
+
+/// Scripts indexed for coverage, with characters a face must map to count as covering them
+const INDEXED_SCRIPTS: &[(Script, &[char])] = &[
+    (Script::Han, &['一', '如', '是', '我', '聞', '經']),
+    (Script::Latin, &['A', 'Z', 'a', 'z', 'e']),
+    (Script::Devanagari, &['अ', 'क', 'ा', '्']),
+    (Script::Tibetan, &['ཀ', 'ག', '་', 'ི']),
+    (Script::Siddham, &['\u{11580}', '\u{1158E}', '\u{115AF}']),
+];
+
+/// Families tried for Chinese body text when no family was chosen
+const DEFAULT_CHINESE_FAMILIES: &[&str] = &[
+    "Noto Serif CJK TC",
+    "Source Han Serif TC",
+    "SimSun",
+    "Microsoft JhengHei",
+    "Microsoft YaHei",
+];
+
+/// Families tried for English body text when no family was chosen
+const DEFAULT_ENGLISH_FAMILIES: &[&str] = &[
+    "EB Garamond",
+    "Noto Serif",
+    "Liberation Serif",
+    "Garamond",
+    "Georgia",
+    "Times New Roman",
+    "DejaVu Serif",
+];
+
+/// Fallback chain for characters the Chinese body font lacks (CJK extensions B+)
+const DEFAULT_CHINESE_FALLBACKS: &[&str] = &[
+    "HanaMinA",
+    "HanaMinB",
+    "BabelStone Han",
+    "Noto Serif CJK TC",
+    "Source Han Serif TC",
+    "MingLiU-ExtB",
+    "SimSun-ExtB",
+];
+
+/// Fallback chain for characters the English body font lacks (IAST diacritics, symbols)
+const DEFAULT_ENGLISH_FALLBACKS: &[&str] = &[
+    "Noto Serif",
+    "DejaVu Serif",
+];
+
+/// Families tried for Devanagari mantras and Sanskrit quotations
+const DEFAULT_DEVANAGARI_FAMILIES: &[&str] = &[
+    "Noto Serif Devanagari",
+    "Sanskrit 2003",
+    "Siddhanta",
+    "Noto Sans Devanagari",
+    "Nirmala UI",
+    "Mangal",
+];
+
+/// Families tried for Siddham dhāraṇīs
+const DEFAULT_SIDDHAM_FAMILIES: &[&str] = &[
+    "Noto Sans Siddham",
+    "Muktamsiddham",
+];
+
+/// Families tried for Tibetan
+const DEFAULT_TIBETAN_FAMILIES: &[&str] = &[
+    "Noto Serif Tibetan",
+    "Jomolhari",
+    "Tibetan Machine Uni",
+    "Microsoft Himalaya",
+    "Noto Sans Tibetan",
+];
+
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "otc"];
+
+/// Tables read to index a face; outlines and layout tables are left on disk
+const INDEX_TABLES: &[[u8; 4]] = &[*b"OS/2", *b"cmap", *b"head", *b"hhea", *b"maxp", *b"name"];
+
+/// Weight and slant of a face
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontStyle {
+    pub weight: u16, // OS/2 usWeightClass (400 = regular, 700 = bold)
+    pub width: u16,  // OS/2 usWidthClass (5 = normal, lower is condensed)
+    pub italic: bool,
+}
+
+impl FontStyle {
+    pub const REGULAR: FontStyle = FontStyle { weight: 400, width: 5, italic: false };
+    pub const BOLD: FontStyle = FontStyle { weight: 700, width: 5, italic: false };
+    pub const ITALIC: FontStyle = FontStyle { weight: 400, width: 5, italic: true };
+
+    /// Lower is closer; slant mismatches outweigh width, which outweighs weight
+    fn distance(&self, other: &FontStyle) -> u32 {
+        let slant = if self.italic == other.italic { 0 } else { 100_000 };
+        let width = (self.width as i32 - other.width as i32).unsigned_abs() * 1_000;
+        slant + width + (self.weight as i32 - other.weight as i32).unsigned_abs()
+    }
+}
+
+/// Where a registered face's font data lives
+#[derive(Debug, Clone)]
+pub enum FontSource {
+    /// Read from disk when the face is loaded
+    File(PathBuf),
+    /// Registered from memory; the label is used in log messages
+    Memory { label: String, data: Arc<Vec<u8>> },
+}
+
+/// One indexed face
+#[derive(Debug, Clone)]
+pub struct RegisteredFace {
+    pub family: String,
+    pub names: Vec<String>, // family, typographic family and full names (all languages)
+    pub style: FontStyle,
+    pub scripts: Vec<Script>,
+    pub source: FontSource,
+    pub face_index: u32,
+}
+
+impl RegisteredFace {
+    /// Raw font file data (the whole collection for .ttc/.otc faces)
+    pub fn load_data(&self) -> Result<Vec<u8>> {
+        match &self.source {
+            FontSource::File(path) => std::fs::read(path)
+                .map_err(|e| anyhow!("Failed to read font {}: {}", path.display(), e)),
+            FontSource::Memory { data, .. } => Ok(data.as_ref().clone()),
+        }
+    }
+
+    /// Path or memory label, for logging and embedding decisions
+    pub fn source_name(&self) -> String {
+        match &self.source {
+            FontSource::File(path) => path.display().to_string(),
+            FontSource::Memory { label, .. } => label.clone(),
+        }
+    }
+
+    pub fn covers(&self, script: Script) -> bool {
+        self.scripts.contains(&script)
+    }
+
+    fn matches_family(&self, family: &str) -> bool {
+        let wanted = normalize_family(family);
+        self.names.iter().any(|name| normalize_family(name) == wanted)
+    }
+}
+
+/// Index of available font faces
+#[derive(Debug, Clone, Default)]
+pub struct FontRegistry {
+    faces: Vec<RegisteredFace>,
+    scanned_directories: HashSet<PathBuf>, // canonical paths, so links cannot loop
+    preferred_families: HashMap<Script, Vec<String>>,
+    fallback_families: HashMap<Script, Vec<String>>,
+}
+
+impl FontRegistry {
+    /// Empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry populated from the platform font directories and any `fonts`
+    /// directory deployed next to the executable
+    pub fn with_system_fonts() -> Self {
+        let mut registry = Self::new();
+        for dir in default_font_directories() {
+            match registry.add_directory(&dir) {
+                Ok(0) => {}
+                Ok(count) => println!("Indexed {} font faces from {}", count, dir.display()),
+                Err(e) => eprintln!("⚠️ Skipping font directory {}: {}", dir.display(), e),
+            }
+        }
+        registry
+    }
+
+    /// Recursively index every font file below `dir`. Returns the number of faces added.
+    ///
+    /// Directories already scanned, including through symbolic links, are skipped.
+    pub fn add_directory(&mut self, dir: &Path) -> Result<usize> {
+        if !dir.is_dir() {
+            return Err(anyhow!("Not a directory: {}", dir.display()));
+        }
+
+        let mut added = 0;
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            let first_visit = current
+                .canonicalize()
+                .map(|canonical| self.scanned_directories.insert(canonical))
+                .unwrap_or(false);
+            if !first_visit {
+                continue;
+            }
+            let entries = match std::fs::read_dir(&current) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+            paths.sort();
+            for path in paths {
+                if path.is_dir() {
+                    pending.push(path);
+                } else if is_font_file(&path) {
+                    // Unreadable or malformed files are skipped rather than failing the scan.
+                    added += self.register_file(&path).unwrap_or(0);
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    /// Index every face of one font file. Returns the number of faces added.
+    ///
+    /// Only the table directories and the tables indexing needs are read; the
+    /// rest of the file is loaded when a face is used.
+    pub fn register_file(&mut self, path: &Path) -> Result<usize> {
+        let faces = read_file_index_tables(path)
+            .map_err(|e| anyhow!("Failed to read font {}: {}", path.display(), e))?;
+        self.register_faces(faces, FontSource::File(path.to_path_buf()))
+    }
+
+    /// Index every face of an in-memory font file. Returns the number of faces added.
+    pub fn register_bytes(&mut self, label: &str, data: Vec<u8>) -> Result<usize> {
+        let faces = index_tables(&mut |offset, len| {
+            usize::try_from(offset)
+                .ok()
+                .and_then(|start| data.get(start..start.checked_add(len)?))
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| anyhow!("Unexpected end of font data at offset {}", offset))
+        })?;
+        let source = FontSource::Memory { label: label.to_string(), data: Arc::new(data) };
+        self.register_faces(faces, source)
+    }
+
+    /// Index the faces of one source from their `index_tables` fonts
+    fn register_faces(&mut self, faces: Vec<Option<Vec<u8>>>, source: FontSource) -> Result<usize> {
+        // Registering the same file or label again replaces its earlier entries.
+        let source_name = match &source {
+            FontSource::File(path) => path.display().to_string(),
+            FontSource::Memory { label, .. } => label.clone(),
+        };
+        self.faces.retain(|existing| existing.source_name() != source_name);
+
+        let mut added = 0;
+        for (face_index, tables) in (0..).zip(faces) {
+            let face = tables.and_then(|tables| index_face(&tables, face_index, source.clone()));
+            if let Some(face) = face {
+                self.faces.push(face);
+                added += 1;
+            }
+        }
+        if added == 0 {
+            return Err(anyhow!("No usable font faces found"));
+        }
+        Ok(added)
+    }
+
+    /// Put `family` first in the preference list used to pick fonts for `script`
+    pub fn set_preferred_family(&mut self, script: Script, family: &str) {
+        let preferred = self.preferred_families.entry(script).or_default();
+        preferred.retain(|existing| normalize_family(existing) != normalize_family(family));
+        preferred.insert(0, family.to_string());
+    }
+
+    /// Families chosen by the caller for `script`, most preferred first
+    pub fn preferred_families(&self, script: Script) -> &[String] {
+        self.preferred_families.get(&script).map_or(&[], |families| families.as_slice())
+    }
+
+    /// Replace the fallback chain for `script`, tried in order for characters the
+    /// primary font lacks
+    pub fn set_fallback_families(&mut self, script: Script, families: &[&str]) {
+        self.fallback_families
+            .insert(script, families.iter().map(|family| family.to_string()).collect());
+    }
+
+    /// Registered faces of the fallback chain for `script`, excluding `primary`.
+    ///
+    /// Families that are not registered are skipped; the chain defaults to
+    /// `defaults` unless the caller set one.
+    pub fn resolve_fallbacks(&self, script: Script, defaults: &[&str], primary: &RegisteredFace) -> Vec<&RegisteredFace> {
+        let families: Vec<&str> = match self.fallback_families.get(&script) {
+            Some(families) => families.iter().map(String::as_str).collect(),
+            None => defaults.to_vec(),
+        };
+
+        let mut chain: Vec<&RegisteredFace> = Vec::new();
+        for family in families {
+            let Some(face) = self.find_family(family, FontStyle::REGULAR) else {
+                continue;
+            };
+            let duplicate = std::iter::once(primary)
+                .chain(chain.iter().copied())
+                .any(|other| other.source_name() == face.source_name() && other.face_index == face.face_index);
+            if !duplicate {
+                chain.push(face);
+            }
+        }
+        chain
+    }
+
+    /// Fallback faces for Chinese body text
+    pub fn chinese_fallbacks(&self, primary: &RegisteredFace) -> Vec<&RegisteredFace> {
+        self.resolve_fallbacks(Script::Han, DEFAULT_CHINESE_FALLBACKS, primary)
+    }
+
+    /// Fallback faces for English body text
+    pub fn english_fallbacks(&self, primary: &RegisteredFace) -> Vec<&RegisteredFace> {
+        self.resolve_fallbacks(Script::Latin, DEFAULT_ENGLISH_FALLBACKS, primary)
+    }
+
+    /// Face of `family` closest to `style`. Later registrations win ties, so fonts
+    /// registered by the caller override system fonts of the same family.
+    pub fn find_family(&self, family: &str, style: FontStyle) -> Option<&RegisteredFace> {
+        self.faces
+            .iter()
+            .rev()
+            .filter(|face| face.matches_family(family))
+            .min_by_key(|face| face.style.distance(&style))
+    }
+
+    /// Face covering `script` closest to `style`. Later registrations win ties, as
+    /// in `find_family`.
+    pub fn find_for_script(&self, script: Script, style: FontStyle) -> Option<&RegisteredFace> {
+        self.faces
+            .iter()
+            .rev()
+            .filter(|face| face.covers(script))
+            .min_by_key(|face| face.style.distance(&style))
+    }
+
+    /// Resolve the font for `script`: caller preferences, then `defaults`, then any covering face
+    pub fn resolve(&self, script: Script, defaults: &[&str], style: FontStyle) -> Option<&RegisteredFace> {
+        let preferred = self.preferred_families(script).iter().map(String::as_str);
+        preferred
+            .chain(defaults.iter().copied())
+            .filter_map(|family| self.find_family(family, style))
+            .find(|face| face.covers(script))
+            .or_else(|| self.find_for_script(script, style))
+    }
+
+    /// Font for Chinese body text
+    pub fn resolve_chinese(&self) -> Option<&RegisteredFace> {
+        self.resolve(Script::Han, DEFAULT_CHINESE_FAMILIES, FontStyle::REGULAR)
+    }
+
+    /// Font for English body text
+    pub fn resolve_english(&self) -> Option<&RegisteredFace> {
+        self.resolve(Script::Latin, DEFAULT_ENGLISH_FAMILIES, FontStyle::REGULAR)
+    }
+
+    /// Font for Devanagari, Siddham or Tibetan text, set ahead of the English fonts
+    pub fn resolve_script(&self, script: Script) -> Option<&RegisteredFace> {
+        let defaults = match script {
+            Script::Devanagari => DEFAULT_DEVANAGARI_FAMILIES,
+            Script::Siddham => DEFAULT_SIDDHAM_FAMILIES,
+            Script::Tibetan => DEFAULT_TIBETAN_FAMILIES,
+            _ => &[],
+        };
+        self.resolve(script, defaults, FontStyle::REGULAR)
+    }
+
+    /// Distinct family names, sorted
+    pub fn families(&self) -> Vec<String> {
+        let mut families: Vec<String> = self.faces.iter().map(|face| face.family.clone()).collect();
+        families.sort();
+        families.dedup();
+        families
+    }
+
+    pub fn faces(&self) -> &[RegisteredFace] {
+        &self.faces
+    }
+
+    pub fn len(&self) -> usize {
+        self.faces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty()
+    }
+}
+
+/// Process-wide registry used by `FontContext::initialize_fonts` and the FFI
+/// registration calls. System directories are scanned on first use.
+pub fn global_registry() -> &'static RwLock<FontRegistry> {
+    static REGISTRY: OnceLock<RwLock<FontRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(FontRegistry::with_system_fonts()))
+}
+
+/// Platform font directories plus deployment and development asset folders
+pub fn default_font_directories() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    // Fonts shipped alongside the executable or DLL host
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(exe_dir) = exe.parent() {
+            dirs.push(exe_dir.join("fonts"));
+            dirs.push(exe_dir.join("assets").join("fonts"));
+        }
+    }
+    // Bundled assets when running from a source checkout
+    dirs.push(PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/fonts")));
+
+    if cfg!(windows) {
+        let windir = std::env::var("WINDIR").unwrap_or_else(|_| "C:\\Windows".to_string());
+        dirs.push(PathBuf::from(windir).join("Fonts"));
+        if let Ok(local) = std::env::var("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Microsoft").join("Windows").join("Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs.push(PathBuf::from("/Library/Fonts"));
+        dirs.push(PathBuf::from("/System/Library/Fonts"));
+        if let Ok(home) = std::env::var("HOME") {
+            dirs.push(PathBuf::from(home).join("Library/Fonts"));
+        }
+    } else {
+        dirs.push(PathBuf::from("/usr/share/fonts"));
+        dirs.push(PathBuf::from("/usr/local/share/fonts"));
+        if let Ok(home) = std::env::var("HOME") {
+            dirs.push(PathBuf::from(&home).join(".local/share/fonts"));
+            dirs.push(PathBuf::from(home).join(".fonts"));
+        }
+    }
+
+    dirs.into_iter().filter(|dir| dir.is_dir()).collect()
+}
+
+/// Reads `len` bytes at `offset` of a font file
+type ReadAt<'a> = dyn FnMut(u64, usize) -> Result<Vec<u8>> + 'a;
+
+/// `index_tables` for a font file, reading only what it asks for
+fn read_file_index_tables(path: &Path) -> Result<Vec<Option<Vec<u8>>>> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    index_tables(&mut |offset, len| {
+        if offset.saturating_add(len as u64) > file_len {
+            return Err(anyhow!("Unexpected end of font data at offset {}", offset));
+        }
+        let mut buffer = vec![0; len];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+/// For each face of a font file or collection, a standalone font holding only
+/// the `INDEX_TABLES` of that face, or `None` if its table directory is malformed
+fn index_tables(read_at: &mut ReadAt) -> Result<Vec<Option<Vec<u8>>>> {
+    let header = read_at(0, 12)?;
+    let face_offsets: Vec<u64> = if header[0..4] == *b"ttcf" {
+        let num_fonts = u32::from_be_bytes([header[8], header[9], header[10], header[11]]) as usize;
+        read_at(12, num_fonts.saturating_mul(4))?
+            .chunks_exact(4)
+            .map(|offset| u32::from_be_bytes([offset[0], offset[1], offset[2], offset[3]]) as u64)
+            .collect()
+    } else {
+        vec![0]
+    };
+    Ok(face_offsets.into_iter().map(|offset| face_index_tables(read_at, offset).ok()).collect())
+}
+
+fn face_index_tables(read_at: &mut ReadAt, face_offset: u64) -> Result<Vec<u8>> {
+    let header = read_at(face_offset, 12)?;
+    let sfnt_version = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let num_tables = u16::from_be_bytes([header[4], header[5]]) as usize;
+
+    let mut tables = Vec::new();
+    for record in read_at(face_offset + 12, num_tables * 16)?.chunks_exact(16) {
+        let tag = [record[0], record[1], record[2], record[3]];
+        if !INDEX_TABLES.contains(&tag) {
+            continue;
+        }
+        let checksum = u32::from_be_bytes([record[4], record[5], record[6], record[7]]);
+        let offset = u32::from_be_bytes([record[8], record[9], record[10], record[11]]);
+        let length = u32::from_be_bytes([record[12], record[13], record[14], record[15]]);
+        tables.push((tag, checksum, read_at(offset as u64, length as usize)?));
+    }
+    let records = tables.iter().map(|(tag, checksum, data)| (*tag, *checksum, data.as_slice())).collect();
+    Ok(font_embedding::write_sfnt(sfnt_version, records))
+}
+
+/// Index face `face_index` of a font file from its `index_tables` font
+fn index_face(tables: &[u8], face_index: u32, source: FontSource) -> Option<RegisteredFace> {
+    let face = ttf_parser::Face::parse(tables, 0).ok()?;
+
+    let family = preferred_family_name(&face)?;
+    let style = FontStyle {
+        weight: face.weight().to_number(),
+        width: face.width().to_number(),
+        italic: face.is_italic() || face.is_oblique(),
+    };
+    let scripts = INDEXED_SCRIPTS
+        .iter()
+        .filter(|(_, samples)| samples.iter().all(|&ch| face.glyph_index(ch).is_some()))
+        .map(|(script, _)| *script)
+        .collect();
+
+    Some(RegisteredFace {
+        family,
+        names: face_names(tables, 0),
+        style,
+        scripts,
+        source,
+        face_index,
+    })
+}
+
+/// English typographic family if present, else the English legacy family, else any family name
+fn preferred_family_name(face: &ttf_parser::Face) -> Option<String> {
+    const ENGLISH_US: u16 = 0x0409;
+    let lookup = |name_id: u16, english_only: bool| {
+        face.names()
+            .into_iter()
+            .filter(|name| name.name_id == name_id && (!english_only || name.language_id == ENGLISH_US))
+            .find_map(|name| name.to_string())
+    };
+
+    lookup(ttf_parser::name_id::TYPOGRAPHIC_FAMILY, true)
+        .or_else(|| lookup(ttf_parser::name_id::FAMILY, true))
+        .or_else(|| lookup(ttf_parser::name_id::TYPOGRAPHIC_FAMILY, false))
+        .or_else(|| lookup(ttf_parser::name_id::FAMILY, false))
+}
+
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| FONT_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+        .unwrap_or(false)
+}
+
+fn normalize_family(name: &str) -> String {
+    name.chars()
+        .filter(|ch| !ch.is_whitespace() && *ch != '-' && *ch != '_')
+        .flat_map(|ch| ch.to_lowercase())
+        .collect()
+}
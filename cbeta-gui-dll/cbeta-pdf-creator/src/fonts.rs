@@ -5,14 +5,65 @@
 
 use anyhow::{Result, anyhow};
 use fontdue::{Font, FontSettings};
-use std::collections::HashMap;
-use std::path::Path;
+
+use crate::font_registry::{self, FontRegistry, RegisteredFace, Script};
+use crate::hyphenation::HyphenationSettings;
+use crate::itemize;
+use crate::metrics_cache::{FontKey, GlyphMetrics, GlyphMetricsCache, ShapeKey};
+use crate::ruby::RubySettings;
+use crate::styles::ParagraphStyles;
+use crate::shaping::{self, FeatureSettings, ShapedGlyph};
+use crate::syllables;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+use unicode_script::UnicodeScript;
+
+/// Unicode normalisation applied to text before layout. CJK compatibility
+/// ideographs (U+F900–U+FAFF, U+2F800–U+2FA1F) are kept as written in every form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextNormalization {
+    None,
+    Nfc, // precomposed letters, e.g. ā as one character
+    Nfd, // letters and combining marks
+}
 
 /// Text justification options
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Justification {
     Left,
-    Justify, // English only for now
+    Right,
+    Center,
+    Justify, // English by total-fit breaking, Chinese by inter-character spacing
+}
+
+/// Direction Chinese lines run in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritingMode {
+    Horizontal,
+    Vertical, // top-to-bottom columns, right to left
+}
+
+/// A loaded face: (font, display name, source path, raw file data, collection face index)
+type LoadedFont = (Font, String, String, Vec<u8>, u32);
+
+/// A face in a script's fallback chain, used for characters the primary font lacks
+#[derive(Clone)]
+pub struct FallbackFont {
+    pub font: Font,
+    pub name: String,
+    pub path: String,
+    pub data: Vec<u8>,
+    pub face_index: u32,
+    pub script: Option<Script>, // chosen for this script: tried before the primary font for its characters
+}
+
+/// Borrowed view of one face of a font chain (slot 0 is the primary font)
+pub struct FaceRef<'a> {
+    pub font: &'a Font,
+    pub name: &'a str,
+    pub path: &'a str,
+    pub data: &'a [u8],
+    pub face_index: u32,
 }
 
 /// Font context containing loaded fonts and rendering settings
@@ -23,15 +74,19 @@ pub struct FontContext {
     pub chinese_font_name: String,  // Track which font was loaded
     pub chinese_font_path: String,  // Track source path to decide embedding strategy
     pub chinese_font_data: Vec<u8>, // Store raw font data for embedding
+    pub chinese_font_index: u32,    // Face index inside .ttc/.otc collections (0 otherwise)
     pub chinese_font_bold: Option<Font>,
+    pub chinese_fallbacks: Vec<FallbackFont>, // Tried in order for characters chinese_font lacks
     
     // English fonts
     pub english_font: Font,
     pub english_font_name: String,  // Track which font was loaded
     pub english_font_path: String,  // Track source path to decide embedding strategy
     pub english_font_data: Vec<u8>, // Store raw font data for embedding
+    pub english_font_index: u32,    // Face index inside .ttc/.otc collections (0 otherwise)
     pub english_font_italic: Option<Font>,
     pub english_font_bold: Option<Font>,
+    pub english_fallbacks: Vec<FallbackFont>, // Tried in order for characters english_font lacks
     
     // Layout settings
     pub page_width: f32,
@@ -46,33 +101,137 @@ pub struct FontContext {
     pub tracking_english: f32,
     pub justification: Justification,
     pub paragraph_spacing: f32,  // multiplier of line height (0.4-0.8 typical for books)
+    pub writing_mode: WritingMode, // Chinese lines are measured along columns when vertical
+    pub font_features: FeatureSettings, // OpenType features per script, on top of the shaper defaults
+    pub hyphenation: HyphenationSettings, // Liang patterns for English-side text (IAST words use Sanskrit)
+    pub punctuation_compression: bool, // adjacent CJK punctuation and marks at line edges lose their blank half (CLREQ)
+    pub hanging_punctuation: bool, // 。，、 may hang into the right margin instead of moving to the next line
+    pub cjk_latin_spacing: f32, // between ideographs and Latin letters or digits, in em of the Chinese size
+    pub normalization: TextNormalization, // applied to paragraphs before layout
+    pub orphan_lines: usize, // fewest lines of a paragraph left at the foot of a page
+    pub widow_lines: usize,  // fewest lines of a paragraph carried to the top of the next page
+    pub keep_headings_with_next: bool, // headings share a page with the first lines of what follows
+    pub keep_pairs_together: bool, // a Chinese paragraph shares a page with the first lines of its English
+    pub short_pair_lines: usize, // pairs of at most this many lines in all move to a new page whole (0: none)
+    pub continued_marker: Option<(String, String)>, // (Chinese, English) set under side-by-side columns broken by a page
+    pub ruby: RubySettings, // annotation size and dictionary readings over Chinese text
+    pub paragraph_styles: ParagraphStyles, // named styles sections select with `<p class="name">`
+    pub verse_columns: usize, // columns of 句 in horizontal Chinese verse: 1, or 2 when they fit
     
-    // Font metrics cache
-    char_metrics: HashMap<char, fontdue::Metrics>,
+    // Glyph metrics in font units, shared with clones and other contexts
+    metrics_cache: GlyphMetricsCache,
 }
 
 impl FontContext {
-    /// Initialize fonts with professional typography choices
+    /// Initialize fonts with professional typography choices, resolved through
+    /// the process-wide font registry
     pub fn initialize_fonts() -> Result<Self> {
-        // Try to load high-quality fonts, fallback to system fonts
-        let (chinese_font, chinese_font_name, chinese_font_path, chinese_font_data) =
-            Self::load_chinese_font()?;
-        let (english_font, english_font_name, english_font_path, english_font_data) = Self::load_english_font()?;
-        
-        println!("Loaded fonts: Chinese={}, English={}", chinese_font_name, english_font_name);
-        
-        Ok(FontContext {
+        let registry = font_registry::global_registry()
+            .read()
+            .map_err(|_| anyhow!("Font registry lock poisoned"))?;
+        Self::from_registry(&registry)
+    }
+
+    /// Pick the Chinese and English body fonts from a registry: caller-preferred
+    /// families first, then the default print families, then any face covering the script
+    pub fn from_registry(registry: &FontRegistry) -> Result<Self> {
+        let chinese_face = registry
+            .resolve_chinese()
+            .ok_or_else(|| anyhow!("No suitable Chinese font found"))?;
+        let english_face = registry
+            .resolve_english()
+            .ok_or_else(|| anyhow!("No suitable English font found"))?;
+
+        let chinese = Self::load_registered_face(chinese_face, "Chinese")?;
+        let english = Self::load_registered_face(english_face, "English")?;
+
+        println!("Loaded fonts: Chinese={}, English={}", chinese.1, english.1);
+
+        let mut context = Self::from_loaded_fonts(chinese, english);
+        for face in registry.chinese_fallbacks(chinese_face) {
+            match Self::load_registered_face(face, "Chinese fallback") {
+                Ok(loaded) => context.chinese_fallbacks.push(FallbackFont::from(loaded)),
+                Err(e) => eprintln!("⚠️ {}", e),
+            }
+        }
+        for face in registry.english_fallbacks(english_face) {
+            match Self::load_registered_face(face, "English fallback") {
+                Ok(loaded) => context.english_fallbacks.push(FallbackFont::from(loaded)),
+                Err(e) => eprintln!("⚠️ {}", e),
+            }
+        }
+        for &script in syllables::SYLLABIC_SCRIPTS {
+            let Some(face) = registry.resolve_script(script) else {
+                continue;
+            };
+            let existing = context
+                .english_fallbacks
+                .iter_mut()
+                .find(|fallback| fallback.path == face.source_name() && fallback.face_index == face.face_index);
+            match existing {
+                Some(fallback) => fallback.script = fallback.script.or(Some(script)),
+                None => match Self::load_registered_face(face, &format!("{:?} font", script)) {
+                    Ok(loaded) => context.english_fallbacks.push(FallbackFont { script: Some(script), ..loaded.into() }),
+                    Err(e) => eprintln!("⚠️ {}", e),
+                },
+            }
+        }
+        Ok(context)
+    }
+
+    /// Append a face to the Chinese or English fallback chain
+    pub fn add_fallback_font_data(&mut self, is_chinese: bool, font_name: &str, font_data: Vec<u8>) -> Result<()> {
+        let loaded = Self::load_font_data(font_name, "", font_data)?;
+        let chain = if is_chinese { &mut self.chinese_fallbacks } else { &mut self.english_fallbacks };
+        chain.push(FallbackFont::from(loaded));
+        Ok(())
+    }
+
+    /// Set text of `script` (Devanagari, Tibetan, ...) in this face, ahead of the
+    /// English fonts. Any face set earlier for the script stays as a fallback.
+    pub fn add_script_font_data(&mut self, script: Script, font_name: &str, font_data: Vec<u8>) -> Result<()> {
+        let loaded = Self::load_font_data(font_name, "", font_data)?;
+        for fallback in self.english_fallbacks.iter_mut().filter(|fallback| fallback.script == Some(script)) {
+            fallback.script = None;
+        }
+        self.english_fallbacks.push(FallbackFont { script: Some(script), ..loaded.into() });
+        Ok(())
+    }
+
+    /// Build a context from in-memory font files instead of searching the system.
+    ///
+    /// For collections the face whose name matches the given family name is used.
+    pub fn from_font_data(
+        chinese_name: &str,
+        chinese_data: Vec<u8>,
+        english_name: &str,
+        english_data: Vec<u8>,
+    ) -> Result<Self> {
+        let chinese = Self::load_font_data(chinese_name, "", chinese_data)?;
+        let english = Self::load_font_data(english_name, "", english_data)?;
+        Ok(Self::from_loaded_fonts(chinese, english))
+    }
+
+    fn from_loaded_fonts(chinese: LoadedFont, english: LoadedFont) -> Self {
+        let (chinese_font, chinese_font_name, chinese_font_path, chinese_font_data, chinese_font_index) = chinese;
+        let (english_font, english_font_name, english_font_path, english_font_data, english_font_index) = english;
+
+        FontContext {
             chinese_font,
             chinese_font_name,
             chinese_font_path,
             chinese_font_data,
+            chinese_font_index,
             chinese_font_bold: None, // TODO: Load bold variant
+            chinese_fallbacks: Vec::new(),
             english_font,
             english_font_name,
             english_font_path,
             english_font_data,
+            english_font_index,
             english_font_italic: None, // TODO: Load italic variant
             english_font_bold: None, // TODO: Load bold variant
+            english_fallbacks: Vec::new(),
             
             // Default page settings (A4-like)
             page_width: 595.0, // A4 width in points
@@ -87,82 +246,55 @@ impl FontContext {
             tracking_english: 8.0,    // Garamond/Georgia loves a little air
             justification: Justification::Justify,
             paragraph_spacing: 0.6,
+            writing_mode: WritingMode::Horizontal,
+            font_features: FeatureSettings::default(),
+            hyphenation: HyphenationSettings::default(),
+            punctuation_compression: true,
+            hanging_punctuation: false,
+            cjk_latin_spacing: 0.25, // CLREQ: a quarter em
+            normalization: TextNormalization::Nfc,
+            orphan_lines: 2,
+            widow_lines: 2,
+            keep_headings_with_next: true,
+            keep_pairs_together: true,
+            short_pair_lines: 6,
+            continued_marker: None,
+            ruby: RubySettings::default(),
+            paragraph_styles: ParagraphStyles::default(),
+            verse_columns: 2,
             
-            char_metrics: HashMap::new(),
-        })
-    }
-    
-    /// Load a high-quality Chinese font
-    fn load_chinese_font() -> Result<(Font, String, String, Vec<u8>)> {
-        // Priority order for Chinese fonts
-        let font_paths = vec![
-            // Bundled open-source, print-ready default for Linux/portable builds.
-            ("Noto Serif CJK TC", concat!(env!("CARGO_MANIFEST_DIR"), "/assets/fonts/NotoSerifCJKtc-Regular.otf")),
-            // Source Han (same glyph design family; also open-source).
-            ("Source Han Serif TC", concat!(env!("CARGO_MANIFEST_DIR"), "/assets/fonts/SourceHanSerifTC-Regular.otf")),
-            // SimSun (high compatibility for Windows PDF viewers)
-            ("SimSun", "C:\\Windows\\Fonts\\simsun.ttc"),
-            // Microsoft JhengHei (Windows system font) - Regular
-            ("Microsoft JhengHei", "C:\\Windows\\Fonts\\msjh.ttc"),
-            // Microsoft YaHei (Windows system font) - Regular  
-            ("Microsoft YaHei", "C:\\Windows\\Fonts\\msyh.ttc"),
-            // Linux packaged fonts (if installed)
-            ("Noto Serif CJK TC", "/usr/share/fonts/opentype/noto/NotoSerifCJK-Regular.ttc"),
-            ("Noto Serif CJK TC", "/usr/share/fonts/opentype/noto/NotoSerifCJKtc-Regular.otf"),
-            // SimSun Bold TTF (fontdue lookup currently returns missing-glyph for many CJK chars)
-            ("SimSun Bold", "C:\\Windows\\Fonts\\simsunb.ttf"),
-        ];
-        
-        for (font_name, font_path) in font_paths {
-            if Path::new(font_path).exists() {
-                println!("Loading Chinese font: {} from {}", font_name, font_path);
-                let font_data = std::fs::read(font_path)?;
-                let font = Font::from_bytes(font_data.clone(), FontSettings::default())
-                    .map_err(|e| anyhow!("Failed to load Chinese font from {}: {}", font_path, e))?;
-                return Ok((
-                    font,
-                    font_name.to_string(),
-                    font_path.to_string(),
-                    font_data,
-                ));
-            }
+            metrics_cache: GlyphMetricsCache::shared(),
         }
-        
-        Err(anyhow!("No suitable Chinese font found"))
     }
-    
-    /// Load a high-quality English font
-    fn load_english_font() -> Result<(Font, String, String, Vec<u8>)> {
-        // Priority order for English fonts
-        let font_paths = vec![
-            // Bundled open-source serif for print-ready portable PDFs.
-            ("EB Garamond", concat!(env!("CARGO_MANIFEST_DIR"), "/assets/fonts/EBGaramond-Regular.ttf")),
-            ("Noto Serif", concat!(env!("CARGO_MANIFEST_DIR"), "/assets/fonts/NotoSerif-Regular.ttf")),
-            // Linux packaged open-source serif fallbacks.
-            ("Noto Serif", "/usr/share/fonts/truetype/noto/NotoSerif-Regular.ttf"),
-            ("Liberation Serif", "/usr/share/fonts/truetype/liberation2/LiberationSerif-Regular.ttf"),
-            // Windows system serif options.
-            ("Garamond", "C:\\Windows\\Fonts\\GARABD.TTF"),
-            ("Georgia", "C:\\Windows\\Fonts\\georgia.ttf"),
-            // Times New Roman (fallback)
-            ("Times New Roman", "C:\\Windows\\Fonts\\times.ttf"),
-            // Last-resort Linux serif
-            ("DejaVu Serif", "/usr/share/fonts/truetype/dejavu/DejaVuSerif.ttf"),
-        ];
-        
-        for (font_name, font_path) in font_paths {
-            if Path::new(font_path).exists() {
-                println!("Loading English font: {} from {}", font_name, font_path);
-                let font_data = std::fs::read(font_path)?;
-                let font = Font::from_bytes(font_data.clone(), FontSettings::default())
-                    .map_err(|e| anyhow!("Failed to load English font from {}: {}", font_path, e))?;
-                return Ok((font, font_name.to_string(), font_path.to_string(), font_data));
-            }
-        }
-        
-        Err(anyhow!("No suitable English font found"))
+
+    /// Parse font file data, selecting the collection face that matches `font_name`
+    fn load_font_data(font_name: &str, font_path: &str, font_data: Vec<u8>) -> Result<LoadedFont> {
+        let face_index = select_face_index(&font_data, font_name);
+        Self::load_font_face(font_name, font_path, font_data, face_index)
+    }
+
+    fn load_font_face(font_name: &str, font_path: &str, font_data: Vec<u8>, face_index: u32) -> Result<LoadedFont> {
+        let font = Font::from_bytes(font_data.clone(), face_settings(face_index))
+            .map_err(|e| anyhow!("Failed to load font {}: {}", font_name, e))?;
+        Ok((font, font_name.to_string(), font_path.to_string(), font_data, face_index))
     }
     
+    /// Load a registered face, reading its data from disk if needed
+    fn load_registered_face(face: &RegisteredFace, role: &str) -> Result<LoadedFont> {
+        let font_path = face.source_name();
+        let font_data = face.load_data()?;
+        let loaded = Self::load_font_face(&face.family, &font_path, font_data, face.face_index)
+            .map_err(|e| anyhow!("Failed to load {} font from {}: {}", role, font_path, e))?;
+        println!(
+            "Loading {} font: {} from {}{}",
+            role,
+            face.family,
+            font_path,
+            describe_face(&loaded.3, loaded.4)
+        );
+        Ok(loaded)
+    }
+
     /// Set PDF generation options
     pub fn set_options(
         &mut self,
@@ -187,55 +319,283 @@ impl FontContext {
         self.paragraph_spacing = paragraph_spacing;
     }
     
-    /// Get font metrics for a character, with caching
-    pub fn get_char_metrics(&mut self, ch: char, is_chinese: bool) -> &fontdue::Metrics {
-        let font = if is_chinese { &self.chinese_font } else { &self.english_font };
-        let font_size = if is_chinese { self.font_size_chinese } else { self.font_size_english };
-        
-        if !self.char_metrics.contains_key(&ch) {
-            let metrics = font.metrics(ch, font_size);
-            self.char_metrics.insert(ch, metrics);
+    /// Face in slot `slot` of the Chinese or English chain (0 = primary font)
+    pub fn face(&self, is_chinese: bool, slot: usize) -> Option<FaceRef<'_>> {
+        if slot == 0 {
+            return Some(if is_chinese {
+                FaceRef {
+                    font: &self.chinese_font,
+                    name: &self.chinese_font_name,
+                    path: &self.chinese_font_path,
+                    data: &self.chinese_font_data,
+                    face_index: self.chinese_font_index,
+                }
+            } else {
+                FaceRef {
+                    font: &self.english_font,
+                    name: &self.english_font_name,
+                    path: &self.english_font_path,
+                    data: &self.english_font_data,
+                    face_index: self.english_font_index,
+                }
+            });
+        }
+        let chain = if is_chinese { &self.chinese_fallbacks } else { &self.english_fallbacks };
+        chain.get(slot - 1).map(|fallback| FaceRef {
+            font: &fallback.font,
+            name: &fallback.name,
+            path: &fallback.path,
+            data: &fallback.data,
+            face_index: fallback.face_index,
+        })
+    }
+
+    /// Font in slot `slot` of the chain, falling back to the primary font
+    pub fn font_for_slot(&self, is_chinese: bool, slot: usize) -> &Font {
+        self.face(is_chinese, slot)
+            .or_else(|| self.face(is_chinese, 0))
+            .map(|face| face.font)
+            .expect("primary font is always present")
+    }
+
+    /// Slot of the first font in the chain that has a glyph for `ch` (0 if none does)
+    pub fn font_slot(&self, ch: char, is_chinese: bool) -> usize {
+        if let Some(slot) = self.script_slot(ch, &[], is_chinese) {
+            return slot;
+        }
+        let (primary, chain) = if is_chinese {
+            (&self.chinese_font, &self.chinese_fallbacks)
+        } else {
+            (&self.english_font, &self.english_fallbacks)
+        };
+        if primary.lookup_glyph_index(ch) != 0 {
+            return 0;
+        }
+        chain
+            .iter()
+            .position(|fallback| fallback.font.lookup_glyph_index(ch) != 0)
+            .map(|index| index + 1)
+            .unwrap_or(0)
+    }
+
+    /// Chain slot of the first font drawing a whole grapheme cluster (base and
+    /// marks), so an accent is never split from its letter; variation selectors
+    /// and joiners need no glyph. Clusters no font covers follow their base character.
+    pub fn cluster_slot(&self, cluster: &str, is_chinese: bool) -> usize {
+        let mut chars = cluster.chars();
+        let Some(base) = chars.next() else {
+            return 0;
+        };
+        let marks: Vec<char> = chars.filter(|&ch| !is_default_ignorable(ch)).collect();
+        if marks.is_empty() {
+            return self.font_slot(base, is_chinese);
+        }
+        if let Some(slot) = self.script_slot(base, &marks, is_chinese) {
+            return slot;
+        }
+        let fallbacks = if is_chinese { self.chinese_fallbacks.len() } else { self.english_fallbacks.len() };
+        (0..=fallbacks)
+            .find(|&slot| {
+                let font = self.font_for_slot(is_chinese, slot);
+                std::iter::once(base).chain(marks.iter().copied()).all(|ch| font.lookup_glyph_index(ch) != 0)
+            })
+            .unwrap_or_else(|| self.font_slot(base, is_chinese))
+    }
+
+    /// Slot of the face chosen for the script of `base`, if it draws `base` and `marks`
+    fn script_slot(&self, base: char, marks: &[char], is_chinese: bool) -> Option<usize> {
+        let chain = if is_chinese { &self.chinese_fallbacks } else { &self.english_fallbacks };
+        let script = base.script();
+        chain
+            .iter()
+            .position(|fallback| {
+                fallback.script == Some(script)
+                    && std::iter::once(base).chain(marks.iter().copied()).all(|ch| fallback.font.lookup_glyph_index(ch) != 0)
+            })
+            .map(|index| index + 1)
+    }
+
+    /// PDF font resource name for a chain slot: `chinese`, `english`, `chinese_fb1`, ...
+    pub fn resource_name(is_chinese: bool, slot: usize) -> String {
+        let base = if is_chinese { "chinese" } else { "english" };
+        if slot == 0 {
+            base.to_string()
+        } else {
+            format!("{}_fb{}", base, slot)
+        }
+    }
+
+    /// Resource name for the same face written top to bottom (Identity-V): `chinese_v`, ...
+    pub fn vertical_resource_name(is_chinese: bool, slot: usize) -> String {
+        format!("{}_v", Self::resource_name(is_chinese, slot))
+    }
+
+    /// Whether a resource name refers to a vertical (Identity-V) font
+    pub fn is_vertical_resource(name: &str) -> bool {
+        name.ends_with("_v")
+    }
+
+    /// Inverse of `resource_name` and `vertical_resource_name`: (is_chinese, slot)
+    pub fn parse_resource_name(name: &str) -> Option<(bool, usize)> {
+        let name = name.strip_suffix("_v").unwrap_or(name);
+        let (base, slot) = match name.split_once("_fb") {
+            Some((base, slot)) => (base, slot.parse().ok()?),
+            None => (name, 0),
+        };
+        match base {
+            "chinese" => Some((true, slot)),
+            "english" => Some((false, slot)),
+            _ => None,
+        }
+    }
+
+    /// Split text into runs drawn with one font each, by the first font in the chain
+    /// covering each grapheme cluster. Devanagari, Siddham and Tibetan get runs
+    /// of their own, so the shaper forms their conjuncts and places their vowel
+    /// signs. Returns (slot, text) pairs.
+    pub fn split_font_runs(&self, text: &str, is_chinese: bool) -> Vec<(usize, String)> {
+        let mut runs: Vec<(usize, String)> = Vec::new();
+        let mut run_script = None;
+        for cluster in syllables::clusters(text) {
+            let slot = self.cluster_slot(cluster, is_chinese);
+            let script = cluster.chars().next().and_then(syllables::syllabic_script);
+            match runs.last_mut() {
+                Some((last_slot, run)) if *last_slot == slot && script == run_script => run.push_str(cluster),
+                _ => runs.push((slot, cluster.to_string())),
+            }
+            run_script = script;
         }
-        
-        &self.char_metrics[&ch]
+        runs
+    }
+
+    /// Cache key of the font in `slot` of the Chinese or English chain
+    pub fn font_key(&self, is_chinese: bool, slot: usize) -> FontKey {
+        let face = self
+            .face(is_chinese, slot)
+            .or_else(|| self.face(is_chinese, 0))
+            .expect("primary font is always present");
+        FontKey::new(face.font, face.face_index)
+    }
+
+    /// Glyph metrics cache used for measuring text
+    pub fn metrics_cache(&self) -> &GlyphMetricsCache {
+        &self.metrics_cache
+    }
+
+    /// Use `cache` instead of the process-wide cache, e.g. to share one between layout threads
+    pub fn set_metrics_cache(&mut self, cache: GlyphMetricsCache) {
+        self.metrics_cache = cache;
+    }
+
+    /// Shape a run drawn entirely with the font in `slot`, with the features set for
+    /// its script. Glyph positions are in font units.
+    pub fn shape_run(&self, text: &str, is_chinese: bool, slot: usize, vertical: bool) -> Arc<[ShapedGlyph]> {
+        let features = self.font_features.for_text(text).to_vec();
+        let font = self.font_key(is_chinese, slot);
+        let key = ShapeKey { font, text: text.to_string(), vertical, features };
+        self.metrics_cache.shaped(key, || {
+            let face = self
+                .face(is_chinese, slot)
+                .or_else(|| self.face(is_chinese, 0))
+                .expect("primary font is always present");
+            shaping::shape(face.data, face.face_index, text, vertical, self.font_features.for_text(text))
+                .unwrap_or_else(|e| {
+                    if first_shaping_failure(font) {
+                        eprintln!("⚠️ Shaping with {} failed, using unshaped glyphs: {}", face.name, e);
+                    }
+                    self.unshaped_glyphs(text, is_chinese, slot, vertical)
+                })
+        })
+    }
+
+    /// One glyph per character with its nominal advance, for faces the shaper cannot read
+    fn unshaped_glyphs(&self, text: &str, is_chinese: bool, slot: usize, vertical: bool) -> Vec<ShapedGlyph> {
+        let font = self.font_for_slot(is_chinese, slot);
+        text.char_indices()
+            .map(|(cluster, ch)| {
+                let glyph_id = font.lookup_glyph_index(ch);
+                let advance = self.pdf_advance(is_chinese, slot, glyph_id, vertical) * font.units_per_em() / 1000.0;
+                ShapedGlyph {
+                    glyph_id,
+                    cluster,
+                    x_advance: if vertical { 0 } else { advance.round() as i32 },
+                    y_advance: if vertical { -advance.round() as i32 } else { 0 },
+                    x_offset: 0,
+                    y_offset: 0,
+                }
+            })
+            .collect()
+    }
+
+    /// Advance a PDF viewer applies to a glyph of the font in `slot`, in 1/1000 em:
+    /// the `hmtx` width (`/W`), or down a column the `vmtx` height (`/W2`), which
+    /// defaults to ascender minus descender like the shaper's.
+    pub fn pdf_advance(&self, is_chinese: bool, slot: usize, glyph_id: u16, vertical: bool) -> f32 {
+        let font = self.font_for_slot(is_chinese, slot);
+        let units_per_em = font.units_per_em();
+        let metrics = self.metrics_cache.glyph_metrics(self.font_key(is_chinese, slot), font, glyph_id);
+        let advance = if !vertical {
+            metrics.advance_width
+        } else if metrics.advance_height > 0.0 {
+            metrics.advance_height
+        } else {
+            font.horizontal_line_metrics(units_per_em)
+                .map(|line| line.ascent - line.descent)
+                .unwrap_or(units_per_em)
+        };
+        (advance * 1000.0 / units_per_em).round()
+    }
+
+    /// Metrics for a character at the current font size, from the font that draws it
+    pub fn get_char_metrics(&self, ch: char, is_chinese: bool) -> GlyphMetrics {
+        let font_size = if is_chinese { self.font_size_chinese } else { self.font_size_english };
+        let slot = self.font_slot(ch, is_chinese);
+        let font = self.font_for_slot(is_chinese, slot);
+
+        self.metrics_cache
+            .glyph_metrics(self.font_key(is_chinese, slot), font, font.lookup_glyph_index(ch))
+            .scaled(font_size, font.units_per_em())
     }
     
-    /// NEW: Accurate width with kerning + tracking
+    /// NEW: Accurate width of the shaped text (ligatures, GPOS kerning, CJK
+    /// features) plus tracking between glyphs.
+    ///
+    /// In vertical writing mode Chinese text is shaped top to bottom and measured
+    /// down the column.
     pub fn calculate_text_width(&mut self, text: &str, is_chinese: bool) -> f32 {
-        let font = if is_chinese { &self.chinese_font } else { &self.english_font };
         let size = if is_chinese { self.font_size_chinese } else { self.font_size_english };
         let tracking = if is_chinese { self.tracking_chinese } else { self.tracking_english };
-        let scale = size / font.units_per_em() as f32;
-
-        let chars: Vec<char> = text.chars().collect();
-        if chars.is_empty() { return 0.0; }
+        let vertical = is_chinese && self.writing_mode == WritingMode::Vertical;
 
+        // Shaped advances are in font units; scale them to the current size
         let mut width = 0.0;
-        for (i, &ch) in chars.iter().enumerate() {
-            // Get metrics without borrowing issues
-            let font_for_metrics = if is_chinese { &self.chinese_font } else { &self.english_font };
-            let font_size_for_metrics = if is_chinese { self.font_size_chinese } else { self.font_size_english };
-            
-            if !self.char_metrics.contains_key(&ch) {
-                let metrics = font_for_metrics.metrics(ch, font_size_for_metrics);
-                self.char_metrics.insert(ch, metrics);
+        for (slot, run) in self.split_font_runs(text, is_chinese) {
+            let scale = size / self.font_for_slot(is_chinese, slot).units_per_em();
+            for glyph in self.shape_run(&run, is_chinese, slot, vertical).iter() {
+                let advance = if vertical { -glyph.y_advance } else { glyph.x_advance };
+                width += advance as f32 * scale;
             }
-            
-            let metrics = &self.char_metrics[&ch];
-            width += metrics.advance_width;
+        }
 
-            if i < chars.len() - 1 {
-                // Kerning (fontdue gives it in font units)
-                if let Some(kern) = font.horizontal_kern(ch, chars[i + 1], size) {
-                    width += kern * scale;
-                }
-                // Tracking (classic print-book value)
-                width += (tracking / 1000.0) * size;
-            }
+        // Tracking between grapheme clusters (classic print-book value); never
+        // between a letter and its accents
+        let clusters = syllables::clusters(text).len();
+        if clusters > 1 {
+            width += (clusters - 1) as f32 * (tracking / 1000.0) * size;
         }
         width
     }
     
+    /// Space added between two adjacent characters of a line: CJK–Latin spacing
+    /// between an ideograph and a Latin letter or digit, in points
+    pub fn script_gap(&self, before: char, after: char) -> f32 {
+        if itemize::is_cjk_latin_boundary(before, after) {
+            self.cjk_latin_spacing * self.font_size_chinese
+        } else {
+            0.0
+        }
+    }
+
     /// Get line height based on font size and line spacing
     pub fn get_line_height(&self, is_chinese: bool) -> f32 {
         let font_size = if is_chinese { self.font_size_chinese } else { self.font_size_english };
@@ -253,7 +613,105 @@ impl FontContext {
     }
 }
 
+impl From<LoadedFont> for FallbackFont {
+    fn from((font, name, path, data, face_index): LoadedFont) -> Self {
+        FallbackFont { font, name, path, data, face_index, script: None }
+    }
+}
+
 /// Initialize fonts for the PDF creator
 pub fn initialize_fonts() -> Result<FontContext> {
     FontContext::initialize_fonts()
 }
+
+/// Number of faces in a font file (1 for plain .ttf/.otf files)
+pub fn face_count(font_data: &[u8]) -> u32 {
+    ttf_parser::fonts_in_collection(font_data).unwrap_or(1)
+}
+
+/// Family, typographic family and full names recorded in a face's name table
+pub fn face_names(font_data: &[u8], face_index: u32) -> Vec<String> {
+    let face = match ttf_parser::Face::parse(font_data, face_index) {
+        Ok(face) => face,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut names = Vec::new();
+    for name in face.names() {
+        let wanted_id = matches!(
+            name.name_id,
+            ttf_parser::name_id::FAMILY | ttf_parser::name_id::TYPOGRAPHIC_FAMILY | ttf_parser::name_id::FULL_NAME
+        );
+        if !wanted_id {
+            continue;
+        }
+        if let Some(value) = name.to_string() {
+            if !names.contains(&value) {
+                names.push(value);
+            }
+        }
+    }
+    names
+}
+
+/// Pick the face of a .ttc/.otc collection whose family or full name matches `wanted`.
+///
+/// Matching ignores case and spaces; single-face files and collections without a
+/// matching face resolve to index 0.
+pub fn select_face_index(font_data: &[u8], wanted: &str) -> u32 {
+    let count = face_count(font_data);
+    if count <= 1 {
+        return 0;
+    }
+
+    let wanted = normalize_face_name(wanted);
+    (0..count)
+        .find(|&index| {
+            face_names(font_data, index)
+                .iter()
+                .any(|name| normalize_face_name(name) == wanted)
+        })
+        .unwrap_or(0)
+}
+
+fn normalize_face_name(name: &str) -> String {
+    name.chars()
+        .filter(|ch| !ch.is_whitespace())
+        .flat_map(|ch| ch.to_lowercase())
+        .collect()
+}
+
+/// Human-readable note on which collection face was loaded (empty for single-face files)
+fn describe_face(font_data: &[u8], face_index: u32) -> String {
+    let count = face_count(font_data);
+    if count <= 1 {
+        return String::new();
+    }
+    let name = face_names(font_data, face_index)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| "unnamed".to_string());
+    format!(" (collection face {} of {}: {})", face_index, count, name)
+}
+
+fn face_settings(face_index: u32) -> FontSettings {
+    FontSettings {
+        collection_index: face_index,
+        ..FontSettings::default()
+    }
+}
+
+/// Variation selectors and joiners, which select or join glyphs rather than add one
+fn is_default_ignorable(ch: char) -> bool {
+    matches!(ch, '\u{200C}' | '\u{200D}' | '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}')
+}
+
+/// True the first time shaping with `font` fails, so each face is reported once
+/// rather than for every run set in it
+fn first_shaping_failure(font: FontKey) -> bool {
+    static FAILED: OnceLock<Mutex<HashSet<FontKey>>> = OnceLock::new();
+    FAILED
+        .get_or_init(Default::default)
+        .lock()
+        .is_ok_and(|mut failed| failed.insert(font))
+}
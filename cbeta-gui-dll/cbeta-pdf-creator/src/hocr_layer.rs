@@ -3,6 +3,7 @@
 //! Creates hOCR markup that allows text selection, copying, and searching
 //! in the generated PDF while maintaining the visual layout.
 
+use crate::syllables;
 use crate::typography::{FormattedParagraph, FormattedLine};
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
@@ -125,11 +126,11 @@ impl HocrGenerator {
         let mut words = Vec::new();
         
         if line.is_chinese {
-            // For Chinese, each character is a "word"
+            // For Chinese, each character (with any marks or variation selector) is a "word"
             let mut x_offset = line.x;
             let char_height = line.height;
             
-            for ch in line.text.chars() {
+            for cluster in syllables::clusters(&line.text) {
                 self.word_counter += 1;
                 let word_id = format!("word_{}", self.word_counter);
                 
@@ -139,7 +140,7 @@ impl HocrGenerator {
                 let word = HocrWord {
                     id: word_id,
                     bbox: (x_offset, line.y, x_offset + char_width, line.y + char_height),
-                    text: ch.to_string(),
+                    text: cluster.to_string(),
                     confidence: 0.95, // High confidence for rendered text
                 };
                 
@@ -156,7 +157,7 @@ impl HocrGenerator {
                 let word_id = format!("word_{}", self.word_counter);
                 
                 // Estimate word width (this would be more accurate with font metrics)
-                let word_width = word_text.len() as f32 * line.font_size * 0.6; // Rough estimate
+                let word_width = syllables::clusters(word_text).len() as f32 * line.font_size * 0.6; // Rough estimate
                 
                 let word = HocrWord {
                     id: word_id,
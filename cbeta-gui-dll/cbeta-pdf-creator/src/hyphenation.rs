@@ -0,0 +1,288 @@
+//! Pattern hyphenation
+//!
+//! Frank Liang's algorithm, as used by TeX: every pattern that matches part of a
+//! word votes on the gaps it covers, and odd votes mark places a hyphen may go.
+//! The US English patterns (Knuth's `hyphen.tex`) and TeX's exception list are
+//! embedded, as are the hyph-utf8 Sanskrit patterns used for IAST-romanised terms
+//! such as *nirvāṇa*. Other languages load hyph-utf8 style pattern files.
+
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+const EN_US_PATTERNS: &str = include_str!("../assets/hyphenation/hyph-en-us.pat.txt");
+const SANSKRIT_PATTERNS: &str = include_str!("../assets/hyphenation/hyph-sa.pat.txt");
+
+/// The exceptions shipped with plain TeX's `hyphen.tex`
+const EN_US_EXCEPTIONS: &str = "as-so-ciate as-so-ciates dec-li-na-tion oblig-a-tory phil-an-thropic \
+    present presents project projects reci-procity re-cog-ni-zance ref-or-ma-tion ret-ri-bu-tion ta-ble";
+
+/// Letters that only occur in IAST transliteration
+const IAST_LETTERS: &str = "āīūṛṝḷḹṃṁḥñṅṇṭḍśṣĀĪŪṚṜḶḸṂṀḤÑṄṆṬḌŚṢ";
+
+/// Language tag of the built-in US English patterns
+pub const ENGLISH: &str = "en-us";
+/// Language tag of the built-in Sanskrit patterns, used for IAST words
+pub const SANSKRIT: &str = "sa";
+
+/// Liang patterns and exceptions for one language
+#[derive(Debug, Default)]
+pub struct Hyphenator {
+    /// Pattern letters (with `.` marking a word edge) to the votes between them
+    patterns: HashMap<String, Vec<u8>>,
+    /// Whole words to their hyphen positions
+    exceptions: HashMap<String, Vec<usize>>,
+    longest_pattern: usize,
+}
+
+impl Hyphenator {
+    /// Build from pattern text (`.ach4 a1b2c ...`) and an exception list
+    /// (`as-so-ciate ...`), both whitespace separated; `%` starts a comment.
+    pub fn from_patterns(patterns: &str, exceptions: &str) -> Self {
+        let mut hyphenator = Self::default();
+        for pattern in entries(patterns) {
+            hyphenator.add_pattern(pattern);
+        }
+        for exception in entries(exceptions) {
+            hyphenator.add_exception(exception);
+        }
+        hyphenator
+    }
+
+    /// Load a hyph-utf8 pattern file (`hyph-de-1996.pat.txt`) and optionally its
+    /// exception file (`.hyp.txt`)
+    pub fn load(patterns_path: &Path, exceptions_path: Option<&Path>) -> Result<Self> {
+        let patterns = std::fs::read_to_string(patterns_path)
+            .map_err(|e| anyhow!("Failed to read hyphenation patterns {}: {}", patterns_path.display(), e))?;
+        let exceptions = match exceptions_path {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read hyphenation exceptions {}: {}", path.display(), e))?,
+            None => String::new(),
+        };
+        let hyphenator = Self::from_patterns(&patterns, &exceptions);
+        if hyphenator.patterns.is_empty() {
+            return Err(anyhow!("No hyphenation patterns in {}", patterns_path.display()));
+        }
+        Ok(hyphenator)
+    }
+
+    fn add_pattern(&mut self, pattern: &str) {
+        let mut letters = String::new();
+        let mut votes = vec![0u8];
+        for ch in pattern.nfc() {
+            match ch.to_digit(10) {
+                Some(vote) => *votes.last_mut().unwrap() = vote as u8,
+                None => {
+                    letters.push(ch);
+                    votes.push(0);
+                }
+            }
+        }
+        self.longest_pattern = self.longest_pattern.max(votes.len() - 1);
+        self.patterns.insert(letters, votes);
+    }
+
+    fn add_exception(&mut self, exception: &str) {
+        let mut word = String::new();
+        let mut positions = Vec::new();
+        let mut letters = 0;
+        for ch in exception.nfc() {
+            if ch == '-' {
+                positions.push(letters);
+            } else {
+                word.extend(ch.to_lowercase());
+                letters += 1;
+            }
+        }
+        self.exceptions.insert(word, positions);
+    }
+
+    /// Character positions in `word` a hyphen may go before, keeping at least
+    /// `left_min` characters before the first and `right_min` after the last.
+    /// `word` should be letters only, in NFC.
+    pub fn hyphenate(&self, word: &str, left_min: usize, right_min: usize) -> Vec<usize> {
+        let lower: Vec<char> = word.chars().map(|ch| ch.to_lowercase().next().unwrap_or(ch)).collect();
+        let len = lower.len();
+        if len < left_min.max(1) + right_min.max(1) {
+            return Vec::new();
+        }
+        let allowed = |position: &usize| *position >= left_min.max(1) && len - *position >= right_min.max(1);
+
+        let key: String = lower.iter().collect();
+        if let Some(positions) = self.exceptions.get(&key) {
+            return positions.iter().copied().filter(allowed).collect();
+        }
+
+        // Votes for the gaps of ".word.": gap i sits before character i of the padded word.
+        let padded: Vec<char> = std::iter::once('.').chain(lower).chain(std::iter::once('.')).collect();
+        let mut votes = vec![0u8; padded.len() + 1];
+        for start in 0..padded.len() {
+            let mut letters = String::new();
+            for &ch in &padded[start..padded.len().min(start + self.longest_pattern)] {
+                letters.push(ch);
+                if let Some(pattern) = self.patterns.get(&letters) {
+                    for (offset, &vote) in pattern.iter().enumerate() {
+                        let gap = &mut votes[start + offset];
+                        *gap = (*gap).max(vote);
+                    }
+                }
+            }
+        }
+
+        // The gap before word character k is gap k + 1 of the padded word.
+        (1..len).filter(|k| votes[k + 1] % 2 == 1).filter(allowed).collect()
+    }
+}
+
+/// Hyphenation settings for English-side text: the selected language, the
+/// languages available, and the minimum fragment lengths.
+#[derive(Debug, Clone)]
+pub struct HyphenationSettings {
+    pub enabled: bool,
+    /// Language tag of the patterns used for English-side text
+    pub language: String,
+    /// Fewest characters left before a hyphen.
+    pub left_min: usize,
+    /// Fewest characters carried over after a hyphen.
+    pub right_min: usize,
+    languages: HashMap<String, Arc<Hyphenator>>,
+}
+
+impl Default for HyphenationSettings {
+    fn default() -> Self {
+        let mut languages = HashMap::new();
+        languages.insert(ENGLISH.to_string(), english());
+        languages.insert(SANSKRIT.to_string(), sanskrit());
+        Self {
+            enabled: true,
+            language: ENGLISH.to_string(),
+            // Do not hyphenate with fewer than 3 letters before/after the hyphen.
+            left_min: 3,
+            right_min: 3,
+            languages,
+        }
+    }
+}
+
+impl HyphenationSettings {
+    /// Make `hyphenator` available under `language` (replacing any earlier one)
+    pub fn add_language(&mut self, language: &str, hyphenator: Hyphenator) {
+        self.languages.insert(language.to_ascii_lowercase(), Arc::new(hyphenator));
+    }
+
+    /// Load a pattern file (and optional exception file) for `language`
+    pub fn load_language(&mut self, language: &str, patterns_path: &Path, exceptions_path: Option<&Path>) -> Result<()> {
+        let hyphenator = Hyphenator::load(patterns_path, exceptions_path)?;
+        self.add_language(language, hyphenator);
+        Ok(())
+    }
+
+    /// Select the patterns for English-side text. Returns false for a language
+    /// that has not been loaded, leaving the selection unchanged.
+    pub fn set_language(&mut self, language: &str) -> bool {
+        let language = language.to_ascii_lowercase();
+        if !self.languages.contains_key(&language) {
+            return false;
+        }
+        self.language = language;
+        true
+    }
+
+    pub fn has_language(&self, language: &str) -> bool {
+        self.languages.contains_key(&language.to_ascii_lowercase())
+    }
+
+    /// Character positions in `word` a hyphen may go before. Words with IAST
+    /// letters use the Sanskrit patterns whatever the selected language; the
+    /// letters may be precomposed or written with combining marks.
+    pub fn hyphenate(&self, word: &str) -> Vec<usize> {
+        if !self.enabled {
+            return Vec::new();
+        }
+
+        // Compose each letter with its marks so the patterns see the NFC forms they
+        // are written in; hyphens only go between letters, never before a mark.
+        let mut letters: Vec<(usize, String)> = Vec::new();
+        for (i, ch) in word.chars().enumerate() {
+            match letters.last_mut() {
+                Some((_, letter)) if is_combining_mark(ch) => letter.push(ch),
+                _ => letters.push((i, ch.to_string())),
+            }
+        }
+        let mut text = String::new();
+        let mut starts = Vec::with_capacity(letters.len());
+        for (_, letter) in &letters {
+            starts.push(text.chars().count());
+            text.extend(letter.nfc());
+        }
+
+        let is_iast = text.chars().any(|ch| IAST_LETTERS.contains(ch) || is_combining_mark(ch));
+        let language = if is_iast { SANSKRIT } else { self.language.as_str() };
+        let Some(hyphenator) = self.languages.get(language) else {
+            return Vec::new();
+        };
+        let points = hyphenator.hyphenate(&text, 1, 1);
+        let composed: Vec<String> = letters.iter().map(|(_, letter)| letter.nfc().collect()).collect();
+        let mut breaks: Vec<usize> = (1..letters.len())
+            .filter(|&k| points.contains(&starts[k]))
+            .map(|k| if is_iast { split_iast_cluster(&composed, k) } else { k })
+            .filter(|&k| k >= self.left_min && letters.len() - k >= self.right_min)
+            .collect();
+        breaks.dedup();
+        breaks.into_iter().map(|k| letters[k].0).collect()
+    }
+}
+
+/// The Sanskrit patterns put a whole consonant cluster on the next line (*ni-rvā-ṇa*),
+/// as Indic scripts do; romanised text carries all but the last consonant over
+/// instead (*nir-vā-ṇa*). Aspirates such as `dh` count as one consonant.
+fn split_iast_cluster(letters: &[String], k: usize) -> usize {
+    let mut last_consonant = k;
+    let mut consonants = 0;
+    let mut i = k;
+    while i < letters.len() && !is_iast_vowel(&letters[i]) {
+        last_consonant = i;
+        consonants += 1;
+        let aspirated = letters.get(i + 1).is_some_and(|next| next.to_lowercase() == "h")
+            && matches!(letters[i].to_lowercase().as_str(), "k" | "g" | "c" | "j" | "ṭ" | "ḍ" | "t" | "d" | "p" | "b");
+        i += if aspirated { 2 } else { 1 };
+    }
+    if consonants >= 2 && i < letters.len() { last_consonant } else { k }
+}
+
+/// IAST vowel letters, including r̥ and l̥ written with a combining ring below
+fn is_iast_vowel(letter: &str) -> bool {
+    let lower = letter.to_lowercase();
+    let mut chars = lower.chars();
+    match (chars.next(), chars.next()) {
+        (Some('r' | 'l'), Some('\u{325}')) => true,
+        (Some(ch), _) => "aāiīuūṛṝḷḹeoáàíìúùéèóòïü".contains(ch),
+        (None, _) => false,
+    }
+}
+
+/// The embedded US English patterns and TeX exceptions, parsed once
+pub fn english() -> Arc<Hyphenator> {
+    static ENGLISH_HYPHENATOR: OnceLock<Arc<Hyphenator>> = OnceLock::new();
+    ENGLISH_HYPHENATOR
+        .get_or_init(|| Arc::new(Hyphenator::from_patterns(EN_US_PATTERNS, EN_US_EXCEPTIONS)))
+        .clone()
+}
+
+/// The embedded Sanskrit patterns (all scripts, including IAST), parsed once
+pub fn sanskrit() -> Arc<Hyphenator> {
+    static SANSKRIT_HYPHENATOR: OnceLock<Arc<Hyphenator>> = OnceLock::new();
+    SANSKRIT_HYPHENATOR
+        .get_or_init(|| Arc::new(Hyphenator::from_patterns(SANSKRIT_PATTERNS, "")))
+        .clone()
+}
+
+/// Whitespace-separated entries with `%` comments removed
+fn entries(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(|line| line.split('%').next().unwrap_or(""))
+        .flat_map(str::split_whitespace)
+}
@@ -0,0 +1,75 @@
+//! Script run itemisation
+//!
+//! Splits the text of a line into runs set in the Chinese or the English fonts.
+//! Ideographs, kana, bopomofo, hangul and full-width punctuation take the Chinese
+//! fonts and letters of other scripts the English ones; spaces, digits and other
+//! shared punctuation follow the paragraph, and combining marks the character
+//! they are attached to.
+
+use unicode_script::{Script, UnicodeScript};
+
+/// Whether `ch` is set in the Chinese fonts (`Some(true)`), the English fonts
+/// (`Some(false)`), or those of its paragraph (`None`)
+pub fn script_role(ch: char) -> Option<bool> {
+    match ch.script() {
+        Script::Han | Script::Hiragana | Script::Katakana | Script::Bopomofo | Script::Hangul => Some(true),
+        Script::Common | Script::Inherited | Script::Unknown => {
+            if is_cjk_symbol(ch) {
+                Some(true)
+            } else {
+                None
+            }
+        }
+        _ => Some(false),
+    }
+}
+
+/// Whether each character of a line is set in the Chinese fonts
+pub fn char_roles(chars: &[char], is_chinese: bool) -> Vec<bool> {
+    let mut roles: Vec<bool> = Vec::with_capacity(chars.len());
+    for &ch in chars {
+        let role = match script_role(ch) {
+            Some(role) => role,
+            None if ch.script() == Script::Inherited => roles.last().copied().unwrap_or(is_chinese),
+            None => is_chinese,
+        };
+        roles.push(role);
+    }
+    roles
+}
+
+/// Runs of `text` as (start, end, is_chinese), in character indices
+pub fn script_runs(text: &str, is_chinese: bool) -> Vec<(usize, usize, bool)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut runs: Vec<(usize, usize, bool)> = Vec::new();
+    for (i, role) in char_roles(&chars, is_chinese).into_iter().enumerate() {
+        match runs.last_mut() {
+            Some((_, end, run_role)) if *run_role == role => *end = i + 1,
+            _ => runs.push((i, i + 1, role)),
+        }
+    }
+    runs
+}
+
+/// Ideographs, kana and bopomofo
+pub fn is_ideographic(ch: char) -> bool {
+    matches!(ch.script(), Script::Han | Script::Hiragana | Script::Katakana | Script::Bopomofo)
+}
+
+/// Whether CJK–Latin spacing goes between two adjacent characters: an ideograph
+/// next to a letter of another script or a digit (CLREQ 3.2.5)
+pub fn is_cjk_latin_boundary(before: char, after: char) -> bool {
+    let is_latin = |ch: char| ch.is_ascii_digit() || (ch.is_alphanumeric() && script_role(ch) == Some(false));
+    (is_ideographic(before) && is_latin(after)) || (is_latin(before) && is_ideographic(after))
+}
+
+/// CJK symbols and punctuation, full-width forms and vertical forms
+fn is_cjk_symbol(ch: char) -> bool {
+    matches!(ch,
+        '\u{3000}'..='\u{303F}' // CJK symbols and punctuation
+        | '\u{FE10}'..='\u{FE1F}' // Vertical forms
+        | '\u{FE30}'..='\u{FE4F}' // CJK compatibility forms
+        | '\u{FF01}'..='\u{FF60}' // Full-width forms
+        | '\u{FFE0}'..='\u{FFE6}'
+    )
+}
@@ -0,0 +1,111 @@
+//! CJK line-breaking rules (kinsoku)
+//!
+//! Character classes from JIS X 4051 and the W3C Chinese layout requirements
+//! (CLREQ): closing punctuation may not start a line, opening brackets may not
+//! end one, and runs such as "……", "——" or a group of digits stay together.
+
+use crate::itemize;
+use crate::syllables::{self, SyllableBreak};
+use unicode_normalization::char::is_combining_mark;
+
+/// Closing brackets and quotes, full stops, commas and other marks that may not begin a line
+pub fn is_line_start_prohibited(ch: char) -> bool {
+    matches!(
+        ch,
+        // Closing brackets and quotes
+        '）' | '〕' | '］' | '｝' | '〉' | '》' | '」' | '』' | '】' | '〙' | '〗' | '〟' | '’' | '”' | '｠' | '»'
+            | ')' | ']' | '}'
+            // Full stops, commas and dividing punctuation
+            | '。' | '．' | '、' | '，' | '：' | '；' | '？' | '！' | '‼' | '⁇' | '⁈' | '⁉'
+            | '.' | ',' | ':' | ';' | '?' | '!'
+            // Middle dots
+            | '・' | '·' | '‧'
+            // Postfixed units
+            | '％' | '‰' | '℃' | '°' | '%'
+            // Iteration marks, prolonged sound mark and small kana
+            | '々' | '〻' | 'ゝ' | 'ゞ' | 'ヽ' | 'ヾ' | 'ー'
+            | 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'っ' | 'ゃ' | 'ゅ' | 'ょ' | 'ゎ' | 'ゕ' | 'ゖ'
+            | 'ァ' | 'ィ' | 'ゥ' | 'ェ' | 'ォ' | 'ッ' | 'ャ' | 'ュ' | 'ョ' | 'ヮ' | 'ヵ' | 'ヶ'
+            | 'ㇰ'..='ㇿ'
+            // Hyphens and wave dash
+            | '‐' | '゠' | '–' | '〜' | '～'
+    ) || syllables::is_line_start_prohibited(ch)
+}
+
+/// Opening brackets, quotes and prefixed currency signs that may not end a line
+pub fn is_line_end_prohibited(ch: char) -> bool {
+    matches!(
+        ch,
+        '（' | '〔' | '［' | '｛' | '〈' | '《' | '「' | '『' | '【' | '〘' | '〖' | '〝' | '‘' | '“' | '｟' | '«'
+            | '(' | '[' | '{'
+            | '＄' | '￥' | '＃' | '$' | '£' | '¥' | '€' | '#'
+            // Tibetan non-breaking tsheg
+            | '\u{0F0C}'
+    )
+}
+
+/// Whether a line may not be broken between `before` and `after`: doubled
+/// ellipses and dashes, and the digits or letters of one number or Latin word.
+pub fn is_inseparable(before: char, after: char) -> bool {
+    matches!((before, after), ('…', '…') | ('‥', '‥') | ('—', '—') | ('―', '―') | ('⸺', '⸺'))
+        || (is_alphanumeric(before) && is_alphanumeric(after))
+}
+
+/// Whether a line may break before `chars[i]`. Siddham breaks between
+/// syllables (`chars` holding the first character of each cluster) and
+/// Tibetan after the tsheg; Devanagari, like Latin, only at spaces.
+pub fn can_break_before(chars: &[char], i: usize) -> bool {
+    if i == 0 || i >= chars.len() {
+        return true;
+    }
+    let (before, after) = (chars[i - 1], chars[i]);
+    // Decimal points and thousands separators inside a number ("3.14", "1,000")
+    let in_number = i >= 2
+        && is_digit(chars[i - 2])
+        && matches!(before, '.' | ',' | '．' | '，')
+        && is_digit(after);
+    !is_line_end_prohibited(before)
+        && !is_line_start_prohibited(after)
+        && (!is_inseparable(before, after) || between_syllables(before, after))
+        && !after.is_whitespace()
+        && !in_number
+}
+
+fn between_syllables(before: char, after: char) -> bool {
+    syllables::break_between(before.encode_utf8(&mut [0; 4]), after.encode_utf8(&mut [0; 4])) == Some(SyllableBreak::Plain)
+}
+
+fn is_digit(ch: char) -> bool {
+    ch.is_ascii_digit() || ('０'..='９').contains(&ch)
+}
+
+/// Digits and letters of scripts written with spaces between words (with their marks)
+fn is_alphanumeric(ch: char) -> bool {
+    ch.is_ascii_alphanumeric()
+        || is_digit(ch)
+        || ('Ａ'..='Ｚ').contains(&ch)
+        || ('ａ'..='ｚ').contains(&ch)
+        || (ch.is_alphanumeric() && itemize::script_role(ch) == Some(false))
+        || is_combining_mark(ch)
+}
+
+/// Opening brackets and quotes, whose blank half comes before the glyph
+pub fn is_opening_bracket(ch: char) -> bool {
+    matches!(ch, '（' | '〔' | '［' | '｛' | '〈' | '《' | '「' | '『' | '【' | '〘' | '〖' | '〝')
+}
+
+/// Full-width punctuation whose glyph fills only half the em box, so half its
+/// advance can be squeezed out when a line is too long
+pub fn is_compressible(ch: char) -> bool {
+    matches!(
+        ch,
+        '（' | '〔' | '［' | '｛' | '〈' | '《' | '「' | '『' | '【' | '〘' | '〖' | '〝'
+            | '）' | '〕' | '］' | '｝' | '〉' | '》' | '」' | '』' | '】' | '〙' | '〗' | '〟'
+            | '。' | '．' | '、' | '，' | '：' | '；' | '？' | '！' | '・'
+    )
+}
+
+/// Marks that may hang into the margin at the end of a line (burasage)
+pub fn is_hangable(ch: char) -> bool {
+    matches!(ch, '。' | '．' | '，' | '、')
+}
@@ -5,16 +5,30 @@
 
 pub mod accumulator;
 pub mod bilingual_generator;
+pub mod document;
 pub mod typography;
 pub mod hocr_layer;
 pub mod fonts;
+pub mod hyphenation;
+pub mod itemize;
+pub mod kinsoku;
+pub mod ruby;
+pub mod syllables;
+pub mod font_embedding;
+pub mod font_registry;
+pub mod metrics_cache;
+pub mod shaping;
+pub mod styles;
 
 // Re-export commonly used functions and types
 pub use fonts::FontContext;
+pub use font_registry::FontRegistry;
+pub use document::{Block, DocumentBuilder, LayoutMode};
 pub use bilingual_generator::{
     create_bilingual_pdf,
     create_bilingual_pdf_with_context,
     create_bilingual_pdf_side_by_side_with_context,
+    create_vertical_pdf_with_context,
 };
 
 use anyhow::Result;
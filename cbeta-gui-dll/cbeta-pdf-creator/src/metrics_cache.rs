@@ -0,0 +1,213 @@
+//! Glyph metrics cache
+//!
+//! Stores advance widths and outline bounds in font units, keyed by font and
+//! glyph, and shaped runs keyed by font, text and features, and scales them to the
+//! requested size at lookup time. A single cache can therefore serve every font
+//! size (auto font sizing tries dozens) and be shared between threads and layout runs.
+//!
+//! Glyph metrics are bounded by the glyphs of the loaded fonts and are kept for
+//! the life of the cache. Shaped runs are keyed by text, so every new document
+//! adds to them; they are kept up to a fixed number and the least recently used
+//! are dropped beyond it.
+
+use crate::shaping::{FontFeature, ShapedGlyph};
+use fontdue::{Font, OutlineBounds};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Identifies one face: the font file hash plus the face index for collections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontKey {
+    pub file_hash: usize,
+    pub face_index: u32,
+}
+
+impl FontKey {
+    pub fn new(font: &Font, face_index: u32) -> Self {
+        Self { file_hash: font.file_hash(), face_index }
+    }
+}
+
+/// Glyph metrics, in font units when cached and in points after `scaled`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphMetrics {
+    pub advance_width: f32,
+    pub advance_height: f32,
+    pub bounds: OutlineBounds,
+}
+
+impl GlyphMetrics {
+    /// Metrics at `size` points for a font with `units_per_em` units
+    pub fn scaled(&self, size: f32, units_per_em: f32) -> GlyphMetrics {
+        let scale = size / units_per_em;
+        GlyphMetrics {
+            advance_width: self.advance_width * scale,
+            advance_height: self.advance_height * scale,
+            bounds: OutlineBounds {
+                xmin: self.bounds.xmin * scale,
+                ymin: self.bounds.ymin * scale,
+                width: self.bounds.width * scale,
+                height: self.bounds.height * scale,
+            },
+        }
+    }
+}
+
+/// Identifies one shaped run: face, text, direction and the features applied
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShapeKey {
+    pub font: FontKey,
+    pub text: String,
+    pub vertical: bool,
+    pub features: Vec<FontFeature>,
+}
+
+/// Shaped runs kept by default before the least recently used are dropped
+pub const DEFAULT_SHAPED_CAPACITY: usize = 4096;
+
+/// A shaped run and when it was last looked up
+struct ShapedEntry {
+    glyphs: Arc<[ShapedGlyph]>,
+    last_used: AtomicU64,
+}
+
+#[derive(Default)]
+struct CacheTables {
+    glyphs: HashMap<(FontKey, u16), GlyphMetrics>,
+    shaped: HashMap<ShapeKey, ShapedEntry>,
+}
+
+/// Thread-safe glyph metrics cache; clones share the same storage
+#[derive(Clone)]
+pub struct GlyphMetricsCache {
+    tables: Arc<RwLock<CacheTables>>,
+    /// Lookup counter that orders shaped runs by last use
+    clock: Arc<AtomicU64>,
+    shaped_capacity: usize,
+}
+
+impl Default for GlyphMetricsCache {
+    fn default() -> Self {
+        Self::with_shaped_capacity(DEFAULT_SHAPED_CAPACITY)
+    }
+}
+
+impl GlyphMetricsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache that keeps at most `capacity` shaped runs
+    pub fn with_shaped_capacity(capacity: usize) -> Self {
+        Self {
+            tables: Arc::default(),
+            clock: Arc::default(),
+            shaped_capacity: capacity.max(1),
+        }
+    }
+
+    /// Process-wide cache shared by every `FontContext` unless one is set explicitly
+    pub fn shared() -> Self {
+        static SHARED: OnceLock<GlyphMetricsCache> = OnceLock::new();
+        SHARED.get_or_init(GlyphMetricsCache::new).clone()
+    }
+
+    /// Unscaled metrics (font units) for glyph `gid`
+    pub fn glyph_metrics(&self, key: FontKey, font: &Font, gid: u16) -> GlyphMetrics {
+        if let Some(metrics) = self.read(|tables| tables.glyphs.get(&(key, gid)).copied()) {
+            return metrics;
+        }
+
+        // Measuring at one pixel per font unit yields font-unit values.
+        let raw = font.metrics_indexed(gid, font.units_per_em());
+        let metrics = GlyphMetrics {
+            advance_width: raw.advance_width,
+            advance_height: raw.advance_height,
+            bounds: raw.bounds,
+        };
+        self.write(|tables| {
+            tables.glyphs.insert((key, gid), metrics);
+        });
+        metrics
+    }
+
+    /// Advance width of glyph `gid` at `size` points
+    pub fn advance(&self, key: FontKey, font: &Font, gid: u16, size: f32) -> f32 {
+        self.glyph_metrics(key, font, gid)
+            .scaled(size, font.units_per_em())
+            .advance_width
+    }
+
+    /// Shaped glyphs for `key`, running `shape` on the first request
+    pub fn shaped(&self, key: ShapeKey, shape: impl FnOnce() -> Vec<ShapedGlyph>) -> Arc<[ShapedGlyph]> {
+        let now = self.clock.fetch_add(1, Ordering::Relaxed);
+        let cached = self.read(|tables| {
+            tables.shaped.get(&key).map(|entry| {
+                entry.last_used.store(now, Ordering::Relaxed);
+                entry.glyphs.clone()
+            })
+        });
+        if let Some(glyphs) = cached {
+            return glyphs;
+        }
+
+        let glyphs: Arc<[ShapedGlyph]> = shape().into();
+        self.write(|tables| {
+            if tables.shaped.len() >= self.shaped_capacity {
+                Self::evict_shaped(&mut tables.shaped, self.shaped_capacity);
+            }
+            tables.shaped.insert(key, ShapedEntry { glyphs: glyphs.clone(), last_used: AtomicU64::new(now) });
+        });
+        glyphs
+    }
+
+    /// Drop the least recently used quarter of the shaped runs, so eviction
+    /// runs once per many insertions rather than on each
+    fn evict_shaped(shaped: &mut HashMap<ShapeKey, ShapedEntry>, capacity: usize) {
+        let mut uses: Vec<u64> = shaped.values().map(|entry| entry.last_used.load(Ordering::Relaxed)).collect();
+        let keep = capacity - capacity.div_ceil(4);
+        if keep == 0 {
+            shaped.clear();
+            return;
+        }
+        let cut = uses.len() - keep;
+        let (_, &mut oldest_kept, _) = uses.select_nth_unstable(cut);
+        shaped.retain(|_, entry| entry.last_used.load(Ordering::Relaxed) >= oldest_kept);
+    }
+
+    /// Most shaped runs kept at once
+    pub fn shaped_capacity(&self) -> usize {
+        self.shaped_capacity
+    }
+
+    /// Number of cached shaped runs
+    pub fn shaped_len(&self) -> usize {
+        self.read(|tables| tables.shaped.len())
+    }
+
+    /// Number of cached glyphs
+    pub fn len(&self) -> usize {
+        self.read(|tables| tables.glyphs.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.write(|tables| *tables = CacheTables::default());
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&CacheTables) -> T) -> T {
+        // A panic while holding the lock cannot leave the tables inconsistent
+        // (every write is a single insert), so poisoning is ignored.
+        let tables = self.tables.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&tables)
+    }
+
+    fn write<T>(&self, f: impl FnOnce(&mut CacheTables) -> T) -> T {
+        let mut tables = self.tables.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut tables)
+    }
+}
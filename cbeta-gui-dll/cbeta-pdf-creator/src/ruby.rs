@@ -0,0 +1,303 @@
+//! Ruby annotations
+//!
+//! Pronunciation (pinyin, zhuyin, Jyutping) set in small type over Chinese
+//! characters. Readings come from HTML ruby markup in the section text
+//! (`<ruby>佛<rt>fó</rt></ruby>`) or from a dictionary supplied by the caller,
+//! such as CC-CEDICT; the longest dictionary word at each position wins. A
+//! reading with one syllable per character is set over each character (mono
+//! ruby), any other over the word as a whole (group ruby).
+
+use crate::itemize;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Text of a paragraph with its annotations
+#[derive(Debug, Clone, PartialEq)]
+pub enum RubySegment {
+    Text(String),
+    Ruby { base: String, text: String },
+}
+
+/// Which reading of a CC-CEDICT style dictionary to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DictionaryReading {
+    /// Pinyin with tone marks (`fó`)
+    Pinyin,
+    /// Pinyin as written in the dictionary, with tone numbers (`fo2`)
+    NumberedPinyin,
+    /// Jyutping from the `{...}` field of CC-Canto entries (`fat6`)
+    Jyutping,
+}
+
+/// Ruby settings for Chinese paragraphs: the annotation size and the readings
+/// looked up for text without markup
+#[derive(Debug, Clone)]
+pub struct RubySettings {
+    /// Markup and dictionary readings are set; off, markup keeps only its base text
+    pub enabled: bool,
+    /// Annotation size in em of the Chinese size
+    pub scale: f32,
+    /// Words to their readings, syllables separated by spaces
+    readings: Arc<HashMap<String, String>>,
+    /// Characters in the longest word
+    longest: usize,
+}
+
+impl Default for RubySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            scale: 0.5,
+            readings: Arc::new(HashMap::new()),
+            longest: 0,
+        }
+    }
+}
+
+impl RubySettings {
+    /// Add the reading of `word` (a character or several), replacing any earlier one
+    pub fn add_reading(&mut self, word: &str, reading: &str) {
+        let (word, reading) = (word.trim(), reading.split_whitespace().collect::<Vec<_>>().join(" "));
+        if word.is_empty() || reading.is_empty() {
+            return;
+        }
+        self.longest = self.longest.max(word.chars().count());
+        Arc::make_mut(&mut self.readings).insert(word.to_string(), reading);
+    }
+
+    /// Add readings from CC-CEDICT style lines
+    /// (`傳統 传统 [chuan2 tong3] {cyun4 tung2} /tradition/`), for both the
+    /// traditional and the simplified headword. The first entry of a word is
+    /// kept, unless it is a capitalised proper name and a common word follows.
+    /// Returns the number of entries read.
+    pub fn add_dictionary(&mut self, data: &str, reading: DictionaryReading) -> usize {
+        let mut entries = 0;
+        for line in data.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut headwords = line.split_whitespace();
+            let (Some(traditional), Some(simplified)) = (headwords.next(), headwords.next()) else {
+                continue;
+            };
+            let field = match reading {
+                DictionaryReading::Jyutping => between(line, '{', '}'),
+                _ => between(line, '[', ']'),
+            };
+            let Some(field) = field else {
+                continue;
+            };
+            let text = match reading {
+                DictionaryReading::Pinyin => field.split_whitespace().map(pinyin_tone_marks).collect::<Vec<_>>().join(" "),
+                _ => field.to_string(),
+            };
+
+            for word in [traditional, simplified] {
+                let keep = self.readings.get(word).is_some_and(|existing| {
+                    !existing.starts_with(char::is_uppercase) || text.starts_with(char::is_uppercase)
+                });
+                if !keep {
+                    self.add_reading(word, &text);
+                }
+            }
+            entries += 1;
+        }
+        entries
+    }
+
+    /// Read a dictionary file: CC-CEDICT style, or `word<TAB>reading` lines
+    /// (for zhuyin and other readings) when `reading` is `None`
+    pub fn load_dictionary(&mut self, path: &Path, reading: Option<DictionaryReading>) -> Result<usize> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read ruby dictionary {}: {}", path.display(), e))?;
+        Ok(match reading {
+            Some(reading) => self.add_dictionary(&data, reading),
+            None => {
+                let mut entries = 0;
+                for (word, reading) in data.lines().filter_map(|line| line.split_once('\t')) {
+                    self.add_reading(word, reading);
+                    entries += 1;
+                }
+                entries
+            }
+        })
+    }
+
+    pub fn clear_readings(&mut self) {
+        self.readings = Arc::new(HashMap::new());
+        self.longest = 0;
+    }
+
+    pub fn has_readings(&self) -> bool {
+        !self.readings.is_empty()
+    }
+
+    /// Split the text of `segments` at the dictionary words it contains, taking
+    /// the longest word starting at each ideograph
+    pub fn annotate(&self, segments: Vec<RubySegment>) -> Vec<RubySegment> {
+        if !self.has_readings() {
+            return segments;
+        }
+        let mut annotated = Vec::new();
+        for segment in segments {
+            let RubySegment::Text(text) = segment else {
+                annotated.push(segment);
+                continue;
+            };
+            let chars: Vec<char> = text.chars().collect();
+            let mut plain = String::new();
+            let mut i = 0;
+            while i < chars.len() {
+                let word = if itemize::is_ideographic(chars[i]) {
+                    (1..=self.longest.min(chars.len() - i)).rev().find_map(|length| {
+                        let word: String = chars[i..i + length].iter().collect();
+                        self.readings.get(&word).map(|reading| (length, word, reading.clone()))
+                    })
+                } else {
+                    None
+                };
+                match word {
+                    Some((length, base, text)) => {
+                        if !plain.is_empty() {
+                            annotated.push(RubySegment::Text(std::mem::take(&mut plain)));
+                        }
+                        annotated.push(RubySegment::Ruby { base, text });
+                        i += length;
+                    }
+                    None => {
+                        plain.push(chars[i]);
+                        i += 1;
+                    }
+                }
+            }
+            if !plain.is_empty() {
+                annotated.push(RubySegment::Text(plain));
+            }
+        }
+        annotated
+    }
+}
+
+/// Split HTML ruby markup out of `text`. A `<ruby>` element holds one or more
+/// base texts, each followed by its `<rt>` reading; `<rb>` tags are optional and
+/// `<rp>` fallback parentheses are dropped. Anything else is plain text.
+pub fn parse_markup(text: &str) -> Vec<RubySegment> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find("<ruby>") {
+        let Some(close) = rest[open..].find("</ruby>").map(|close| open + close) else {
+            break;
+        };
+        if open > 0 {
+            push_text(&mut segments, &rest[..open]);
+        }
+
+        let mut element = &rest[open + "<ruby>".len()..close];
+        let mut base = String::new();
+        while !element.is_empty() {
+            if let Some(after) = element.strip_prefix("<rt>") {
+                let end = after.find("</rt>").unwrap_or(after.len());
+                let reading = after[..end].trim();
+                if base.is_empty() || reading.is_empty() {
+                    push_text(&mut segments, &base);
+                } else {
+                    segments.push(RubySegment::Ruby { base: base.clone(), text: reading.to_string() });
+                }
+                base.clear();
+                element = after.get(end + "</rt>".len()..).unwrap_or("");
+            } else if let Some(after) = element.strip_prefix("<rp>") {
+                element = after.find("</rp>").map_or("", |end| &after[end + "</rp>".len()..]);
+            } else if let Some(after) = element.strip_prefix("<rb>").or_else(|| element.strip_prefix("</rb>")) {
+                element = after;
+            } else {
+                let ch = element.chars().next().expect("element is not empty");
+                base.push(ch);
+                element = &element[ch.len_utf8()..];
+            }
+        }
+        push_text(&mut segments, &base);
+        rest = &rest[close + "</ruby>".len()..];
+    }
+    push_text(&mut segments, rest);
+    segments
+}
+
+/// Text of `segments` without the annotations
+pub fn plain_text(segments: &[RubySegment]) -> String {
+    segments
+        .iter()
+        .map(|segment| match segment {
+            RubySegment::Text(text) => text.as_str(),
+            RubySegment::Ruby { base, .. } => base.as_str(),
+        })
+        .collect()
+}
+
+fn push_text(segments: &mut Vec<RubySegment>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match segments.last_mut() {
+        Some(RubySegment::Text(previous)) => previous.push_str(text),
+        _ => segments.push(RubySegment::Text(text.to_string())),
+    }
+}
+
+fn between(line: &str, open: char, close: char) -> Option<&str> {
+    let start = line.find(open)? + open.len_utf8();
+    let end = start + line[start..].find(close)?;
+    Some(line[start..end].trim())
+}
+
+/// Write a numbered pinyin syllable (`lu:e4`, `zhong1`) with its tone mark
+/// (`lüè`, `zhōng`): on a or e, on the o of ou, else on the last vowel
+pub fn pinyin_tone_marks(syllable: &str) -> String {
+    let syllable = syllable.replace("u:", "ü").replace("U:", "Ü").replace('v', "ü").replace('V', "Ü");
+    let Some(tone) = syllable.chars().last().and_then(|ch| ch.to_digit(10)) else {
+        return syllable;
+    };
+    let letters = &syllable[..syllable.len() - 1];
+    if !(1..=5).contains(&tone) || letters.is_empty() {
+        return syllable;
+    }
+    if tone == 5 {
+        return letters.to_string();
+    }
+
+    let chars: Vec<char> = letters.chars().collect();
+    let lower: Vec<char> = chars.iter().map(|ch| ch.to_lowercase().next().unwrap_or(*ch)).collect();
+    let position = lower
+        .iter()
+        .position(|&ch| ch == 'a' || ch == 'e')
+        .or_else(|| lower.windows(2).position(|pair| pair == ['o', 'u']))
+        .or_else(|| lower.iter().rposition(|ch| "iouü".contains(*ch)));
+    let Some(position) = position else {
+        return letters.to_string();
+    };
+
+    const MARKED: [(char, [char; 4]); 12] = [
+        ('a', ['ā', 'á', 'ǎ', 'à']),
+        ('e', ['ē', 'é', 'ě', 'è']),
+        ('i', ['ī', 'í', 'ǐ', 'ì']),
+        ('o', ['ō', 'ó', 'ǒ', 'ò']),
+        ('u', ['ū', 'ú', 'ǔ', 'ù']),
+        ('ü', ['ǖ', 'ǘ', 'ǚ', 'ǜ']),
+        ('A', ['Ā', 'Á', 'Ǎ', 'À']),
+        ('E', ['Ē', 'É', 'Ě', 'È']),
+        ('I', ['Ī', 'Í', 'Ǐ', 'Ì']),
+        ('O', ['Ō', 'Ó', 'Ǒ', 'Ò']),
+        ('U', ['Ū', 'Ú', 'Ǔ', 'Ù']),
+        ('Ü', ['Ǖ', 'Ǘ', 'Ǚ', 'Ǜ']),
+    ];
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, &ch)| match MARKED.iter().find(|(vowel, _)| i == position && *vowel == ch) {
+            Some((_, marks)) => marks[tone as usize - 1],
+            None => ch,
+        })
+        .collect()
+}
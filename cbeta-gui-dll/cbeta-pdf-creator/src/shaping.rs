@@ -0,0 +1,157 @@
+//! OpenType shaping
+//!
+//! Turns a run of text drawn with one font into positioned glyph IDs with
+//! rustybuzz, applying the font's GSUB and GPOS features: ligatures and kerning
+//! for Latin, `halt`/`palt`/`vert` and friends for CJK. Results are in font units,
+//! so one shaped run serves every font size.
+
+use anyhow::{Result, anyhow};
+use rustybuzz::{Direction, Feature, UnicodeBuffer};
+use std::collections::HashMap;
+use std::str::FromStr;
+use unicode_script::{Script, UnicodeScript};
+
+/// One OpenType feature setting, e.g. `palt` on or `liga` off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontFeature {
+    pub tag: [u8; 4],
+    pub value: u32,
+}
+
+impl FontFeature {
+    pub fn new(tag: &[u8; 4], value: u32) -> Self {
+        Self { tag: *tag, value }
+    }
+
+    /// Parse a feature in the usual notation: `palt`, `+kern`, `-liga`, `salt=2`
+    pub fn parse(text: &str) -> Option<Self> {
+        let feature = Feature::from_str(text.trim()).ok()?;
+        Some(Self { tag: feature.tag.to_bytes(), value: feature.value })
+    }
+
+    fn to_rustybuzz(self) -> Feature {
+        Feature::new(rustybuzz::ttf_parser::Tag::from_bytes(&self.tag), self.value, ..)
+    }
+}
+
+/// Features applied on top of the shaper's defaults, per script.
+///
+/// Runs are keyed by their first character with a specific script; text made of
+/// spaces, digits and punctuation only uses the `Common` entry.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureSettings {
+    by_script: HashMap<Script, Vec<FontFeature>>,
+}
+
+impl FeatureSettings {
+    /// Replace the features for `script`
+    pub fn set(&mut self, script: Script, features: Vec<FontFeature>) {
+        self.by_script.insert(script, features);
+    }
+
+    /// Parse a comma- or space-separated feature list (`"palt, -liga"`) for `script`.
+    /// Returns false if any entry is not a valid feature, leaving the settings unchanged.
+    pub fn set_from_str(&mut self, script: Script, features: &str) -> bool {
+        let parsed: Option<Vec<FontFeature>> = features
+            .split(|ch: char| ch == ',' || ch.is_whitespace())
+            .filter(|entry| !entry.is_empty())
+            .map(FontFeature::parse)
+            .collect();
+        match parsed {
+            Some(parsed) => {
+                self.set(script, parsed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Features for `script` (empty when none were set)
+    pub fn get(&self, script: Script) -> &[FontFeature] {
+        self.by_script.get(&script).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Features for a run of text, chosen by its script
+    pub fn for_text(&self, text: &str) -> &[FontFeature] {
+        self.get(run_script(text))
+    }
+}
+
+/// A glyph positioned by the shaper, in font units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShapedGlyph {
+    pub glyph_id: u16,
+    /// Byte offset in the run text of the first character this glyph draws
+    pub cluster: usize,
+    pub x_advance: i32,
+    /// Downward advances in vertical runs are negative
+    pub y_advance: i32,
+    pub x_offset: i32,
+    pub y_offset: i32,
+}
+
+/// First specific (not Common/Inherited) script in `text`, or `Common`
+pub fn run_script(text: &str) -> Script {
+    text.chars()
+        .map(|ch| ch.script())
+        .find(|script| !matches!(script, Script::Common | Script::Inherited | Script::Unknown))
+        .unwrap_or(Script::Common)
+}
+
+/// Shape `text` with one face. `vertical` shapes top to bottom, which also turns on
+/// the `vert` feature.
+pub fn shape(
+    font_data: &[u8],
+    face_index: u32,
+    text: &str,
+    vertical: bool,
+    features: &[FontFeature],
+) -> Result<Vec<ShapedGlyph>> {
+    let face = rustybuzz::Face::from_slice(font_data, face_index)
+        .ok_or_else(|| anyhow!("Failed to parse font face {} for shaping", face_index))?;
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    if vertical {
+        buffer.set_direction(Direction::TopToBottom);
+    }
+
+    let features: Vec<Feature> = features.iter().map(|feature| feature.to_rustybuzz()).collect();
+    let output = rustybuzz::shape(&face, &features, buffer);
+    Ok(output
+        .glyph_infos()
+        .iter()
+        .zip(output.glyph_positions())
+        .map(|(info, position)| ShapedGlyph {
+            glyph_id: info.glyph_id as u16,
+            cluster: info.cluster as usize,
+            x_advance: position.x_advance,
+            y_advance: position.y_advance,
+            x_offset: position.x_offset,
+            y_offset: position.y_offset,
+        })
+        .collect())
+}
+
+/// Text each glyph stands for: the whole cluster for its first glyph, nothing for
+/// the glyphs after it (marks, pieces of split vowels). Runs are shaped left to
+/// right or top to bottom, so the glyphs of a cluster are adjacent.
+pub fn cluster_texts<'a>(text: &'a str, glyphs: &[ShapedGlyph]) -> Vec<&'a str> {
+    let mut starts: Vec<usize> = glyphs.iter().map(|glyph| glyph.cluster).collect();
+    starts.sort_unstable();
+    starts.dedup();
+
+    glyphs
+        .iter()
+        .enumerate()
+        .map(|(i, glyph)| {
+            if i > 0 && glyphs[i - 1].cluster == glyph.cluster {
+                return "";
+            }
+            let next = starts.partition_point(|&start| start <= glyph.cluster);
+            let end = starts.get(next).copied().unwrap_or(text.len());
+            &text[glyph.cluster..end]
+        })
+        .collect()
+}
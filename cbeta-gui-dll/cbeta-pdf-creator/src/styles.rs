@@ -0,0 +1,141 @@
+//! Paragraph styles
+//!
+//! Named sets of indents, alignment, spacing and type sizes. A section selects
+//! one by starting with `<p class="name">` (the closing `</p>` is optional);
+//! sections without one, or naming a style that was never defined, are set in
+//! the default style. Styles are defined in code or from a specification
+//! such as `first-line-indent: 2; align: justify; space-after: 6`.
+
+use crate::fonts::Justification;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+
+/// Indents are in em of the paragraph's type size, spaces in points
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParagraphStyle {
+    /// Extra indent of the first line (two ideographic spaces is 2.0)
+    pub first_line_indent: f32,
+    /// Extra indent of every line but the first
+    pub hanging_indent: f32,
+    pub left_indent: f32,
+    pub right_indent: f32,
+    /// Alignment of the lines; the document's justification when not set
+    pub alignment: Option<Justification>,
+    /// Space above the paragraph, dropped at the top of a page
+    pub space_before: f32,
+    /// Space below the paragraph
+    pub space_after: f32,
+    /// Type size of Chinese paragraphs, in points
+    pub chinese_size: Option<f32>,
+    /// Type size of English paragraphs, in points
+    pub english_size: Option<f32>,
+}
+
+impl ParagraphStyle {
+    /// Read a style from `property: value` pairs separated by semicolons:
+    /// `first-line-indent`, `hanging-indent`, `left-indent`, `right-indent`
+    /// (em), `align` (`left`, `right`, `center`, `justify`), `space-before`,
+    /// `space-after`, `chinese-size` and `english-size` (points).
+    ///
+    /// Values must be finite; sizes must be positive and indents and spaces
+    /// must not be negative.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut style = Self::default();
+        for declaration in spec.split(';').map(str::trim).filter(|declaration| !declaration.is_empty()) {
+            let (property, value) = declaration
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Expected `property: value` in paragraph style, found '{}'", declaration))?;
+            let (property, value) = (property.trim().to_ascii_lowercase(), value.trim());
+            if property == "align" {
+                style.alignment = Some(match value.to_ascii_lowercase().as_str() {
+                    "left" => Justification::Left,
+                    "right" => Justification::Right,
+                    "center" | "centre" => Justification::Center,
+                    "justify" => Justification::Justify,
+                    _ => return Err(anyhow!("Unknown paragraph alignment '{}'", value)),
+                });
+                continue;
+            }
+
+            let number: f32 = value
+                .trim_end_matches("em")
+                .trim_end_matches("pt")
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid value '{}' for paragraph style property '{}'", value, property))?;
+            let is_size = property.ends_with("-size");
+            if !number.is_finite() || number < 0.0 || (is_size && number == 0.0) {
+                let expected = if is_size { "a positive number" } else { "a number of at least 0" };
+                return Err(anyhow!("Paragraph style property '{}' must be {}, found '{}'", property, expected, value));
+            }
+            match property.as_str() {
+                "first-line-indent" => style.first_line_indent = number,
+                "hanging-indent" => style.hanging_indent = number,
+                "left-indent" => style.left_indent = number,
+                "right-indent" => style.right_indent = number,
+                "space-before" => style.space_before = number,
+                "space-after" => style.space_after = number,
+                "chinese-size" => style.chinese_size = Some(number),
+                "english-size" => style.english_size = Some(number),
+                _ => return Err(anyhow!("Unknown paragraph style property '{}'", property)),
+            }
+        }
+        Ok(style)
+    }
+
+    /// Type size set by the style for Chinese or English paragraphs
+    pub fn font_size(&self, is_chinese: bool) -> Option<f32> {
+        if is_chinese {
+            self.chinese_size
+        } else {
+            self.english_size
+        }
+    }
+}
+
+/// Paragraph styles by name
+#[derive(Debug, Clone, Default)]
+pub struct ParagraphStyles {
+    styles: HashMap<String, ParagraphStyle>,
+}
+
+impl ParagraphStyles {
+    /// Define the style `name`, replacing any earlier definition
+    pub fn set(&mut self, name: &str, style: ParagraphStyle) {
+        self.styles.insert(name.trim().to_string(), style);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ParagraphStyle> {
+        self.styles.get(name.trim())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<ParagraphStyle> {
+        self.styles.remove(name.trim())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.styles.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+/// Split a leading `<p class="name">` off a section, returning the style name
+/// and the text inside the element
+pub fn parse_markup(text: &str) -> (Option<&str>, &str) {
+    let trimmed = text.trim_start();
+    let Some(rest) = trimmed.strip_prefix("<p class=") else {
+        return (None, text);
+    };
+    let Some(quote) = rest.chars().next().filter(|&ch| ch == '"' || ch == '\'') else {
+        return (None, text);
+    };
+    let Some((name, body)) = rest[1..].split_once(quote) else {
+        return (None, text);
+    };
+    let Some(body) = body.trim_start().strip_prefix('>') else {
+        return (None, text);
+    };
+    let body = body.trim_end();
+    (Some(name.trim()), body.strip_suffix("</p>").unwrap_or(body))
+}
@@ -0,0 +1,118 @@
+//! Syllables of Devanagari, Siddham and Tibetan
+//!
+//! Mantras and dhāraṇīs in the canon are transcribed in Siddham, Devanagari or
+//! Tibetan. These scripts are shaped a syllable at a time: a conjunct
+//! (consonants joined by a virama, or Tibetan subjoined letters) and its vowel
+//! signs form one cluster, which spacing never goes inside and a line never
+//! breaks inside. Lines break between syllables: Tibetan after the tsheg or a
+//! shad, Siddham between aksaras, and Devanagari between aksaras of a word
+//! with a hyphen, as Sanskrit editions do.
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_script::{Script, UnicodeScript};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Scripts set as syllables, with their own fonts and break rules
+pub const SYLLABIC_SCRIPTS: &[Script] = &[Script::Devanagari, Script::Siddham, Script::Tibetan];
+
+/// A line break allowed between two syllables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyllableBreak {
+    /// Nothing is shown at the break
+    Plain,
+    /// A hyphen ends the line
+    Hyphenated,
+}
+
+const ZWJ: char = '\u{200D}';
+
+/// The script of `ch` if it is set as syllables
+pub fn syllabic_script(ch: char) -> Option<Script> {
+    let script = ch.script();
+    SYLLABIC_SCRIPTS.contains(&script).then_some(script)
+}
+
+/// Devanagari and Siddham virama, and the Tibetan halanta
+pub fn is_virama(ch: char) -> bool {
+    matches!(ch, '\u{094D}' | '\u{115BF}' | '\u{0F84}')
+}
+
+/// Tsheg and shads, after which a Tibetan line may break. The non-breaking
+/// tsheg (U+0F0C) is not one of them.
+pub fn is_tibetan_break_mark(ch: char) -> bool {
+    matches!(ch, '\u{0F0B}' | '\u{0F0D}'..='\u{0F12}' | '\u{0F14}')
+}
+
+/// Dandas, shads and the tsheg, which may not start a line
+pub fn is_line_start_prohibited(ch: char) -> bool {
+    matches!(ch, '\u{0964}' | '\u{0965}' | '\u{115C1}' | '\u{115C2}' | '\u{0F0C}') || is_tibetan_break_mark(ch)
+}
+
+/// A consonant or independent vowel, which starts a syllable
+fn starts_syllable(ch: char) -> bool {
+    syllabic_script(ch).is_some() && ch.is_alphabetic() && !is_combining_mark(ch)
+}
+
+/// Grapheme clusters of `text`, with conjuncts joined: a cluster ending in a
+/// virama (or virama and ZWJ) takes the consonant after it. Unicode grapheme
+/// rules do this for Devanagari but not for Siddham.
+pub fn clusters(text: &str) -> Vec<&str> {
+    let mut clusters: Vec<&str> = Vec::new();
+    let mut start = 0;
+    for (offset, cluster) in text.grapheme_indices(true) {
+        let previous = &text[start..offset];
+        let first = cluster.chars().next().unwrap_or(' ');
+        let joins = !clusters.is_empty()
+            && previous.chars().rev().find(|&ch| ch != ZWJ).is_some_and(is_virama)
+            && starts_syllable(first)
+            && previous.chars().next().and_then(syllabic_script) == syllabic_script(first);
+        if joins {
+            let last = clusters.len() - 1;
+            clusters[last] = &text[start..offset + cluster.len()];
+        } else {
+            start = offset;
+            clusters.push(cluster);
+        }
+    }
+    clusters
+}
+
+/// Whether a line may break between the clusters `before` and `after`
+pub fn break_between(before: &str, after: &str) -> Option<SyllableBreak> {
+    let (Some(last), Some(first)) = (before.chars().last(), after.chars().next()) else {
+        return None;
+    };
+    let script = syllabic_script(first)?;
+    if before.chars().next().and_then(syllabic_script) != Some(script) || !starts_syllable(first) {
+        return None;
+    }
+    match script {
+        Script::Tibetan => is_tibetan_break_mark(last).then_some(SyllableBreak::Plain),
+        _ if is_virama(last) || last == ZWJ || !before.chars().any(|ch| ch.is_alphabetic()) => None,
+        Script::Siddham => Some(SyllableBreak::Plain),
+        _ => Some(SyllableBreak::Hyphenated),
+    }
+}
+
+/// Break points (character positions) inside a word, between its syllables.
+/// Hyphenated breaks leave at least `left_min` syllables before the break
+/// and `right_min` after it.
+pub fn break_points(word: &str, left_min: usize, right_min: usize) -> Vec<(usize, SyllableBreak)> {
+    if !word.chars().any(|ch| syllabic_script(ch).is_some()) {
+        return Vec::new();
+    }
+    let clusters = clusters(word);
+    let mut points = Vec::new();
+    let mut position = 0;
+    for (i, pair) in clusters.windows(2).enumerate() {
+        position += pair[0].chars().count();
+        let syllables = |clusters: &[&str]| clusters.iter().filter(|c| c.chars().any(starts_syllable)).count();
+        match break_between(pair[0], pair[1]) {
+            Some(SyllableBreak::Hyphenated)
+                if syllables(&clusters[..=i]) < left_min || syllables(&clusters[i + 1..]) < right_min => {}
+            Some(kind) => points.push((position, kind)),
+            None => {}
+        }
+    }
+    points
+}
@@ -4,9 +4,16 @@
 //! with print-quality typography similar to Adobe InDesign, following the rules from the
 //! typesetting guide (no widows/orphans, no stacked hyphens, no rivers, proper rag, etc.). [file:1]
 
-use crate::fonts::{FontContext, Justification};
+use crate::fonts::{FontContext, Justification, TextNormalization, WritingMode};
+use crate::itemize;
+use crate::kinsoku;
+use crate::ruby::{self, RubySegment};
+use crate::styles::{self, ParagraphStyle};
+use crate::syllables::{self, SyllableBreak};
 use unicode_bidi::BidiInfo;
-use unicode_script::{Script, UnicodeScript};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 use anyhow::Result;
 
 /// Text tokens for professional paragraph composition. [file:6]
@@ -16,6 +23,9 @@ pub enum TextToken {
     Space,
     Punctuation(char),
     DiscretionaryHyphen, // U+00AD soft hyphen point
+    ZeroWidthBreak,      // U+200B or a syllable boundary: a break point that shows nothing
+    /// Chinese base text with its reading set over it, measured as the wider of the two
+    Ruby { base: String, text: String },
 }
 
 /// Space adjustment data for precise justification. [file:6]
@@ -41,6 +51,10 @@ pub struct FormattedLine {
     pub font_size: f32,
     /// Baseline position for proper leading. [file:6]
     pub baseline: f32,
+    /// Advance before the first character: the paragraph style's indents and
+    /// alignment, less half a character when an opening bracket starting a
+    /// Chinese line is set half width.
+    pub indent: f32,
     /// Tokenized representation. [file:6]
     pub tokens: Vec<TextToken>,
     /// Precise justification data. [file:6]
@@ -48,29 +62,44 @@ pub struct FormattedLine {
     pub is_justified: bool,
     /// Whether this line ends with a hyphen. [file:6]
     pub hyphenated: bool,
+    /// Script runs covering `text`, each set in its own fonts, size and tracking
+    pub runs: Vec<ScriptRun>,
+    /// Annotations over the line's ruby bases
+    pub ruby: Vec<RubyText>,
+}
+
+/// A stretch of a line set in the Chinese or the English fonts
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptRun {
+    /// Character range in the line text
+    pub start: usize,
+    pub end: usize,
+    pub is_chinese: bool,
+    pub font_size: f32,
+}
+
+/// A reading set centred over base characters of a line
+#[derive(Debug, Clone, PartialEq)]
+pub struct RubyText {
+    /// Character range of the base in the line text
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    /// Advance of the annotation at its size
+    pub width: f32,
+    /// Script runs of the annotation, at the ruby size
+    pub runs: Vec<ScriptRun>,
 }
 
 impl FormattedLine {
     /// Convert tokens back to string. [file:6]
     pub fn tokens_to_string(&self) -> String {
-        let mut result = String::new();
-        for token in &self.tokens {
-            match token {
-                TextToken::Word(word) => result.push_str(word),
-                TextToken::Space => result.push(' '),
-                TextToken::Punctuation(punct) => result.push(*punct),
-                TextToken::DiscretionaryHyphen => result.push('-'),
-            }
-        }
-        result
+        tokens_to_text(&self.tokens)
     }
 
-    /// Count word tokens on this line. [file:6]
+    /// Count words on this line; the pieces of a hyphenatable word count once. [file:6]
     pub fn word_count(&self) -> usize {
-        self.tokens
-            .iter()
-            .filter(|t| matches!(t, TextToken::Word(_)))
-            .count()
+        count_words(&self.tokens)
     }
 
     /// Return true if the last visible token is a hyphen. [file:6]
@@ -84,6 +113,176 @@ impl FormattedLine {
     }
 }
 
+/// Index after the last token that is not a space
+fn visible_end(tokens: &[TextToken]) -> usize {
+    tokens
+        .iter()
+        .rposition(|t| *t != TextToken::Space)
+        .map_or(0, |i| i + 1)
+}
+
+/// Text of a run of tokens: a discretionary hyphen shows only at the end. [file:6]
+fn tokens_to_text(tokens: &[TextToken]) -> String {
+    let visible_end = visible_end(tokens);
+    let mut result = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            TextToken::Word(word) | TextToken::Ruby { base: word, .. } => result.push_str(word),
+            TextToken::Space => result.push(' '),
+            TextToken::Punctuation(punct) => result.push(*punct),
+            TextToken::DiscretionaryHyphen if i + 1 == visible_end => result.push('-'),
+            TextToken::DiscretionaryHyphen | TextToken::ZeroWidthBreak => {}
+        }
+    }
+    result
+}
+
+/// Words in a run of tokens, counting the pieces of a hyphenatable word once
+fn count_words(tokens: &[TextToken]) -> usize {
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|&(i, t)| matches!(t, TextToken::Word(_) | TextToken::Ruby { .. }) && (i == 0 || !joins_word(&tokens[i - 1])))
+        .count()
+}
+
+/// A break point between the pieces of one word
+fn joins_word(token: &TextToken) -> bool {
+    matches!(token, TextToken::DiscretionaryHyphen | TextToken::ZeroWidthBreak)
+}
+
+/// Characters a token puts in the line text
+fn token_len(token: &TextToken) -> usize {
+    match token {
+        TextToken::Word(word) | TextToken::Ruby { base: word, .. } => word.chars().count(),
+        TextToken::DiscretionaryHyphen | TextToken::ZeroWidthBreak => 0,
+        TextToken::Space | TextToken::Punctuation(_) => 1,
+    }
+}
+
+/// The character a Chinese token stands for (Chinese tokens are one character each)
+fn token_char(token: &TextToken) -> char {
+    match token {
+        TextToken::Word(word) | TextToken::Ruby { base: word, .. } => word.chars().next().unwrap_or(' '),
+        TextToken::Space => ' ',
+        TextToken::Punctuation(punct) => *punct,
+        TextToken::DiscretionaryHyphen => '\u{00AD}',
+        TextToken::ZeroWidthBreak => '\u{200B}',
+    }
+}
+
+/// Normalize `text` to `form`, keeping CJK compatibility ideographs as written:
+/// NFC and NFD would replace them by their unified equivalents, losing the
+/// variant the source text chose.
+fn normalize_text(text: &str, form: TextNormalization) -> String {
+    let normalize = |segment: &str, out: &mut String| match form {
+        TextNormalization::None => out.push_str(segment),
+        TextNormalization::Nfc => out.extend(segment.nfc()),
+        TextNormalization::Nfd => out.extend(segment.nfd()),
+    };
+    let mut normalized = String::with_capacity(text.len());
+    let mut segment_start = 0;
+    for (i, ch) in text.char_indices() {
+        if matches!(ch, '\u{F900}'..='\u{FAFF}' | '\u{2F800}'..='\u{2FA1F}') {
+            normalize(&text[segment_start..i], &mut normalized);
+            normalized.push(ch);
+            segment_start = i + ch.len_utf8();
+        }
+    }
+    normalize(&text[segment_start..], &mut normalized);
+    normalized
+}
+
+/// A Chinese line as measured for setting
+struct CjkLine {
+    /// Blank squeezed out of each character set inside the measure; a hanging
+    /// mark at the end has no entry
+    squeeze: Vec<f32>,
+    /// Width inside the measure
+    width: f32,
+}
+
+/// Blank squeezed out of each full-width mark of a Chinese line by punctuation
+/// compression (CLREQ): of two adjacent marks only one blank half is kept, so
+/// "。」" and "」「" take one and a half characters; an opening bracket starting
+/// the line and a mark ending it lose their blank too. [file:6]
+fn compressed_blanks(chars: &[char], widths: &[f32]) -> Vec<f32> {
+    let mut squeeze = vec![0.0f32; chars.len()];
+    for i in 0..chars.len().saturating_sub(1) {
+        let (before, after) = (chars[i], chars[i + 1]);
+        if !kinsoku::is_compressible(before) || !kinsoku::is_compressible(after) {
+            continue;
+        }
+        if !kinsoku::is_opening_bracket(before) {
+            squeeze[i] = widths[i] / 2.0;
+        } else if kinsoku::is_opening_bracket(after) {
+            squeeze[i + 1] = widths[i + 1] / 2.0;
+        }
+    }
+    if chars.first().is_some_and(|&ch| kinsoku::is_opening_bracket(ch)) {
+        squeeze[0] = widths[0] / 2.0;
+    }
+    if let Some(last) = chars.len().checked_sub(1) {
+        if kinsoku::is_compressible(chars[last]) && !kinsoku::is_opening_bracket(chars[last]) {
+            squeeze[last] = widths[last] / 2.0;
+        }
+    }
+    squeeze
+}
+
+/// Squeeze `overflow` out of a pushed-in line: a closing mark at the end hangs
+/// into its blank half, and other full-width punctuation gives up to half its
+/// advance, starting from the end of the line. Returns the width squeezed out. [file:6]
+fn push_in(chars: &[char], widths: &[f32], squeeze: &mut [f32], overflow: f32) -> f32 {
+    let mut remaining = overflow;
+    for (i, &ch) in chars.iter().enumerate().rev() {
+        if remaining <= 0.0 {
+            break;
+        }
+        if kinsoku::is_compressible(ch) {
+            let taken = remaining.min(widths[i] / 2.0 - squeeze[i]).max(0.0);
+            squeeze[i] += taken;
+            remaining -= taken;
+        }
+    }
+    overflow - remaining.max(0.0)
+}
+
+/// Adjustments for squeezed marks: opening brackets lose the blank before them,
+/// moving the line start back when they come first (the returned indent), and
+/// everything else the blank after. The last mark needs no adjustment; its
+/// blank simply sits past the end of the line.
+fn squeeze_adjustments(chars: &[char], widths: &[f32], squeeze: &[f32]) -> (f32, Vec<SpaceAdjustment>) {
+    let mut indent = 0.0;
+    let mut adjustments = Vec::new();
+    for (i, &squeezed) in squeeze.iter().enumerate() {
+        if squeezed <= 0.0 {
+            continue;
+        }
+        let position = if kinsoku::is_opening_bracket(chars[i]) {
+            match i.checked_sub(1) {
+                Some(previous) => previous,
+                None => {
+                    indent -= squeezed;
+                    continue;
+                }
+            }
+        } else if i + 1 < chars.len() {
+            i
+        } else {
+            continue;
+        };
+        adjustments.push(SpaceAdjustment {
+            position,
+            base_width: widths[i],
+            adjusted_width: widths[i] - squeezed,
+            adjustment_ratio: -squeezed / widths[i],
+        });
+    }
+    adjustments.sort_by_key(|adjustment| adjustment.position);
+    (indent, adjustments)
+}
+
 /// Represents a paragraph with proper typography. [file:6]
 #[derive(Debug, Clone)]
 pub struct FormattedParagraph {
@@ -97,6 +296,118 @@ pub struct FormattedParagraph {
     pub line_spacing: f32,
     /// Baseline-to-baseline leading. [file:6]
     pub leading: f32,
+    /// Lines are columns set top to bottom, right to left (vertical writing mode).
+    pub vertical: bool,
+    /// Space the paragraph style asks for above and below the paragraph
+    pub space_before: f32,
+    pub space_after: f32,
+    /// Kept on a page with the first lines of the paragraph after it (headings)
+    pub keep_with_next: bool,
+    /// Never broken by a page unless taller than one (short pairs)
+    pub keep_together: bool,
+    /// Starts a new page
+    pub break_before: bool,
+}
+
+impl FormattedParagraph {
+    /// Split the paragraph before line `at` for a page break, returning the rest
+    /// as a continuation paragraph. Lines keep their breaks, spacing and
+    /// justification; the continuation's baselines start again from zero.
+    pub fn split_off(&mut self, at: usize) -> FormattedParagraph {
+        let mut rest = self.lines.split_off(at.min(self.lines.len()));
+        if let Some(offset) = rest.first().map(|line| line.baseline) {
+            for line in &mut rest {
+                line.baseline -= offset;
+            }
+        }
+        self.height = lines_height(&self.lines, self.leading);
+        FormattedParagraph {
+            height: lines_height(&rest, self.leading),
+            lines: rest,
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            is_chinese: self.is_chinese,
+            font_size: self.font_size,
+            line_spacing: self.line_spacing,
+            leading: self.leading,
+            vertical: self.vertical,
+            space_before: 0.0,
+            space_after: self.space_after,
+            keep_with_next: self.keep_with_next,
+            keep_together: false,
+            break_before: false,
+        }
+    }
+}
+
+/// Height of a paragraph's lines from their baseline positions. [file:6]
+fn lines_height(lines: &[FormattedLine], leading: f32) -> f32 {
+    match (lines.first(), lines.last()) {
+        (Some(first), Some(last)) => (last.baseline - first.baseline) + leading,
+        _ => leading,
+    }
+}
+
+/// Space between the two columns of 句 in verse, in em
+const VERSE_COLUMN_GAP: f32 = 2.0;
+
+/// Pair one-line verse paragraphs into rows of two aligned columns, the second
+/// line of each row drawn beside the first on its baseline. `None` when a line
+/// turns over or the columns are wider than the measure.
+fn verse_columns(cells: Vec<FormattedParagraph>, max_width: f32, style: &ParagraphStyle) -> Option<Vec<FormattedParagraph>> {
+    if cells.iter().any(|cell| cell.lines.len() != 1) {
+        return None;
+    }
+    let font_size = cells.first()?.font_size;
+    let column_width = |column: usize| {
+        cells.iter().skip(column).step_by(2).map(|cell| cell.lines[0].width).fold(0.0, f32::max)
+    };
+    let (left, right) = (column_width(0), column_width(1));
+    let gap = VERSE_COLUMN_GAP * font_size;
+    let indents = style.left_indent + style.first_line_indent + style.right_indent;
+    let slack = max_width - indents * font_size - (left + gap + right);
+    if slack < 0.0 {
+        return None;
+    }
+    let offset = match style.alignment {
+        Some(Justification::Right) => slack,
+        Some(Justification::Center) => slack / 2.0,
+        _ => 0.0,
+    };
+
+    let mut rows = Vec::new();
+    let mut cells = cells.into_iter();
+    while let Some(mut row) = cells.next() {
+        let baseline = row.lines[0].baseline;
+        row.lines[0].x += offset;
+        if let Some(mut line) = cells.next().and_then(|cell| cell.lines.into_iter().next()) {
+            line.x += offset + left + gap;
+            line.baseline = baseline;
+            row.lines.push(line);
+        }
+        rows.push(row);
+    }
+    Some(rows)
+}
+
+/// Width each line of a paragraph is set to: the first line's differs from
+/// the others' by its first-line or hanging indent
+#[derive(Debug, Clone, Copy)]
+struct Measure {
+    first: f32,
+    rest: f32,
+}
+
+impl Measure {
+    /// Width of line `index`; the first line is also the one starting at token 0
+    fn line(&self, index: usize) -> f32 {
+        if index == 0 {
+            self.first
+        } else {
+            self.rest
+        }
+    }
 }
 
 /// Professional text layout engine. [file:6]
@@ -110,6 +421,7 @@ impl TextLayoutEngine {
     }
 
     /// Layout a paragraph with premium TeX-like typography and the guide rules applied. [file:1][file:6]
+    /// A section starting with `<p class="name">` is set in that paragraph style.
     pub fn layout_paragraph(
         &mut self,
         text: &str,
@@ -118,14 +430,115 @@ impl TextLayoutEngine {
         max_width: f32,
         is_chinese: bool,
     ) -> Result<FormattedParagraph> {
-        // Smart punctuation normalization (smart quotes, ellipsis, dashes). [file:1][file:6]
-        let normalized_text = self.normalize_punctuation(text);
+        let (name, text) = styles::parse_markup(text);
+        let style = name
+            .and_then(|name| self.font_context.paragraph_styles.get(name))
+            .cloned()
+            .unwrap_or_default();
+        self.layout_styled_paragraph(text, x, y, max_width, is_chinese, &style)
+    }
 
-        // Apply bidi processing before tokenization. [file:6]
-        let bidi_text = self.process_bidi_text(&normalized_text);
+    /// Layout a paragraph in `style`: its type size and alignment in place of the
+    /// document's, and its indents taken from `max_width`.
+    pub fn layout_styled_paragraph(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        max_width: f32,
+        is_chinese: bool,
+        style: &ParagraphStyle,
+    ) -> Result<FormattedParagraph> {
+        let document = (
+            self.font_context.font_size_chinese,
+            self.font_context.font_size_english,
+            self.font_context.justification,
+        );
+        if let Some(size) = style.chinese_size {
+            self.font_context.font_size_chinese = size;
+        }
+        if let Some(size) = style.english_size {
+            self.font_context.font_size_english = size;
+        }
+        if let Some(alignment) = style.alignment {
+            self.font_context.justification = alignment;
+        }
+        let paragraph = self.layout_in_style(text, x, y, max_width, is_chinese, style);
+        (
+            self.font_context.font_size_chinese,
+            self.font_context.font_size_english,
+            self.font_context.justification,
+        ) = document;
+        paragraph
+    }
+
+    /// Layout verse a line at a time, each line kept whole and never justified.
+    /// Horizontal Chinese verse goes two lines to a row, in two aligned columns
+    /// of 句, when `verse_columns` is 2 and the columns fit the measure; a line
+    /// longer than the measure turns over. Returns a paragraph for each row.
+    pub fn layout_verse(
+        &mut self,
+        lines: &[&str],
+        x: f32,
+        y: f32,
+        max_width: f32,
+        is_chinese: bool,
+        style: &ParagraphStyle,
+    ) -> Result<Vec<FormattedParagraph>> {
+        let mut style = style.clone();
+        if matches!(style.alignment, None | Some(Justification::Justify)) {
+            style.alignment = Some(Justification::Left);
+        }
+        let lines: Vec<&str> = lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty()).collect();
+
+        if is_chinese
+            && lines.len() > 1
+            && self.font_context.verse_columns >= 2
+            && self.font_context.writing_mode == WritingMode::Horizontal
+        {
+            // Lines are set flush left in their columns, then the columns aligned as a whole
+            let alignment = style.alignment;
+            style.alignment = Some(Justification::Left);
+            let cells = lines
+                .iter()
+                .map(|line| self.layout_styled_paragraph(line, x, y, max_width, true, &style))
+                .collect::<Result<Vec<_>>>()?;
+            style.alignment = alignment;
+            if let Some(rows) = verse_columns(cells, max_width, &style) {
+                return Ok(rows);
+            }
+        }
+
+        lines
+            .iter()
+            .map(|line| self.layout_styled_paragraph(line, x, y, max_width, is_chinese, &style))
+            .collect()
+    }
+
+    fn layout_in_style(
+        &mut self,
+        text: &str,
+        x: f32,
+        y: f32,
+        max_width: f32,
+        is_chinese: bool,
+        style: &ParagraphStyle,
+    ) -> Result<FormattedParagraph> {
+        // Ruby markup and dictionary readings go over horizontal Chinese text; elsewhere
+        // only the base text of the markup is kept.
+        let segments = ruby::parse_markup(text);
+        let with_ruby = is_chinese
+            && self.font_context.ruby.enabled
+            && self.font_context.writing_mode == WritingMode::Horizontal;
 
         // Tokenize text for professional composition. [file:6]
-        let tokens = self.tokenize_text(&bidi_text, is_chinese)?;
+        let tokens = if with_ruby {
+            let segments = self.font_context.ruby.annotate(segments);
+            self.tokenize_ruby_text(&segments)?
+        } else {
+            let text = self.prepare_text(&ruby::plain_text(&segments));
+            self.tokenize_text(&text, is_chinese)?
+        };
 
         // Calculate proper baseline-to-baseline leading (minimum +4 over size). [file:1][file:6]
         let font_size = if is_chinese {
@@ -137,21 +550,45 @@ impl TextLayoutEngine {
         if leading < font_size + 4.0 {
             leading = font_size + 4.0;
         }
+        // Annotated lines make room for the readings above them
+        if tokens.iter().any(|token| matches!(token, TextToken::Ruby { .. })) {
+            leading += self.ruby_size();
+        }
+
+        // Indents narrow the lines, never below one character
+        let indent = |extra: f32| (style.left_indent + extra) * font_size;
+        let right = style.right_indent * font_size;
+        let measure = Measure {
+            first: (max_width - indent(style.first_line_indent) - right).max(font_size),
+            rest: (max_width - indent(style.hanging_indent) - right).max(font_size),
+        };
 
         // Use paragraph composer instead of greedy wrapper. [file:6]
-        let mut lines = self.compose_paragraph(&tokens, max_width, leading, is_chinese)?;
+        let mut lines = self.compose_paragraph(&tokens, measure, leading, is_chinese)?;
 
         // Post-process lines for widows/orphans, stacked hyphens, rag quality, and word count per line. [file:1][file:6]
-        self.post_process_lines(&mut lines, max_width, is_chinese)?;
+        self.post_process_lines(&mut lines, measure, is_chinese)?;
+
+        // Lines start after their indents, moved along by right or centred alignment
+        for (i, line) in lines.iter_mut().enumerate() {
+            let width = if line.is_chinese {
+                line.width
+            } else {
+                let visible = visible_end(&line.tokens);
+                self.calculate_line_width(&line.tokens[..visible], false)
+            };
+            let slack = (measure.line(i) - width).max(0.0);
+            let offset = match self.font_context.justification {
+                Justification::Right => slack,
+                Justification::Center => slack / 2.0,
+                Justification::Left | Justification::Justify => 0.0,
+            };
+            let extra = if i == 0 { style.first_line_indent } else { style.hanging_indent };
+            line.indent += indent(extra) + offset;
+        }
 
         // Calculate paragraph height using baseline positions. [file:6]
-        let total_height = if lines.is_empty() {
-            leading
-        } else {
-            let first_baseline = lines.first().unwrap().baseline;
-            let last_baseline = lines.last().unwrap().baseline;
-            (last_baseline - first_baseline) + leading
-        };
+        let total_height = lines_height(&lines, leading);
 
         Ok(FormattedParagraph {
             lines,
@@ -163,9 +600,51 @@ impl TextLayoutEngine {
             font_size,
             line_spacing: self.font_context.line_spacing,
             leading,
+            vertical: self.font_context.writing_mode == WritingMode::Vertical,
+            space_before: style.space_before,
+            space_after: style.space_after,
+            keep_with_next: false,
+            keep_together: false,
+            break_before: false,
         })
     }
 
+    /// Text as set: Unicode normalization, smart punctuation and bidi processing
+    fn prepare_text(&self, text: &str) -> String {
+        // Unicode normalization, then smart punctuation (smart quotes, ellipsis, dashes). [file:1][file:6]
+        let text = normalize_text(text, self.font_context.normalization);
+        let normalized_text = self.normalize_punctuation(&text);
+
+        // Apply bidi processing before tokenization. [file:6]
+        self.process_bidi_text(&normalized_text)
+    }
+
+    /// Tokenize a Chinese paragraph with its annotations. A reading with one
+    /// syllable per character of its base goes over each character, any other
+    /// over the base as a whole, which is then never broken across lines.
+    fn tokenize_ruby_text(&self, segments: &[RubySegment]) -> Result<Vec<TextToken>> {
+        let mut tokens = Vec::new();
+        for segment in segments {
+            match segment {
+                RubySegment::Text(text) => tokens.extend(self.tokenize_text(&self.prepare_text(text), true)?),
+                RubySegment::Ruby { base, text } => {
+                    let base = self.prepare_text(base);
+                    let clusters: Vec<&str> = base.graphemes(true).collect();
+                    let syllables: Vec<&str> = text.split_whitespace().collect();
+                    if clusters.len() > 1 && clusters.len() == syllables.len() {
+                        tokens.extend(clusters.iter().zip(&syllables).map(|(cluster, syllable)| TextToken::Ruby {
+                            base: cluster.to_string(),
+                            text: syllable.to_string(),
+                        }));
+                    } else {
+                        tokens.push(TextToken::Ruby { base, text: text.clone() });
+                    }
+                }
+            }
+        }
+        Ok(tokens)
+    }
+
     /// Smart punctuation normalization for professional typography. [file:1][file:6]
     fn normalize_punctuation(&self, text: &str) -> String {
         let mut normalized = String::with_capacity(text.len());
@@ -213,24 +692,29 @@ impl TextLayoutEngine {
         normalized
     }
 
-    /// Tokenize text into Word/Space/Punctuation for professional composition. [file:6]
+    /// Tokenize text into Word/Space/Punctuation for professional composition, one
+    /// grapheme cluster (or conjunct) at a time so letters keep their combining
+    /// marks and syllables stay whole. [file:6]
     fn tokenize_text(&self, text: &str, is_chinese: bool) -> Result<Vec<TextToken>> {
         let mut tokens = Vec::new();
         let mut current_word = String::new();
 
-        for ch in text.chars() {
+        for cluster in syllables::clusters(text) {
+            let mut chars = cluster.chars();
+            let ch = chars.next().unwrap_or(' ');
+            let single = chars.next().is_none();
             if is_chinese {
-                // Chinese: each character is a word token, punctuation stays punctuation. [file:6]
+                // Chinese: each character (with its marks) is a word token, punctuation stays punctuation. [file:6]
                 if !current_word.is_empty() {
                     tokens.push(TextToken::Word(current_word.clone()));
                     current_word.clear();
                 }
-                if self.is_chinese_punctuation(ch) {
+                if single && self.is_chinese_punctuation(ch) {
                     tokens.push(TextToken::Punctuation(ch));
                 } else if ch.is_whitespace() {
                     tokens.push(TextToken::Space);
                 } else {
-                    tokens.push(TextToken::Word(ch.to_string()));
+                    tokens.push(TextToken::Word(cluster.to_string()));
                 }
             } else {
                 // English: proper word/space/punctuation tokenization. [file:6]
@@ -247,14 +731,20 @@ impl TextLayoutEngine {
                         current_word.clear();
                     }
                     tokens.push(TextToken::DiscretionaryHyphen);
-                } else if ch.is_ascii_punctuation() && ch != '\'' && ch != '-' {
+                } else if ch == '\u{200B}' {
+                    if !current_word.is_empty() {
+                        tokens.push(TextToken::Word(current_word.clone()));
+                        current_word.clear();
+                    }
+                    tokens.push(TextToken::ZeroWidthBreak);
+                } else if single && ch.is_ascii_punctuation() && ch != '\'' && ch != '-' {
                     if !current_word.is_empty() {
                         tokens.push(TextToken::Word(current_word.clone()));
                         current_word.clear();
                     }
                     tokens.push(TextToken::Punctuation(ch));
                 } else {
-                    current_word.push(ch);
+                    current_word.push_str(cluster);
                 }
             }
         }
@@ -263,20 +753,85 @@ impl TextLayoutEngine {
             tokens.push(TextToken::Word(current_word));
         }
 
-        Ok(tokens)
+        if is_chinese {
+            Ok(tokens)
+        } else {
+            Ok(self.insert_hyphenation_points(tokens))
+        }
     }
 
-    /// TeX-like paragraph composer with optimal line breaking (simplified). [file:6]
+    /// Split hyphenatable English words at their hyphenation points, with a
+    /// discretionary hyphen between the pieces. Words already carrying soft hyphens
+    /// are left as the author marked them. Devanagari, Siddham and Tibetan words
+    /// are split between their syllables instead. [file:1][file:6]
+    fn insert_hyphenation_points(&self, tokens: Vec<TextToken>) -> Vec<TextToken> {
+        let mut hyphenated = Vec::with_capacity(tokens.len());
+        for (i, token) in tokens.iter().enumerate() {
+            let marked = (i > 0 && joins_word(&tokens[i - 1])) || tokens.get(i + 1).is_some_and(joins_word);
+            match token {
+                TextToken::Word(word) if !marked && word.chars().any(|ch| syllables::syllabic_script(ch).is_some()) => {
+                    let points = syllables::break_points(word, SYLLABLES_BEFORE_HYPHEN, SYLLABLES_AFTER_HYPHEN);
+                    let chars: Vec<char> = word.chars().collect();
+                    let mut start = 0;
+                    for (point, kind) in points {
+                        let token = match kind {
+                            SyllableBreak::Plain => TextToken::ZeroWidthBreak,
+                            SyllableBreak::Hyphenated if self.font_context.hyphenation.enabled => {
+                                TextToken::DiscretionaryHyphen
+                            }
+                            SyllableBreak::Hyphenated => continue,
+                        };
+                        hyphenated.push(TextToken::Word(chars[start..point].iter().collect()));
+                        hyphenated.push(token);
+                        start = point;
+                    }
+                    hyphenated.push(TextToken::Word(chars[start..].iter().collect()));
+                    continue;
+                }
+                _ => {}
+            }
+            let points = match token {
+                TextToken::Word(word) if !marked => self.find_hyphenation_points(word),
+                _ => None,
+            };
+            let (Some(points), TextToken::Word(word)) = (points, token) else {
+                hyphenated.push(token.clone());
+                continue;
+            };
+
+            let chars: Vec<char> = word.chars().collect();
+            let mut start = 0;
+            for point in points {
+                hyphenated.push(TextToken::Word(chars[start..point].iter().collect()));
+                hyphenated.push(TextToken::DiscretionaryHyphen);
+                start = point;
+            }
+            hyphenated.push(TextToken::Word(chars[start..].iter().collect()));
+        }
+        hyphenated
+    }
+
+    /// TeX-like paragraph composer: total-fit breaking for justified English, greedy otherwise. [file:6]
     fn compose_paragraph(
         &mut self,
         tokens: &[TextToken],
-        max_width: f32,
+        measure: Measure,
         leading: f32,
         is_chinese: bool,
     ) -> Result<Vec<FormattedLine>> {
         let mut lines = Vec::new();
-        let breakpoints = self.find_breakpoints(tokens, is_chinese)?;
-        let optimal_breaks = self.greedy_line_breaks(tokens, &breakpoints, max_width, is_chinese)?;
+        let breakpoints = self.find_breakpoints(tokens)?;
+        let justify = !is_chinese && self.font_context.justification == Justification::Justify;
+        let total_fit = if justify {
+            self.optimize_line_breaks(tokens, &breakpoints, measure, is_chinese)?
+        } else {
+            None
+        };
+        let optimal_breaks = match total_fit {
+            Some(breaks) => breaks,
+            None if is_chinese => self.kinsoku_line_breaks(tokens, measure)?,
+            None => self.greedy_line_breaks(tokens, &breakpoints, measure, is_chinese)?,
+        };
         let mut line_start = 0;
         let mut baseline = 0.0;
 
@@ -284,29 +839,28 @@ impl TextLayoutEngine {
             if break_index <= line_start {
                 continue;
             }
-
-            let line_tokens = &tokens[line_start..break_index];
-            let line_width = self.calculate_line_width(line_tokens, is_chinese);
-
-            let (justified_tokens, space_adjustments) = if !is_chinese
-                && self.font_context.justification == Justification::Justify
-                && line_start != 0
-            {
-                // Do not justify first line of paragraph. [file:1][file:6]
-                self.justify_line(line_tokens, max_width, is_chinese)?
+            let max_width = measure.line(lines.len());
+
+            let line_tokens = Self::set_line_tokens(&tokens[line_start..break_index], justify);
+            let mut line_width = self.calculate_line_width(&line_tokens, is_chinese);
+
+            let (justified_tokens, space_adjustments, indent) = if justify && break_index < tokens.len() {
+                // Every line but the last is set to the full measure; the total-fit breaks
+                // were chosen for exactly that. [file:1][file:6]
+                let (justified_tokens, adjustments) = self.justify_line(&line_tokens, max_width, is_chinese)?;
+                (justified_tokens, adjustments, 0.0)
+            } else if is_chinese {
+                let fill = self.font_context.justification == Justification::Justify && break_index < tokens.len();
+                let (width, indent, adjustments) = self.set_chinese_line(&line_tokens, max_width, fill);
+                line_width = width;
+                (line_tokens, adjustments, indent)
             } else {
-                (line_tokens.to_vec(), Vec::new())
+                (line_tokens, Vec::new(), 0.0)
             };
 
-            let mut line_text = String::new();
-            for token in &justified_tokens {
-                match token {
-                    TextToken::Word(word) => line_text.push_str(word),
-                    TextToken::Space => line_text.push(' '),
-                    TextToken::Punctuation(punct) => line_text.push(*punct),
-                    TextToken::DiscretionaryHyphen => line_text.push('-'),
-                }
-            }
+            let line_text = tokens_to_text(&justified_tokens);
+            let runs = self.line_runs(&line_text, is_chinese);
+            let ruby = self.line_ruby(&justified_tokens);
 
             let hyphenated = justified_tokens
                 .iter()
@@ -328,10 +882,13 @@ impl TextLayoutEngine {
                     self.font_context.font_size_english
                 },
                 baseline,
+                indent,
                 tokens: justified_tokens,
                 space_adjustments: space_adjustments.clone(),
                 is_justified: !space_adjustments.is_empty(),
                 hyphenated,
+                runs,
+                ruby,
             });
 
             baseline += leading;
@@ -341,12 +898,19 @@ impl TextLayoutEngine {
         Ok(lines)
     }
 
+    /// Tokens as set on a line. Justified lines drop the spaces they were broken at,
+    /// which would otherwise be stretched past the margin.
+    fn set_line_tokens(tokens: &[TextToken], justified: bool) -> Vec<TextToken> {
+        let end = if justified { visible_end(tokens) } else { tokens.len() };
+        tokens[..end].to_vec()
+    }
+
     /// Greedy line wrapping that respects max width and known breakpoints.
     fn greedy_line_breaks(
         &mut self,
         tokens: &[TextToken],
         breakpoints: &[usize],
-        max_width: f32,
+        measure: Measure,
         is_chinese: bool,
     ) -> Result<Vec<usize>> {
         if tokens.is_empty() {
@@ -362,7 +926,7 @@ impl TextLayoutEngine {
             let width = self.calculate_line_width(&tokens[line_start..i], is_chinese);
             let is_breakpoint = breakpoints.binary_search(&i).is_ok();
 
-            if width <= max_width {
+            if width <= measure.line(breaks.len()) {
                 if is_breakpoint {
                     best_break = i;
                 }
@@ -391,8 +955,222 @@ impl TextLayoutEngine {
         Ok(breaks)
     }
 
+    /// Chinese line breaking with JIS X 4051 / CLREQ kinsoku: fill each line, and
+    /// when the break would put closing punctuation at the start of the next line
+    /// (or an opening bracket at the end of this one, or split "……", "——" or a
+    /// number), either push the punctuation in by squeezing it, or push characters
+    /// out to the previous allowed break. Lines are measured with punctuation
+    /// compression and hanging applied. [file:6]
+    fn kinsoku_line_breaks(&mut self, tokens: &[TextToken], measure: Measure) -> Result<Vec<usize>> {
+        let chars: Vec<char> = tokens.iter().map(token_char).collect();
+        let widths = self.char_widths(tokens);
+
+        let mut breaks = Vec::new();
+        let mut start = 0;
+        while start < tokens.len() {
+            let max_width = measure.line(breaks.len());
+            let mut end = start + 1;
+            while end < tokens.len()
+                && self.set_cjk_line(&chars[start..=end], &widths[start..=end], max_width).width <= max_width
+            {
+                end += 1;
+            }
+            if end == tokens.len() {
+                breaks.push(end);
+                break;
+            }
+
+            let break_at = if kinsoku::can_break_before(&chars, end) {
+                end
+            } else {
+                // Push in: keep the marks that may not start a line on this one, if
+                // squeezing the line's punctuation makes room for them.
+                let mut pushed = end;
+                while pushed < chars.len()
+                    && !kinsoku::can_break_before(&chars, pushed)
+                    && kinsoku::is_line_start_prohibited(chars[pushed])
+                {
+                    pushed += 1;
+                }
+                let line = self.set_cjk_line(&chars[start..pushed], &widths[start..pushed], max_width);
+                let squeezable: f32 = line
+                    .squeeze
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| kinsoku::is_compressible(chars[start + i]))
+                    .map(|(i, squeezed)| widths[start + i] / 2.0 - squeezed)
+                    .sum();
+                let can_push_in = pushed > end
+                    && kinsoku::can_break_before(&chars, pushed)
+                    && line.width - max_width <= squeezable;
+
+                if can_push_in {
+                    pushed
+                } else {
+                    // Push out to the last allowed break; a line with none is broken anyway.
+                    (start + 1..end)
+                        .rev()
+                        .find(|&i| kinsoku::can_break_before(&chars, i))
+                        .unwrap_or(end)
+                }
+            };
+            breaks.push(break_at);
+            start = break_at;
+        }
+
+        Ok(breaks)
+    }
+
+    /// Widths of single-character tokens of a Chinese line
+    fn char_widths(&mut self, tokens: &[TextToken]) -> Vec<f32> {
+        tokens
+            .iter()
+            .map(|token| self.calculate_line_width(std::slice::from_ref(token), true))
+            .collect()
+    }
+
+    /// Measure a Chinese line: squeeze its punctuation if compression is on, and
+    /// let a final 。，、 hang past `max_width` if hanging is on and the line is too long.
+    fn set_cjk_line(&self, chars: &[char], widths: &[f32], max_width: f32) -> CjkLine {
+        let measure = |body: usize| {
+            let squeeze = if self.font_context.punctuation_compression {
+                compressed_blanks(&chars[..body], &widths[..body])
+            } else {
+                vec![0.0; body]
+            };
+            let gaps: f32 = chars[..body]
+                .windows(2)
+                .map(|pair| self.font_context.script_gap(pair[0], pair[1]))
+                .sum();
+            let width = widths[..body].iter().sum::<f32>() - squeeze.iter().sum::<f32>() + gaps;
+            CjkLine { squeeze, width }
+        };
+
+        let line = measure(chars.len());
+        let can_hang = self.font_context.hanging_punctuation
+            && chars.len() > 1
+            && chars.last().is_some_and(|&ch| kinsoku::is_hangable(ch));
+        if can_hang && line.width > max_width {
+            measure(chars.len() - 1)
+        } else {
+            line
+        }
+    }
+
+    /// Set a Chinese line within `max_width`: compressed punctuation, then marks
+    /// pushed in by kinsoku squeezed back inside the measure, and when `fill` is
+    /// set, the space left over spread across the gaps. Returns the width, the
+    /// indent and the adjustments between characters.
+    fn set_chinese_line(&mut self, tokens: &[TextToken], max_width: f32, fill: bool) -> (f32, f32, Vec<SpaceAdjustment>) {
+        let chars: Vec<char> = tokens.iter().map(token_char).collect();
+        let widths = self.char_widths(tokens);
+        let mut line = self.set_cjk_line(&chars, &widths, max_width);
+        // A hanging mark sits outside the measure and is left as it is.
+        let body = line.squeeze.len();
+
+        if line.width > max_width {
+            line.width -= push_in(&chars[..body], &widths[..body], &mut line.squeeze, line.width - max_width);
+        }
+        let (mut indent, mut adjustments) = squeeze_adjustments(&chars[..body], &widths[..body], &line.squeeze);
+
+        if fill && line.width < max_width {
+            // Chinese lines fill the column, all but the last. [file:6]
+            let spread = self.cjk_justify_adjustments(&tokens[..body], max_width - line.width);
+            line.width += spread.iter().map(|a| a.adjusted_width - a.base_width).sum::<f32>();
+            adjustments.extend(spread);
+            adjustments.sort_by_key(|adjustment| adjustment.position);
+        }
+
+        // Adjustments were made per token; the PDF writer places them by character.
+        let mut starts = Vec::with_capacity(tokens.len());
+        let mut offset = 0;
+        for token in tokens {
+            starts.push(offset);
+            offset += token_len(token);
+        }
+        for adjustment in &mut adjustments {
+            adjustment.position = starts[adjustment.position];
+        }
+
+        // A reading wider than its base takes the room it needs, the base centred in it.
+        for (i, token) in tokens.iter().enumerate() {
+            let TextToken::Ruby { base, .. } = token else {
+                continue;
+            };
+            let base_width = self.calculate_line_width(&[TextToken::Word(base.clone())], true);
+            let padding = (widths[i] - base_width) / 2.0;
+            if padding <= 0.0 {
+                continue;
+            }
+            let pad = |position: usize| SpaceAdjustment {
+                position,
+                base_width,
+                adjusted_width: base_width + padding,
+                adjustment_ratio: padding / base_width.max(f32::EPSILON),
+            };
+            match starts[i].checked_sub(1) {
+                Some(previous) => adjustments.push(pad(previous)),
+                None => indent += padding,
+            }
+            adjustments.push(pad(starts[i] + token_len(token) - 1));
+        }
+        adjustments.sort_by_key(|adjustment| adjustment.position);
+        (line.width, indent, adjustments)
+    }
+
+    /// Spread `extra` over the gaps between the characters of a Chinese line, in
+    /// CLREQ order: first the gaps after punctuation and before opening brackets
+    /// (up to half a character each), then every gap evenly. Gaps inside "……",
+    /// "——" and runs of Latin letters or digits are left alone. [file:6]
+    fn cjk_justify_adjustments(&mut self, tokens: &[TextToken], extra: f32) -> Vec<SpaceAdjustment> {
+        let chars: Vec<char> = tokens.iter().map(token_char).collect();
+        let gaps: Vec<usize> = (0..chars.len().saturating_sub(1))
+            .filter(|&i| !kinsoku::is_inseparable(chars[i], chars[i + 1]))
+            .collect();
+        if gaps.is_empty() || extra <= 0.0 {
+            return Vec::new();
+        }
+
+        let widths = self.char_widths(tokens);
+        let mut expansion = vec![0.0f32; chars.len()];
+        let mut remaining = extra;
+
+        // Punctuation gaps first
+        let punctuation_gaps: Vec<usize> = gaps
+            .iter()
+            .copied()
+            .filter(|&i| {
+                (kinsoku::is_compressible(chars[i]) && !kinsoku::is_opening_bracket(chars[i]))
+                    || kinsoku::is_opening_bracket(chars[i + 1])
+            })
+            .collect();
+        for &i in &punctuation_gaps {
+            let limit = widths[i].max(widths[i + 1]) / 2.0;
+            let share = (remaining / punctuation_gaps.len() as f32).min(limit);
+            expansion[i] += share;
+        }
+        remaining -= expansion.iter().sum::<f32>();
+
+        // Then every gap evenly
+        if remaining > 0.0 {
+            let share = remaining / gaps.len() as f32;
+            for &i in &gaps {
+                expansion[i] += share;
+            }
+        }
+
+        gaps.iter()
+            .map(|&i| SpaceAdjustment {
+                position: i,
+                base_width: widths[i],
+                adjusted_width: widths[i] + expansion[i],
+                adjustment_ratio: if widths[i] > 0.0 { expansion[i] / widths[i] } else { 0.0 },
+            })
+            .collect()
+    }
+
     /// Find all possible breakpoints in the token stream. [file:1][file:6]
-    fn find_breakpoints(&self, tokens: &[TextToken], is_chinese: bool) -> Result<Vec<usize>> {
+    fn find_breakpoints(&self, tokens: &[TextToken]) -> Result<Vec<usize>> {
         let mut breakpoints = Vec::new();
 
         for (i, token) in tokens.iter().enumerate() {
@@ -405,18 +1183,10 @@ impl TextLayoutEngine {
                     // Can break after certain punctuation. [file:6]
                     breakpoints.push(i + 1);
                 }
-                TextToken::DiscretionaryHyphen => {
-                    // Can break at soft hyphen. [file:1][file:6]
+                TextToken::DiscretionaryHyphen | TextToken::ZeroWidthBreak => {
+                    // Can break at soft hyphens, hyphenation points and between syllables. [file:1][file:6]
                     breakpoints.push(i + 1);
                 }
-                TextToken::Word(word) if !is_chinese => {
-                    // Check for hyphenation points in English words. [file:1][file:6]
-                    if let Some(hyphen_points) = self.find_hyphenation_points(word) {
-                        for &pos in &hyphen_points {
-                            breakpoints.push(i + pos);
-                        }
-                    }
-                }
                 _ => {}
             }
         }
@@ -426,7 +1196,8 @@ impl TextLayoutEngine {
         Ok(breakpoints)
     }
 
-    /// Simple hyphenation point detection according to the guide. [file:1][file:6]
+    /// Hyphenation points (character positions) of an English word, from the
+    /// selected language's patterns. [file:1][file:6]
     fn find_hyphenation_points(&self, word: &str) -> Option<Vec<usize>> {
         // Do not hyphenate words shorter than 6 characters. [file:1]
         if word.chars().count() < 6 {
@@ -434,25 +1205,34 @@ impl TextLayoutEngine {
         }
 
         // Avoid hyphenating capitalized words (names). [file:1]
-        if word.chars().next().map_or(false, |c| c.is_uppercase()) {
+        if word.chars().next().is_some_and(|c| c.is_uppercase()) {
             return None;
         }
 
-        let mut points = Vec::new();
-        let chars: Vec<char> = word.chars().collect();
-        let len = chars.len();
-
-        // Do not hyphenate with fewer than 3 letters before/after the hyphen. [file:1]
-        let start = 3;
-        let end = len.saturating_sub(3);
+        // Quoted Chinese terms are never hyphenated.
+        if self.detect_script(word) {
+            return None;
+        }
 
-        for i in start..end {
-            let ch = chars[i - 1];
-            if matches!(ch, 'a' | 'e' | 'i' | 'o' | 'u' | 'y') {
-                points.push(i);
-            }
+        // Hyphenate the letters between any leading and trailing quotes; words with
+        // digits, apostrophes or explicit hyphens inside are left whole.
+        let chars: Vec<char> = word.chars().collect();
+        let is_letter = |ch: &char| ch.is_alphabetic() || is_combining_mark(*ch);
+        let start = chars.iter().position(is_letter)?;
+        let end = chars.iter().rposition(is_letter)? + 1;
+        if !chars[start..end].iter().all(is_letter) {
+            return None;
         }
 
+        let core: String = chars[start..end].iter().collect();
+        let points: Vec<usize> = self
+            .font_context
+            .hyphenation
+            .hyphenate(&core)
+            .into_iter()
+            .map(|point| start + point)
+            .collect();
+
         if points.is_empty() {
             None
         } else {
@@ -460,32 +1240,119 @@ impl TextLayoutEngine {
         }
     }
 
-    /// Calculate line width from tokens. [file:6]
+    /// Calculate line width from tokens, each script run in its own fonts, with
+    /// CJK–Latin spacing between ideographs and Latin letters or digits. [file:6]
     fn calculate_line_width(&mut self, tokens: &[TextToken], is_chinese: bool) -> f32 {
         let mut width = 0.0;
+        let visible_end = visible_end(tokens);
+        let mut previous: Option<char> = None;
 
-        for token in tokens {
-            match token {
-                TextToken::Word(word) => {
-                    width += self.font_context.calculate_text_width(word, is_chinese);
-                }
-                TextToken::Space => {
-                    width += self.font_context.calculate_text_width(" ", is_chinese);
-                }
-                TextToken::Punctuation(punct) => {
-                    width += self
-                        .font_context
-                        .calculate_text_width(&punct.to_string(), is_chinese);
-                }
-                TextToken::DiscretionaryHyphen => {
-                    width += self.font_context.calculate_text_width("-", is_chinese);
+        for (i, token) in tokens.iter().enumerate() {
+            let text = match token {
+                TextToken::Word(word) | TextToken::Ruby { base: word, .. } => word.clone(),
+                TextToken::Space => " ".to_string(),
+                TextToken::Punctuation(punct) => punct.to_string(),
+                TextToken::DiscretionaryHyphen if i + 1 == visible_end => "-".to_string(),
+                TextToken::DiscretionaryHyphen | TextToken::ZeroWidthBreak => continue,
+            };
+            let chars: Vec<char> = text.chars().collect();
+            let mut token_width = 0.0;
+            for (start, end, run_is_chinese) in self.script_runs(&text, is_chinese) {
+                let run: String = chars[start..end].iter().collect();
+                token_width += self.font_context.calculate_text_width(&run, run_is_chinese);
+            }
+            if let TextToken::Ruby { text: reading, .. } = token {
+                token_width = token_width.max(self.ruby_width(reading));
+            }
+            width += token_width;
+            // Spacing goes between clusters, by their base characters
+            for cluster in syllables::clusters(&text) {
+                let base = cluster.chars().next().unwrap_or(' ');
+                if let Some(before) = previous {
+                    width += self.font_context.script_gap(before, base);
                 }
+                previous = Some(base);
             }
         }
 
         width
     }
 
+    /// Script runs of a line's text as (start, end, is_chinese). Vertical Chinese
+    /// columns are set in the Chinese fonts throughout, Latin letters upright.
+    fn script_runs(&self, text: &str, is_chinese: bool) -> Vec<(usize, usize, bool)> {
+        if is_chinese && self.font_context.writing_mode == WritingMode::Vertical {
+            let len = text.chars().count();
+            return if len == 0 { Vec::new() } else { vec![(0, len, true)] };
+        }
+        itemize::script_runs(text, is_chinese)
+    }
+
+    /// Script runs of a line with the font size of each
+    fn line_runs(&self, text: &str, is_chinese: bool) -> Vec<ScriptRun> {
+        self.script_runs(text, is_chinese)
+            .into_iter()
+            .map(|(start, end, is_chinese)| ScriptRun {
+                start,
+                end,
+                is_chinese,
+                font_size: if is_chinese {
+                    self.font_context.font_size_chinese
+                } else {
+                    self.font_context.font_size_english
+                },
+            })
+            .collect()
+    }
+
+    /// Size of ruby annotations
+    fn ruby_size(&self) -> f32 {
+        self.font_context.ruby.scale * self.font_context.font_size_chinese
+    }
+
+    /// Advance of a reading at the ruby size
+    fn ruby_width(&mut self, text: &str) -> f32 {
+        let scale = self.ruby_size();
+        let chars: Vec<char> = text.chars().collect();
+        let mut width = 0.0;
+        for (start, end, is_chinese) in itemize::script_runs(text, false) {
+            let run: String = chars[start..end].iter().collect();
+            let size = if is_chinese { self.font_context.font_size_chinese } else { self.font_context.font_size_english };
+            width += self.font_context.calculate_text_width(&run, is_chinese) * scale / size;
+        }
+        width
+    }
+
+    /// Annotations over the ruby tokens of a line, placed by character
+    fn line_ruby(&mut self, tokens: &[TextToken]) -> Vec<RubyText> {
+        let size = self.ruby_size();
+        let mut annotations = Vec::new();
+        let mut offset = 0;
+        for token in tokens {
+            let length = token_len(token);
+            if let TextToken::Ruby { text, .. } = token {
+                annotations.push(RubyText {
+                    start: offset,
+                    end: offset + length,
+                    text: text.clone(),
+                    width: self.ruby_width(text),
+                    runs: itemize::script_runs(text, false)
+                        .into_iter()
+                        .map(|(start, end, is_chinese)| ScriptRun { start, end, is_chinese, font_size: size })
+                        .collect(),
+                });
+            }
+            offset += length;
+        }
+        annotations
+    }
+
+    /// Rebuild a line's text and script runs after its tokens changed
+    fn refresh_text(&self, line: &mut FormattedLine) {
+        line.text = line.tokens_to_string();
+        line.runs = self.line_runs(&line.text, line.is_chinese);
+    }
+
     /// Apply justification with precise space adjustments. [file:1][file:6]
     fn justify_line(
         &mut self,
@@ -496,7 +1363,8 @@ impl TextLayoutEngine {
         let current_width = self.calculate_line_width(tokens, is_chinese);
         let extra_space = max_width - current_width;
 
-        if extra_space <= 0.0 || tokens.is_empty() {
+        // Tight lines shrink their spaces as loose ones stretch them. [file:6]
+        if extra_space == 0.0 || tokens.is_empty() {
             return Ok((tokens.to_vec(), Vec::new()));
         }
 
@@ -537,9 +1405,10 @@ impl TextLayoutEngine {
                 _ => {
                     adjusted_tokens.push(token.clone());
                     position += match token {
-                        TextToken::Word(word) => word.chars().count(),
+                        TextToken::Word(word) | TextToken::Ruby { base: word, .. } => word.chars().count(),
                         TextToken::Punctuation(_) => 1,
-                        TextToken::DiscretionaryHyphen => 1,
+                        // Only shown at the end of the line, after the last space.
+                        TextToken::DiscretionaryHyphen | TextToken::ZeroWidthBreak => 0,
                         TextToken::Space => 1,
                     };
                 }
@@ -549,32 +1418,92 @@ impl TextLayoutEngine {
         Ok((adjusted_tokens, adjustments))
     }
 
-    /// Optimize line breaks using dynamic programming (simplified TeX algorithm). [file:6]
+    /// Knuth–Plass total-fit line breaking over a box/glue/penalty model of the paragraph:
+    /// words and punctuation are boxes, spaces are glue and soft hyphens are flagged
+    /// penalties. Chooses the breaks with the fewest total demerits, charging for loose or
+    /// tight lines, hyphens, consecutive hyphens, fitness-class changes and widows.
+    /// Returns None when no set of breaks avoids an overfull line. [file:6]
     fn optimize_line_breaks(
-        &self,
+        &mut self,
+        tokens: &[TextToken],
         breakpoints: &[usize],
-        _max_width: f32,
-        _is_chinese: bool,
-    ) -> Result<Vec<usize>> {
-        // For now, just ensure we at least end at the last breakpoint to avoid crashes. [file:6]
-        if let Some(&last_breakpoint) = breakpoints.last() {
-            Ok(vec![last_breakpoint])
-        } else {
-            Ok(vec![])
+        measure: Measure,
+        is_chinese: bool,
+    ) -> Result<Option<Vec<usize>>> {
+        if tokens.is_empty() {
+            return Ok(Some(vec![]));
         }
+
+        let space_width = self.font_context.calculate_text_width(" ", is_chinese);
+        let mut sums = ItemSums {
+            width: vec![0.0; tokens.len() + 1],
+            stretch: vec![0.0; tokens.len() + 1],
+            shrink: vec![0.0; tokens.len() + 1],
+            words: vec![0; tokens.len() + 1],
+            hyphen_width: self.font_context.calculate_text_width("-", is_chinese),
+        };
+        for (i, token) in tokens.iter().enumerate() {
+            let (width, stretch, shrink) = match token {
+                TextToken::Space => (space_width, space_width * SPACE_STRETCH, space_width * SPACE_SHRINK),
+                // A soft hyphen has width only when the line is broken there.
+                TextToken::DiscretionaryHyphen | TextToken::ZeroWidthBreak => (0.0, 0.0, 0.0),
+                _ => (self.calculate_line_width(std::slice::from_ref(token), is_chinese), 0.0, 0.0),
+            };
+            sums.width[i + 1] = sums.width[i] + width;
+            sums.stretch[i + 1] = sums.stretch[i] + stretch;
+            sums.shrink[i + 1] = sums.shrink[i] + shrink;
+            let starts_word = matches!(token, TextToken::Word(_)) && (i == 0 || !joins_word(&tokens[i - 1]));
+            sums.words[i + 1] = sums.words[i] + usize::from(starts_word);
+        }
+
+        // Break at glue, soft hyphens and after , ; -- never so the next line starts with a space.
+        let mut candidates: Vec<usize> = breakpoints
+            .iter()
+            .copied()
+            .filter(|&b| {
+                b == tokens.len()
+                    || (b > 0
+                        && b < tokens.len()
+                        && tokens[b] != TextToken::Space
+                        && matches!(
+                            tokens[b - 1],
+                            TextToken::Space
+                                | TextToken::DiscretionaryHyphen
+                                | TextToken::ZeroWidthBreak
+                                | TextToken::Punctuation(',')
+                                | TextToken::Punctuation(';')
+                        ))
+            })
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        // Second pass accepts any badness, as long as nothing overflows. [file:6]
+        for tolerance in [TOLERANCE, f64::INFINITY] {
+            if let Some(breaks) = total_fit_breaks(tokens, &candidates, &sums, measure, tolerance) {
+                return Ok(Some(breaks));
+            }
+        }
+        Ok(None)
     }
 
     /// Post-process lines for widow/orphan control, stacked hyphens, rag, and line word counts. [file:1][file:6]
     fn post_process_lines(
         &mut self,
         lines: &mut Vec<FormattedLine>,
-        max_width: f32,
+        measure: Measure,
         is_chinese: bool,
     ) -> Result<()> {
         if lines.is_empty() {
             return Ok(());
         }
 
+        // Justified English was broken by the total-fit composer, which already weighs
+        // widows and stacked hyphens; moving words now would undo its spacing. [file:1]
+        if !is_chinese && self.font_context.justification == Justification::Justify {
+            return Ok(());
+        }
+
         // Ensure minimum of 3 lines in a paragraph (where possible). [file:1]
         if lines.len() < 3 {
             // No automatic fix here; caller may choose a different layout. [file:1]
@@ -586,12 +1515,12 @@ impl TextLayoutEngine {
 
             // No single-word last line (widow). [file:1]
             if lines[last_index].word_count() == 1 {
-                self.try_pull_word_from_previous_line(lines, last_index, max_width, is_chinese)?;
+                self.try_pull_word_from_previous_line(lines, last_index, measure.line(last_index - 1), is_chinese)?;
             }
 
             // No single-word first line of paragraph (orphan). [file:1]
             if lines[0].word_count() == 1 && lines.len() > 1 {
-                self.try_push_word_to_next_line(lines, 0, max_width, is_chinese)?;
+                self.try_push_word_to_next_line(lines, 0, measure.rest, is_chinese)?;
             }
 
             // Enforce 5–15 words per line guideline where possible. [file:1]
@@ -600,9 +1529,9 @@ impl TextLayoutEngine {
                 if wc > 0 && (wc < 5 || wc > 15) {
                     // We only adjust obvious extremes via simple neighbor moves. [file:1]
                     if wc < 5 && i + 1 < lines.len() {
-                        self.try_push_word_to_next_line(lines, i, max_width, is_chinese)?;
+                        self.try_push_word_to_next_line(lines, i, measure.rest, is_chinese)?;
                     } else if wc > 15 && i + 1 < lines.len() {
-                        self.try_pull_word_from_next_line(lines, i, max_width, is_chinese)?;
+                        self.try_pull_word_from_next_line(lines, i, measure.line(i), is_chinese)?;
                     }
                 }
             }
@@ -612,7 +1541,7 @@ impl TextLayoutEngine {
         for i in 1..lines.len() {
             if lines[i - 1].ends_with_hyphen() && lines[i].ends_with_hyphen() {
                 // Mark second line as non-hyphenated by trying to pull one more word. [file:1]
-                self.try_pull_word_from_next_line(lines, i - 1, max_width, is_chinese)?;
+                self.try_pull_word_from_next_line(lines, i - 1, measure.line(i - 1), is_chinese)?;
             }
         }
 
@@ -641,19 +1570,7 @@ impl TextLayoutEngine {
             return Ok(());
         }
 
-        let mut first_word_tokens = Vec::new();
-        for token in &lines[last_index].tokens {
-            match token {
-                TextToken::Word(_) => {
-                    first_word_tokens.push(token.clone());
-                    break;
-                }
-                TextToken::Space => {
-                    first_word_tokens.push(token.clone());
-                }
-                _ => break,
-            }
-        }
+        let first_word_tokens = Self::leading_word_tokens(&lines[last_index].tokens);
 
         if first_word_tokens.is_empty() {
             return Ok(());
@@ -664,12 +1581,12 @@ impl TextLayoutEngine {
             // Move tokens.
             lines[prev_index].tokens.extend(first_word_tokens.clone());
             lines[prev_index].width += added_width;
-            lines[prev_index].text = lines[prev_index].tokens_to_string();
+            self.refresh_text(&mut lines[prev_index]);
 
             let remove_count = first_word_tokens.len();
             lines[last_index].tokens.drain(0..remove_count);
             lines[last_index].width = self.calculate_line_width(&lines[last_index].tokens, is_chinese);
-            lines[last_index].text = lines[last_index].tokens_to_string();
+            self.refresh_text(&mut lines[last_index]);
         }
 
         Ok(())
@@ -691,24 +1608,8 @@ impl TextLayoutEngine {
             return Ok(());
         }
 
-        let mut trailing_tokens = Vec::new();
-        let mut remove_from = lines[index].tokens.len();
-
-        for (i, token) in lines[index].tokens.iter().enumerate().rev() {
-            match token {
-                TextToken::Word(_) => {
-                    trailing_tokens.push(token.clone());
-                    remove_from = i;
-                    break;
-                }
-                TextToken::Space => {
-                    trailing_tokens.push(token.clone());
-                }
-                _ => break,
-            }
-        }
-
-        trailing_tokens.reverse();
+        let remove_from = Self::trailing_word_start(&lines[index].tokens);
+        let trailing_tokens = lines[index].tokens[remove_from..].to_vec();
 
         if trailing_tokens.is_empty() {
             return Ok(());
@@ -719,11 +1620,11 @@ impl TextLayoutEngine {
             // Move tokens.
             lines[index + 1].tokens.splice(0..0, trailing_tokens.clone());
             lines[index + 1].width += added_width;
-            lines[index + 1].text = lines[index + 1].tokens_to_string();
+            self.refresh_text(&mut lines[index + 1]);
 
             lines[index].tokens.truncate(remove_from);
             lines[index].width = self.calculate_line_width(&lines[index].tokens, is_chinese);
-            lines[index].text = lines[index].tokens_to_string();
+            self.refresh_text(&mut lines[index]);
         }
 
         Ok(())
@@ -745,19 +1646,7 @@ impl TextLayoutEngine {
             return Ok(());
         }
 
-        let mut first_word_tokens = Vec::new();
-        for token in &lines[index + 1].tokens {
-            match token {
-                TextToken::Word(_) => {
-                    first_word_tokens.push(token.clone());
-                    break;
-                }
-                TextToken::Space => {
-                    first_word_tokens.push(token.clone());
-                }
-                _ => break,
-            }
-        }
+        let first_word_tokens = Self::leading_word_tokens(&lines[index + 1].tokens);
 
         if first_word_tokens.is_empty() {
             return Ok(());
@@ -767,18 +1656,58 @@ impl TextLayoutEngine {
         if lines[index].width + added_width <= max_width {
             lines[index].tokens.extend(first_word_tokens.clone());
             lines[index].width += added_width;
-            lines[index].text = lines[index].tokens_to_string();
+            self.refresh_text(&mut lines[index]);
 
             let remove_count = first_word_tokens.len();
             lines[index + 1].tokens.drain(0..remove_count);
             lines[index + 1].width =
                 self.calculate_line_width(&lines[index + 1].tokens, is_chinese);
-            lines[index + 1].text = lines[index + 1].tokens_to_string();
+            self.refresh_text(&mut lines[index + 1]);
         }
 
         Ok(())
     }
 
+    /// Leading spaces and the first word of a line, including all pieces of a
+    /// word split at hyphenation points. [file:6]
+    fn leading_word_tokens(tokens: &[TextToken]) -> Vec<TextToken> {
+        let mut word = Vec::new();
+        for token in tokens {
+            let in_word = word.iter().any(|t| matches!(t, TextToken::Word(_)));
+            match token {
+                TextToken::Space if !in_word => word.push(token.clone()),
+                TextToken::Word(_) if !in_word || word.last().is_some_and(joins_word) => word.push(token.clone()),
+                TextToken::DiscretionaryHyphen | TextToken::ZeroWidthBreak if in_word => word.push(token.clone()),
+                _ => break,
+            }
+        }
+        // A hyphen or break the line ends at stays with the line.
+        if word.last().is_some_and(joins_word) {
+            word.clear();
+        }
+        word
+    }
+
+    /// Start of the last word of a line and the spaces after it (the line length if
+    /// the line does not end in a word). [file:6]
+    fn trailing_word_start(tokens: &[TextToken]) -> usize {
+        let mut start = tokens.len();
+        while start > 0 && tokens[start - 1] == TextToken::Space {
+            start -= 1;
+        }
+        if start == 0 || !matches!(tokens[start - 1], TextToken::Word(_)) {
+            return tokens.len();
+        }
+        start -= 1;
+        while start >= 2
+            && joins_word(&tokens[start - 1])
+            && matches!(tokens[start - 2], TextToken::Word(_))
+        {
+            start -= 2;
+        }
+        start
+    }
+
     /// Adjust rag to avoid sloping alignment and keep an undulating rag. [file:1][file:6]
     fn adjust_rag(&mut self, lines: &mut [FormattedLine]) -> Result<()> {
         if lines.len() < 3 {
@@ -907,12 +1836,7 @@ impl TextLayoutEngine {
     /// Detect script of text (Chinese vs English). [file:6]
     pub fn detect_script(&self, text: &str) -> bool {
         // If any character is CJK script, treat as Chinese. [file:6]
-        text.chars().any(|ch| {
-            ch.script() == Script::Han
-                || ch.script() == Script::Hiragana
-                || ch.script() == Script::Katakana
-                || ch.script() == Script::Bopomofo
-        })
+        text.chars().any(itemize::is_ideographic)
     }
 }
 
@@ -920,3 +1844,168 @@ impl TextLayoutEngine {
 pub fn create_layout_engine(font_context: FontContext) -> TextLayoutEngine {
     TextLayoutEngine::new(font_context)
 }
+
+/// Line penalty added to every line's badness, as TeX's `\linepenalty`
+const LINE_PENALTY: f64 = 10.0;
+/// Penalty for breaking at a hyphenation point (`\hyphenpenalty`)
+const HYPHEN_PENALTY: f64 = 50.0;
+/// Extra demerits for two hyphenated lines in a row (`\doublehyphendemerits`)
+const DOUBLE_HYPHEN_DEMERITS: f64 = 10_000.0;
+/// Extra demerits for hyphenating the second-last line (`\finalhyphendemerits`)
+const FINAL_HYPHEN_DEMERITS: f64 = 5_000.0;
+/// Extra demerits between lines whose fitness classes are not adjacent (`\adjdemerits`)
+const ADJ_DEMERITS: f64 = 10_000.0;
+/// Extra demerits for a last line holding a single word (widow). [file:1]
+const WIDOW_DEMERITS: f64 = 10_000.0;
+/// Largest badness accepted on the first pass (`\tolerance`)
+const TOLERANCE: f64 = 200.0;
+/// Badness of a line that cannot stretch far enough
+const INFINITELY_BAD: f64 = 10_000.0;
+/// Interword glue stretches by half a space and shrinks by a third
+const SPACE_STRETCH: f32 = 0.5;
+const SPACE_SHRINK: f32 = 1.0 / 3.0;
+/// Aksaras kept on either side of a hyphen in a Devanagari word
+const SYLLABLES_BEFORE_HYPHEN: usize = 2;
+const SYLLABLES_AFTER_HYPHEN: usize = 2;
+
+/// Running totals over the paragraph's tokens, so any line's natural width,
+/// stretch and shrink is a difference of two entries.
+struct ItemSums {
+    width: Vec<f32>,
+    stretch: Vec<f32>,
+    shrink: Vec<f32>,
+    words: Vec<usize>,
+    hyphen_width: f32,
+}
+
+/// A feasible break found by the total-fit search
+struct BreakNode {
+    /// Token index the next line starts at
+    position: usize,
+    /// 0 tight, 1 decent, 2 loose, 3 very loose
+    fitness: usize,
+    hyphenated: bool,
+    total_demerits: f64,
+    previous: Option<usize>,
+}
+
+/// One Knuth–Plass pass: keeps the active breaks from which a line can still be
+/// set, and for each candidate the cheapest way to reach it per fitness class.
+fn total_fit_breaks(
+    tokens: &[TextToken],
+    candidates: &[usize],
+    sums: &ItemSums,
+    measure: Measure,
+    tolerance: f64,
+) -> Option<Vec<usize>> {
+    let end = tokens.len();
+    let mut nodes = vec![BreakNode {
+        position: 0,
+        fitness: 1,
+        hyphenated: false,
+        total_demerits: 0.0,
+        previous: None,
+    }];
+    let mut active = vec![0usize];
+
+    for &b in candidates {
+        let last_line = b == end;
+        let hyphenated = !last_line && tokens[b - 1] == TextToken::DiscretionaryHyphen;
+        // The glue a line is broken at is discarded.
+        let line_end = tokens[..b]
+            .iter()
+            .rposition(|t| *t != TextToken::Space)
+            .map_or(0, |i| i + 1);
+        let mut best: [Option<(f64, usize)>; 4] = [None; 4];
+
+        active.retain(|&a| {
+            let node = &nodes[a];
+            let line_start = node.position;
+            let max_width = measure.line(line_start);
+            let natural = sums.width[line_end.max(line_start)] - sums.width[line_start]
+                + if hyphenated { sums.hyphen_width } else { 0.0 };
+            let ratio = if natural > max_width {
+                let shrink = sums.shrink[line_end.max(line_start)] - sums.shrink[line_start];
+                if shrink > 0.0 { ((max_width - natural) / shrink) as f64 } else { f64::NEG_INFINITY }
+            } else if last_line {
+                // The last line ends with infinitely stretchable glue.
+                0.0
+            } else {
+                let stretch = sums.stretch[line_end.max(line_start)] - sums.stretch[line_start];
+                if stretch > 0.0 {
+                    ((max_width - natural) / stretch) as f64
+                } else if natural < max_width {
+                    f64::INFINITY
+                } else {
+                    0.0
+                }
+            };
+
+            let badness = (100.0 * ratio.abs().powi(3)).min(INFINITELY_BAD);
+            if ratio >= -1.0 && badness <= tolerance {
+                let fitness: usize = match ratio {
+                    r if r < -0.5 => 0,
+                    r if r <= 0.5 => 1,
+                    r if r <= 1.0 => 2,
+                    _ => 3,
+                };
+                let mut demerits = (LINE_PENALTY + badness).powi(2);
+                if hyphenated {
+                    demerits += HYPHEN_PENALTY.powi(2);
+                    if node.hyphenated {
+                        demerits += DOUBLE_HYPHEN_DEMERITS;
+                    }
+                }
+                if last_line && node.hyphenated {
+                    demerits += FINAL_HYPHEN_DEMERITS;
+                }
+                if fitness.abs_diff(node.fitness) > 1 {
+                    demerits += ADJ_DEMERITS;
+                }
+                if last_line && line_start > 0 && sums.words[end] - sums.words[line_start] == 1 {
+                    demerits += WIDOW_DEMERITS;
+                }
+
+                let total = node.total_demerits + demerits;
+                if best[fitness].is_none_or(|(current, _)| total < current) {
+                    best[fitness] = Some((total, a));
+                }
+            }
+
+            // Later breaks only make this line longer; the end of the paragraph is forced.
+            ratio >= -1.0 && !last_line
+        });
+
+        for (fitness, entry) in best.iter().enumerate() {
+            if let Some((total_demerits, previous)) = *entry {
+                nodes.push(BreakNode {
+                    position: b,
+                    fitness,
+                    hyphenated,
+                    total_demerits,
+                    previous: Some(previous),
+                });
+                active.push(nodes.len() - 1);
+            }
+        }
+
+        if active.is_empty() {
+            return None;
+        }
+    }
+
+    let mut current = active
+        .iter()
+        .copied()
+        .filter(|&a| nodes[a].position == end)
+        .min_by(|&a, &b| nodes[a].total_demerits.total_cmp(&nodes[b].total_demerits));
+    let mut breaks = Vec::new();
+    while let Some(index) = current {
+        if nodes[index].position > 0 {
+            breaks.push(nodes[index].position);
+        }
+        current = nodes[index].previous;
+    }
+    breaks.reverse();
+    Some(breaks)
+}
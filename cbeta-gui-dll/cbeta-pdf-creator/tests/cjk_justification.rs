@@ -0,0 +1,58 @@
+//! Justified Chinese lines fill the column: leftover space goes to the gaps by
+//! punctuation first, then evenly between all characters.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::FontContext;
+use cbeta_pdf_creator::typography::TextLayoutEngine;
+
+fn test_context() -> FontContext {
+    let chars: Vec<char> = "如是我聞一時佛在舍衛國，「」".chars().collect();
+    common::test_context(&chars, &['a'])
+}
+
+#[test]
+fn short_lines_are_spread_to_the_measure() {
+    let mut engine = TextLayoutEngine::new(test_context());
+    // The opening bracket may not end the line, so the first line is a character short.
+    let paragraph = engine.layout_paragraph("如是，我聞一時佛在「衛國」", 0.0, 0.0, 100.0, true).unwrap();
+    let first = &paragraph.lines[0];
+    assert_eq!(first.text, "如是，我聞一時佛在");
+    assert!((first.width - 100.0).abs() < 1e-3, "width {}", first.width);
+
+    let expansion: Vec<(usize, f32)> = first
+        .space_adjustments
+        .iter()
+        .map(|adjustment| (adjustment.position, adjustment.adjusted_width - adjustment.base_width))
+        .collect();
+    // Half a character after the comma, the rest shared by all eight gaps
+    assert_eq!(expansion.len(), 8);
+    assert!((expansion[2].1 - 5.625).abs() < 1e-3, "{:?}", expansion);
+    assert!(expansion.iter().filter(|&&(position, _)| position != 2).all(|&(_, extra)| (extra - 0.625).abs() < 1e-3));
+
+    // The last line keeps its natural spacing.
+    assert!(paragraph.lines.last().unwrap().space_adjustments.is_empty());
+}
+
+#[test]
+fn pdf_spaces_characters_with_tj_offsets() {
+    let context = test_context();
+    let chinese = "如是我聞一時佛在舍衛國，".repeat(12);
+    let document = common::sections(&[chinese], &[String::new()]);
+    let pages = common::render_pages(&context, &document, LayoutMode::Alternating, "cjk_justification");
+
+    // Every line but the last moves its characters apart (negative TJ numbers).
+    let spread_lines = pages[0]
+        .iter()
+        .filter(|op| op.operator == "TJ")
+        .filter(|op| {
+            op.operands[0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|item| item.as_float().is_ok_and(|value| value < 0.0))
+        })
+        .count();
+    assert!(spread_lines >= 1);
+}
@@ -0,0 +1,83 @@
+//! Full-width punctuation is compressed where marks meet or start and end a
+//! line, and 。，、 may hang into the margin.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::FontContext;
+use cbeta_pdf_creator::typography::{FormattedLine, TextLayoutEngine};
+
+fn test_context() -> FontContext {
+    let chars: Vec<char> = "如是我聞一時佛在舍衛國祇，。「」".chars().collect();
+    common::test_context(&chars, &['a'])
+}
+
+fn layout(context: &FontContext, text: &str, max_width: f32) -> Vec<FormattedLine> {
+    let mut engine = TextLayoutEngine::new(context.clone());
+    engine.layout_paragraph(text, 0.0, 0.0, max_width, true).expect("layout succeeds").lines
+}
+
+fn squeezed(line: &FormattedLine) -> Vec<(usize, f32)> {
+    line.space_adjustments
+        .iter()
+        .map(|adjustment| (adjustment.position, adjustment.adjusted_width - adjustment.base_width))
+        .collect()
+}
+
+#[test]
+fn adjacent_and_line_edge_punctuation_is_set_half_width() {
+    let mut context = test_context();
+
+    // The full stop gives up its blank to the closing bracket after it.
+    let line = &layout(&context, "如是。」我聞", 200.0)[0];
+    assert_eq!(squeezed(line), [(2, -5.0)]);
+    assert_eq!(line.width, 55.0);
+
+    // A closing and an opening bracket keep half a character between them.
+    let line = &layout(&context, "如」「是", 200.0)[0];
+    assert_eq!(squeezed(line), [(1, -5.0)]);
+
+    // Brackets at the start and end of a line lose their outer blank.
+    let line = &layout(&context, "「如是」", 200.0)[0];
+    assert_eq!(line.indent, -5.0);
+    assert!(line.space_adjustments.is_empty());
+    assert_eq!(line.width, 30.0);
+
+    context.punctuation_compression = false;
+    let line = &layout(&context, "「如是。」", 200.0)[0];
+    assert_eq!(line.indent, 0.0);
+    assert!(line.space_adjustments.is_empty());
+    assert_eq!(line.width, 50.0);
+}
+
+#[test]
+fn full_stops_hang_into_the_margin_when_enabled() {
+    let mut context = test_context();
+    let text = "如是我聞一時佛在舍衛。國祇";
+
+    let lines: Vec<String> = layout(&context, text, 100.0).into_iter().map(|line| line.text).collect();
+    assert_eq!(lines, ["如是我聞一時佛在舍", "衛。國祇"]);
+
+    context.hanging_punctuation = true;
+    let lines = layout(&context, text, 100.0);
+    assert_eq!(lines[0].text, "如是我聞一時佛在舍衛。");
+    assert_eq!(lines[0].width, 100.0);
+    assert!(lines[0].space_adjustments.is_empty());
+}
+
+#[test]
+fn pdf_moves_trimmed_brackets_back_to_the_line_start() {
+    let context = test_context();
+    let document = common::sections(&["「如是。」我聞".to_string()], &[String::new()]);
+    let pages = common::render_pages(&context, &document, LayoutMode::Alternating, "cjk_punctuation");
+    let tj = pages[0]
+        .iter()
+        .find(|op| op.operator == "TJ")
+        .map(|op| op.operands[0].as_array().unwrap().clone())
+        .expect("Chinese line shown");
+
+    // Half a character back before the bracket, half a character less after the full stop
+    let offsets: Vec<f32> = tj.iter().filter_map(|item| item.as_float().ok()).collect();
+    assert_eq!(offsets, [500.0, 500.0]);
+    assert!(tj[0].as_float().is_ok());
+}
@@ -0,0 +1,406 @@
+//! Shared helpers for integration tests
+//!
+//! Builds tiny in-memory TrueType fonts so tests do not depend on the CJK and
+//! serif fonts installed on the machine running them, font contexts using them,
+//! and PDFs rendered to temporary files and read back.
+
+use cbeta_pdf_creator::document::{DocumentBuilder, LayoutMode};
+use cbeta_pdf_creator::fonts::FontContext;
+use lopdf::content::{Content, Operation};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Context with test fonts drawing `chinese` 1000 units and `english` 500 units
+/// wide, on an A4 page with 72pt margins and both languages at 10pt
+#[allow(dead_code)]
+pub fn test_context(chinese: &[char], english: &[char]) -> FontContext {
+    let mut context = FontContext::from_font_data(
+        "Test CJK",
+        build_test_font(chinese, 1000),
+        "Test Latin",
+        build_test_font(english, 500),
+    )
+    .expect("test fonts load");
+    context.set_options(595.0, 842.0, 72.0, 10.0, 10.0, 1.4, 0.0, 0.0, 0.6);
+    context
+}
+
+/// Document of parallel sections, as the `create_*_pdf` functions build it
+#[allow(dead_code)]
+pub fn sections(chinese: &[String], english: &[String]) -> DocumentBuilder {
+    DocumentBuilder::from_sections(chinese, english).expect("sections pair up")
+}
+
+/// Write `document` in `layout` and read the PDF back.
+///
+/// The temporary file is named after `name` plus a counter, so tests running
+/// in parallel never read or delete each other's output.
+#[allow(dead_code)]
+pub fn render_pdf(context: &FontContext, document: &DocumentBuilder, layout: LayoutMode, name: &str) -> lopdf::Document {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let file = format!("cbeta_{}_{}_{}.pdf", name, std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+    let output = std::env::temp_dir().join(file);
+
+    document
+        .write_pdf(layout, output.to_str().unwrap(), context)
+        .expect("PDF generation succeeds");
+    let pdf = lopdf::Document::load(&output);
+    let _ = std::fs::remove_file(&output);
+    pdf.expect("generated PDF parses")
+}
+
+/// Content stream operations of each page
+#[allow(dead_code)]
+pub fn page_operations(pdf: &lopdf::Document) -> Vec<Vec<Operation>> {
+    pdf.page_iter()
+        .map(|page_id| Content::decode(&pdf.get_page_content(page_id).unwrap()).unwrap().operations)
+        .collect()
+}
+
+/// Content stream operations of each page of `document` set in `layout`
+#[allow(dead_code)]
+pub fn render_pages(context: &FontContext, document: &DocumentBuilder, layout: LayoutMode, name: &str) -> Vec<Vec<Operation>> {
+    page_operations(&render_pdf(context, document, layout, name))
+}
+
+/// Build a TrueType font with one box glyph per character.
+///
+/// Glyph IDs follow the order of `chars` (starting at 1, after .notdef) and every
+/// glyph advances by `advance` font units on a 1000 unit em.
+pub fn build_test_font(chars: &[char], advance: u16) -> Vec<u8> {
+    let mut mapped: Vec<char> = chars.to_vec();
+    mapped.dedup();
+    let cmap: Vec<(char, u16)> = mapped.iter().enumerate().map(|(i, &ch)| (ch, i as u16 + 1)).collect();
+    build_test_font_with_cmap(&cmap, advance)
+}
+
+/// Build a TrueType font from explicit (character, glyph ID) pairs, so several
+/// characters can share one glyph.
+#[allow(dead_code)]
+pub fn build_test_font_with_cmap(cmap_entries: &[(char, u16)], advance: u16) -> Vec<u8> {
+    build_test_font_with_tables(cmap_entries, advance, Vec::new())
+}
+
+/// Build a TrueType font from (character, glyph ID) pairs plus extra tables such
+/// as GSUB or GPOS.
+#[allow(dead_code)]
+pub fn build_test_font_with_tables(
+    cmap_entries: &[(char, u16)],
+    advance: u16,
+    extra_tables: Vec<(&[u8; 4], Vec<u8>)>,
+) -> Vec<u8> {
+    let num_glyphs = cmap_entries.iter().map(|&(_, gid)| gid).max().unwrap_or(0) + 1;
+
+    // glyf + loca (long offsets): every glyph is a simple four-point box
+    let mut glyf = Vec::new();
+    let mut loca = Vec::new();
+    for _ in 0..num_glyphs {
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+        push_i16(&mut glyf, 1); // numberOfContours
+        for v in [50i16, 0, advance as i16 - 50, 700] {
+            push_i16(&mut glyf, v);
+        }
+        push_u16(&mut glyf, 3); // endPtsOfContours
+        push_u16(&mut glyf, 0); // instructionLength
+        glyf.extend_from_slice(&[0x01; 4]); // on-curve, 16-bit deltas
+        for dx in [50i16, 0, advance as i16 - 100, 0] {
+            push_i16(&mut glyf, dx);
+        }
+        for dy in [0i16, 700, 0, -700] {
+            push_i16(&mut glyf, dy);
+        }
+    }
+    loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+
+    let mut head = Vec::new();
+    push_u32(&mut head, 0x0001_0000); // version
+    push_u32(&mut head, 0x0001_0000); // fontRevision
+    push_u32(&mut head, 0); // checkSumAdjustment
+    push_u32(&mut head, 0x5F0F_3CF5); // magicNumber
+    push_u16(&mut head, 0x000B); // flags
+    push_u16(&mut head, 1000); // unitsPerEm
+    head.extend_from_slice(&[0; 16]); // created, modified
+    for v in [0i16, -120, advance as i16, 880] {
+        push_i16(&mut head, v);
+    }
+    push_u16(&mut head, 0); // macStyle
+    push_u16(&mut head, 8); // lowestRecPPEM
+    push_i16(&mut head, 2); // fontDirectionHint
+    push_i16(&mut head, 1); // indexToLocFormat (long)
+    push_i16(&mut head, 0); // glyphDataFormat
+
+    let mut hhea = Vec::new();
+    push_u32(&mut hhea, 0x0001_0000);
+    for v in [880i16, -120, 0] {
+        push_i16(&mut hhea, v); // ascender, descender, lineGap
+    }
+    push_u16(&mut hhea, advance); // advanceWidthMax
+    for v in [0i16, 0, advance as i16, 1, 0, 0, 0, 0, 0, 0, 0] {
+        push_i16(&mut hhea, v);
+    }
+    push_u16(&mut hhea, num_glyphs); // numberOfHMetrics
+
+    let mut hmtx = Vec::new();
+    for _ in 0..num_glyphs {
+        push_u16(&mut hmtx, advance);
+        push_i16(&mut hmtx, 50);
+    }
+
+    let mut maxp = Vec::new();
+    push_u32(&mut maxp, 0x0001_0000);
+    push_u16(&mut maxp, num_glyphs);
+    for v in [4u16, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0] {
+        push_u16(&mut maxp, v);
+    }
+
+    // cmap with a single format 12 subtable (Windows, UCS-4)
+    let mut cmap = Vec::new();
+    push_u16(&mut cmap, 0);
+    push_u16(&mut cmap, 1);
+    push_u16(&mut cmap, 3);
+    push_u16(&mut cmap, 10);
+    push_u32(&mut cmap, 12);
+    let mut groups: Vec<(u32, u32)> = cmap_entries
+        .iter()
+        .map(|&(ch, gid)| (ch as u32, gid as u32))
+        .collect();
+    groups.sort_unstable();
+    push_u16(&mut cmap, 12);
+    push_u16(&mut cmap, 0);
+    push_u32(&mut cmap, 16 + 12 * groups.len() as u32);
+    push_u32(&mut cmap, 0);
+    push_u32(&mut cmap, groups.len() as u32);
+    for (code, gid) in groups {
+        push_u32(&mut cmap, code);
+        push_u32(&mut cmap, code);
+        push_u32(&mut cmap, gid);
+    }
+
+    let mut os2 = vec![0u8; 86];
+    os2[0..2].copy_from_slice(&1u16.to_be_bytes()); // version
+    os2[2..4].copy_from_slice(&(advance as i16).to_be_bytes()); // xAvgCharWidth
+    os2[4..6].copy_from_slice(&400u16.to_be_bytes()); // usWeightClass
+    os2[6..8].copy_from_slice(&5u16.to_be_bytes()); // usWidthClass
+
+    let mut post = Vec::new();
+    push_u32(&mut post, 0x0003_0000);
+    post.extend_from_slice(&[0; 28]);
+
+    let mut tables: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"OS/2", os2),
+        (b"cmap", cmap),
+        (b"glyf", glyf),
+        (b"head", head),
+        (b"hhea", hhea),
+        (b"hmtx", hmtx),
+        (b"loca", loca),
+        (b"maxp", maxp),
+        (b"post", post),
+    ];
+    tables.extend(extra_tables);
+    tables.sort_by_key(|(tag, _)| **tag);
+
+    let mut font = Vec::new();
+    push_u32(&mut font, 0x0001_0000);
+    push_u16(&mut font, tables.len() as u16);
+    push_u16(&mut font, 128); // searchRange
+    push_u16(&mut font, 3); // entrySelector
+    push_u16(&mut font, tables.len() as u16 * 16 - 128); // rangeShift
+
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, data) in &tables {
+        font.extend_from_slice(*tag);
+        push_u32(&mut font, checksum(data));
+        push_u32(&mut font, offset as u32);
+        push_u32(&mut font, data.len() as u32);
+        offset += (data.len() + 3) & !3;
+    }
+    for (_, data) in &tables {
+        font.extend_from_slice(data);
+        while font.len() % 4 != 0 {
+            font.push(0);
+        }
+    }
+    font
+}
+
+/// Build a TrueType collection (`ttcf`) of single-face fonts, each keeping its
+/// own tables
+#[allow(dead_code)]
+pub fn build_test_collection(faces: &[Vec<u8>]) -> Vec<u8> {
+    let mut collection = Vec::new();
+    collection.extend_from_slice(b"ttcf");
+    push_u32(&mut collection, 0x0001_0000);
+    push_u32(&mut collection, faces.len() as u32);
+    let header_len = 12 + 4 * faces.len();
+
+    let mut body = Vec::new();
+    for face in faces {
+        let base = header_len + body.len();
+        push_u32(&mut collection, base as u32);
+        let mut face = face.clone();
+        // Table offsets are from the start of the collection
+        let num_tables = u16::from_be_bytes([face[4], face[5]]) as usize;
+        for record in (0..num_tables).map(|i| 12 + 16 * i) {
+            let field = record + 8..record + 12;
+            let offset = u32::from_be_bytes(face[field.clone()].try_into().unwrap());
+            face[field].copy_from_slice(&(offset + base as u32).to_be_bytes());
+        }
+        body.extend_from_slice(&face);
+        while body.len() % 4 != 0 {
+            body.push(0);
+        }
+    }
+    collection.extend_from_slice(&body);
+    collection
+}
+
+/// Set the OS/2 `fsType` embedding bits of a font built by `build_test_font`
+#[allow(dead_code)]
+pub fn set_fs_type(font: &mut [u8], fs_type: u16) {
+    let os2 = table_offset(font, b"OS/2").expect("font has an OS/2 table");
+    font[os2 + 8..os2 + 10].copy_from_slice(&fs_type.to_be_bytes());
+}
+
+/// Set the `hmtx` advance of glyph `gid` in a font built by `build_test_font`
+#[allow(dead_code)]
+pub fn set_advance(font: &mut [u8], gid: u16, advance: u16) {
+    let hmtx = table_offset(font, b"hmtx").expect("font has an hmtx table") + 4 * gid as usize;
+    font[hmtx..hmtx + 2].copy_from_slice(&advance.to_be_bytes());
+}
+
+/// Offset of table `tag` in a single-face font
+#[allow(dead_code)]
+pub fn table_offset(font: &[u8], tag: &[u8; 4]) -> Option<usize> {
+    let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+    (0..num_tables).map(|i| 12 + 16 * i).find(|&record| &font[record..record + 4] == tag).map(|record| {
+        u32::from_be_bytes([font[record + 8], font[record + 9], font[record + 10], font[record + 11]]) as usize
+    })
+}
+
+/// `name` table giving `family` as the English (US) family name
+#[allow(dead_code)]
+pub fn name_table(family: &str) -> Vec<u8> {
+    let string: Vec<u8> = family.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let mut name = Vec::new();
+    push_u16(&mut name, 0); // format
+    push_u16(&mut name, 1); // count
+    push_u16(&mut name, 18); // storageOffset
+    for v in [3u16, 1, 0x0409, 1, string.len() as u16, 0] {
+        push_u16(&mut name, v); // Windows, Unicode BMP, en-US, family name
+    }
+    name.extend_from_slice(&string);
+    name
+}
+
+/// GSUB table whose `liga` feature replaces `components` with `ligature`
+#[allow(dead_code)]
+pub fn gsub_ligature(components: &[u16], ligature: u16) -> Vec<u8> {
+    let mut subtable = Vec::new();
+    push_u16(&mut subtable, 1); // substFormat
+    push_u16(&mut subtable, 8); // coverageOffset
+    push_u16(&mut subtable, 1); // ligatureSetCount
+    push_u16(&mut subtable, 14); // ligatureSetOffsets[0]
+    push_coverage(&mut subtable, components[0]);
+    push_u16(&mut subtable, 1); // ligatureCount
+    push_u16(&mut subtable, 4); // ligatureOffsets[0]
+    push_u16(&mut subtable, ligature);
+    push_u16(&mut subtable, components.len() as u16);
+    for &component in &components[1..] {
+        push_u16(&mut subtable, component);
+    }
+    layout_table(b"liga", 4, subtable)
+}
+
+/// GPOS table whose `kern` feature adds `adjust` units to the advance of `left`
+/// when followed by `right`
+#[allow(dead_code)]
+pub fn gpos_pair_kerning(left: u16, right: u16, adjust: i16) -> Vec<u8> {
+    let mut subtable = Vec::new();
+    push_u16(&mut subtable, 1); // posFormat
+    push_u16(&mut subtable, 12); // coverageOffset
+    push_u16(&mut subtable, 0x0004); // valueFormat1: XAdvance
+    push_u16(&mut subtable, 0); // valueFormat2
+    push_u16(&mut subtable, 1); // pairSetCount
+    push_u16(&mut subtable, 18); // pairSetOffsets[0]
+    push_coverage(&mut subtable, left);
+    push_u16(&mut subtable, 1); // pairValueCount
+    push_u16(&mut subtable, right);
+    push_i16(&mut subtable, adjust);
+    layout_table(b"kern", 2, subtable)
+}
+
+/// GPOS table whose `feature` adds `adjust` units to the advance of `glyph`
+#[allow(dead_code)]
+pub fn gpos_single_advance(feature: &[u8; 4], glyph: u16, adjust: i16) -> Vec<u8> {
+    let mut subtable = Vec::new();
+    push_u16(&mut subtable, 1); // posFormat
+    push_u16(&mut subtable, 8); // coverageOffset
+    push_u16(&mut subtable, 0x0004); // valueFormat: XAdvance
+    push_i16(&mut subtable, adjust);
+    push_coverage(&mut subtable, glyph);
+    layout_table(feature, 1, subtable)
+}
+
+/// GSUB/GPOS table with one DFLT script, one feature and one single-subtable lookup
+fn layout_table(feature: &[u8; 4], lookup_type: u16, subtable: Vec<u8>) -> Vec<u8> {
+    let mut table = Vec::new();
+    push_u32(&mut table, 0x0001_0000); // version 1.0
+    push_u16(&mut table, 10); // scriptListOffset
+    push_u16(&mut table, 30); // featureListOffset
+    push_u16(&mut table, 44); // lookupListOffset
+
+    // ScriptList: DFLT with a default LangSys using feature 0
+    push_u16(&mut table, 1);
+    table.extend_from_slice(b"DFLT");
+    push_u16(&mut table, 8);
+    push_u16(&mut table, 4); // defaultLangSysOffset
+    push_u16(&mut table, 0); // langSysCount
+    push_u16(&mut table, 0); // lookupOrderOffset
+    push_u16(&mut table, 0xFFFF); // requiredFeatureIndex
+    push_u16(&mut table, 1);
+    push_u16(&mut table, 0);
+
+    // FeatureList: the feature uses lookup 0
+    push_u16(&mut table, 1);
+    table.extend_from_slice(feature);
+    push_u16(&mut table, 8);
+    push_u16(&mut table, 0); // featureParamsOffset
+    push_u16(&mut table, 1);
+    push_u16(&mut table, 0);
+
+    // LookupList
+    push_u16(&mut table, 1);
+    push_u16(&mut table, 4);
+    push_u16(&mut table, lookup_type);
+    push_u16(&mut table, 0); // lookupFlag
+    push_u16(&mut table, 1); // subTableCount
+    push_u16(&mut table, 8);
+    table.extend_from_slice(&subtable);
+    table
+}
+
+fn push_coverage(out: &mut Vec<u8>, glyph: u16) {
+    push_u16(out, 1); // coverageFormat
+    push_u16(out, 1); // glyphCount
+    push_u16(out, glyph);
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn push_u16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn push_i16(out: &mut Vec<u8>, v: i16) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn push_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
@@ -0,0 +1,101 @@
+//! Devanagari, Siddham and Tibetan are set a syllable at a time, in fonts chosen
+//! for each script, with lines broken between syllables: Tibetan after the tsheg.
+
+mod common;
+
+use cbeta_pdf_creator::font_registry::Script;
+use cbeta_pdf_creator::fonts::FontContext;
+use cbeta_pdf_creator::kinsoku;
+use cbeta_pdf_creator::syllables::{self, SyllableBreak};
+use cbeta_pdf_creator::typography::TextLayoutEngine;
+
+const MANTRA: &str = "ཨོཾ་མ་ཎི་པདྨེ་ཧཱུྃ།";
+const SVAHA: &str = "𑖭𑖿𑖪𑖯𑖮𑖯";
+const PRAJNAPARAMITA: &str = "प्रज्ञापारमिता";
+
+fn test_context() -> FontContext {
+    let english: Vec<char> = format!("ab -{}{}{}", MANTRA, SVAHA, PRAJNAPARAMITA).chars().collect();
+    common::test_context(&['如', '是'], &english)
+}
+
+fn english_lines(text: &str, width: f32) -> Vec<String> {
+    let mut engine = TextLayoutEngine::new(test_context());
+    let paragraph = engine.layout_paragraph(text, 0.0, 0.0, width, false).expect("layout succeeds");
+    paragraph.lines.into_iter().map(|line| line.text.trim_end().to_string()).collect()
+}
+
+#[test]
+fn conjuncts_and_vowel_signs_stay_in_one_cluster() {
+    // Siddham sv + ā, joined across the virama
+    assert_eq!(syllables::clusters(SVAHA), ["𑖭𑖿𑖪𑖯", "𑖮𑖯"]);
+    assert_eq!(syllables::clusters("क्षमा"), ["क्ष", "मा"]);
+    // Tibetan subjoined letters and vowel signs stack on their root letter
+    assert_eq!(syllables::clusters("བསྒྲུབས"), ["བ", "སྒྲུ", "བ", "ས"]);
+}
+
+#[test]
+fn words_break_between_syllables() {
+    let breaks = |word: &str| syllables::break_points(word, 2, 2);
+    assert_eq!(breaks("ཨོཾ་མ་ཎི"), [(4, SyllableBreak::Plain), (6, SyllableBreak::Plain)]);
+    assert_eq!(breaks(SVAHA), [(4, SyllableBreak::Plain)]);
+    // प्र ज्ञा | पा | र | मि ता: two aksaras either side of a hyphen
+    assert_eq!(
+        breaks(PRAJNAPARAMITA),
+        [(7, SyllableBreak::Hyphenated), (9, SyllableBreak::Hyphenated), (10, SyllableBreak::Hyphenated)]
+    );
+    assert!(breaks("abc").is_empty());
+}
+
+#[test]
+fn tibetan_lines_end_at_a_tsheg_or_shad() {
+    let text = MANTRA.repeat(4);
+    let lines = english_lines(&text, 60.0);
+    assert!(lines.len() > 1, "{:?}", lines);
+    assert!(lines.iter().all(|line| line.ends_with('་') || line.ends_with('།')), "{:?}", lines);
+    assert_eq!(lines.concat(), text);
+}
+
+#[test]
+fn devanagari_words_are_hyphenated_between_aksaras() {
+    let lines = english_lines(PRAJNAPARAMITA, 40.0);
+    assert!(lines.len() > 1, "{:?}", lines);
+    let first = lines[0].strip_suffix('-').expect("first line ends with a hyphen");
+    assert!(["प्रज्ञा", "प्रज्ञापा", "प्रज्ञापार"].contains(&first), "{:?}", lines);
+    assert_eq!(lines.concat().replace('-', ""), PRAJNAPARAMITA);
+}
+
+#[test]
+fn chinese_paragraphs_break_siddham_syllables_and_after_tsheg() {
+    let chars = |text: &str| -> Vec<char> { text.chars().collect() };
+    // First characters of the clusters 𑖭𑖿𑖪𑖯 | 𑖮𑖯
+    assert!(kinsoku::can_break_before(&chars("𑖭𑖮"), 1));
+    assert!(kinsoku::can_break_before(&chars("་མ"), 1));
+    assert!(!kinsoku::can_break_before(&chars("མ་"), 1));
+    assert!(!kinsoku::can_break_before(&chars("ཎ།"), 1));
+    // Devanagari, like Latin, breaks only at spaces
+    assert!(!kinsoku::can_break_before(&chars("कम"), 1));
+    assert!(!kinsoku::can_break_before(&chars("क।"), 1));
+}
+
+#[test]
+fn each_script_uses_its_own_font() {
+    let mut context = test_context();
+    // The English font covers Tibetan too; the Tibetan font still wins for it.
+    let tibetan: Vec<char> = MANTRA.chars().collect();
+    context
+        .add_script_font_data(Script::Tibetan, "Test Tibetan", common::build_test_font(&tibetan, 700))
+        .expect("script font loads");
+
+    assert_eq!(context.cluster_slot("པདྨེ", false), 1);
+    assert_eq!(context.font_slot('a', false), 0);
+    assert_eq!(context.cluster_slot("𑖮𑖯", false), 0);
+    assert_eq!(
+        context.split_font_runs("ab མ་ཎི b", false),
+        [(0, "ab ".to_string()), (1, "མ་ཎི".to_string()), (0, " b".to_string())]
+    );
+    // Siddham and Devanagari in one font are still shaped as separate runs
+    assert_eq!(
+        context.split_font_runs(&format!("{}{}", SVAHA, PRAJNAPARAMITA), false),
+        [(0, SVAHA.to_string()), (0, PRAJNAPARAMITA.to_string())]
+    );
+}
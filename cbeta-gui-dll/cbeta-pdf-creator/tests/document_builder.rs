@@ -0,0 +1,143 @@
+//! Structured documents: typed blocks set in every layout mode, headings styled
+//! and kept with what follows, page breaks and document metadata.
+
+mod common;
+
+use cbeta_pdf_creator::document::{self, Block, DocumentBuilder, LayoutMode};
+use cbeta_pdf_creator::fonts::{FontContext, Justification};
+use cbeta_pdf_creator::styles::ParagraphStyle;
+use lopdf::{Document, Object};
+
+const TEXT: &str = "如是我聞一時佛在舍衛國";
+
+fn test_context() -> FontContext {
+    let chars: Vec<char> = TEXT.chars().collect();
+    common::test_context(&chars, &['a', ' '])
+}
+
+fn write(document: &DocumentBuilder, layout: LayoutMode, name: &str) -> Document {
+    common::render_pdf(&test_context(), document, layout, name)
+}
+
+/// The operations of each page that match `operator`, with their operands
+fn page_operands(pdf: &Document, operator: &str) -> Vec<Vec<Object>> {
+    common::page_operations(pdf)
+        .into_iter()
+        .flatten()
+        .filter(|op| op.operator == operator)
+        .map(|op| op.operands)
+        .collect()
+}
+
+/// Type sizes set on each page, in order
+fn page_sizes(pdf: &Document) -> Vec<Vec<f32>> {
+    common::page_operations(pdf)
+        .iter()
+        .map(|operations| {
+            operations
+                .iter()
+                .filter(|op| op.operator == "Tf")
+                .map(|op| op.operands[1].as_float().unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn sections_become_pairs_and_headings() {
+    let chinese = vec!["<h2>序品</h2>".to_string(), "如是我聞".to_string()];
+    let english = vec!["Prologue".to_string(), "Thus have I heard".to_string()];
+    let document = DocumentBuilder::from_sections(&chinese, &english).unwrap();
+    assert_eq!(
+        document.blocks(),
+        [
+            Block::Heading { level: 2, chinese: "序品".into(), english: "Prologue".into() },
+            Block::BilingualPair { chinese: "如是我聞".into(), english: "Thus have I heard".into() },
+        ]
+    );
+    assert_eq!(document::parse_heading("<h7>x</h7>"), None);
+    assert!(DocumentBuilder::from_sections(&chinese, &english[..1]).is_err());
+
+    let verse = Block::Verse { chinese: vec!["如是".into(), "我聞".into()], english: vec!["Thus".into()] };
+    assert_eq!(verse.rows(), [("如是", "Thus"), ("我聞", "")]);
+}
+
+#[test]
+fn blocks_are_set_in_their_styles() {
+    let mut context = test_context();
+    let heading = Block::Heading { level: 1, chinese: TEXT.into(), english: String::new() };
+    let style = heading.style(&context).unwrap();
+    assert_eq!(style.font_size(true), Some(15.0));
+    assert_eq!(style.alignment, Some(Justification::Center));
+    let note = Block::Note { chinese: TEXT.into(), english: String::new() };
+    assert_eq!(note.style(&context).unwrap().font_size(false), Some(8.5));
+    assert_eq!(Block::BilingualPair { chinese: TEXT.into(), english: String::new() }.style(&context), None);
+
+    // A style defined by the document takes the place of the built-in one
+    context.paragraph_styles.set("heading1", ParagraphStyle::parse("chinese-size: 20").unwrap());
+    assert_eq!(heading.style(&context).unwrap().font_size(true), Some(20.0));
+}
+
+#[test]
+fn headings_never_end_a_page() {
+    for pairs in 25..45 {
+        let mut document = DocumentBuilder::new();
+        for _ in 0..pairs {
+            document.pair(TEXT, "");
+        }
+        document.heading(1, TEXT, "").pair(TEXT.repeat(8), "");
+
+        let sizes = page_sizes(&write(&document, LayoutMode::Alternating, "keep"));
+        let heading_page = sizes.iter().find(|page| page.contains(&15.0)).expect("heading is set");
+        assert_eq!(heading_page.last(), Some(&10.0), "{} pairs: {:?}", pairs, sizes);
+    }
+}
+
+#[test]
+fn page_breaks_start_a_new_page_in_every_layout() {
+    let mut document = DocumentBuilder::new();
+    document.pair(TEXT, "").page_break().page_break().pair(TEXT, "");
+    for (layout, name) in [
+        (LayoutMode::Alternating, "break_alternating"),
+        (LayoutMode::SideBySide, "break_side_by_side"),
+        (LayoutMode::Vertical, "break_vertical"),
+    ] {
+        assert_eq!(write(&document, layout, name).get_pages().len(), 2, "{:?}", layout);
+    }
+}
+
+#[test]
+fn metadata_goes_to_the_document_information() {
+    let mut document = DocumentBuilder::new();
+    document.metadata("title", "金剛般若波羅蜜經").metadata("Translator", "Kumārajīva").pair(TEXT, "");
+    let pdf = write(&document, LayoutMode::Alternating, "metadata");
+
+    let info_id = pdf.trailer.get(b"Info").unwrap().as_reference().unwrap();
+    let info = pdf.get_dictionary(info_id).unwrap();
+    let text = |key: &[u8]| {
+        let bytes = info.get(key).unwrap().as_str().unwrap();
+        let units: Vec<u16> = bytes[2..].chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+        String::from_utf16(&units).unwrap()
+    };
+    assert_eq!(text(b"Title"), "金剛般若波羅蜜經");
+    assert_eq!(text(b"Translator"), "Kumārajīva");
+}
+
+#[test]
+fn verse_lines_pair_with_their_translation_side_by_side() {
+    let mut document = DocumentBuilder::new();
+    document.verse(["如是我聞", "一時佛在", "舍衛國"], ["aa aa", "aaa", "a aaa"]);
+    let pdf = write(&document, LayoutMode::SideBySide, "verse");
+
+    let mut baselines: Vec<i32> = page_operands(&pdf, "Tm")
+        .iter()
+        .map(|operands| operands[5].as_float().unwrap().round() as i32)
+        .collect();
+    assert_eq!(baselines.len(), 6);
+    baselines.sort_unstable();
+    baselines.dedup();
+    assert_eq!(baselines.len(), 3, "each Chinese line level with its English");
+    // Lines follow each other at the leading, without paragraph spacing
+    assert_eq!(baselines[1] - baselines[0], 14);
+    assert_eq!(baselines[2] - baselines[1], 14);
+}
@@ -0,0 +1,208 @@
+//! Embedded fonts are subset to the glyphs a document draws, keep their glyph
+//! IDs, and are only referenced when the font's licence forbids embedding. Their
+//! widths and descriptor metrics are read from the font's own tables.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::font_embedding::{self, EmbeddingPermission};
+use cbeta_pdf_creator::fonts::FontContext;
+use lopdf::{Dictionary, Document, Object};
+use ttf_parser::{Face, GlyphId, RawFace};
+
+const CHARS: [char; 6] = ['如', '是', '我', '聞', '一', '時'];
+
+/// Glyph IDs of `font` that have an outline
+fn outlined_glyphs(font: &[u8]) -> Vec<u16> {
+    let face = Face::parse(font, 0).expect("font parses");
+    (0..face.number_of_glyphs()).filter(|&gid| face.glyph_bounding_box(GlyphId(gid)).is_some()).collect()
+}
+
+/// The Chinese CIDFont and its FontDescriptor in a PDF set with `chinese_font`
+fn chinese_font(chinese_font: Vec<u8>, text: &str) -> (Document, Dictionary, Dictionary) {
+    let context = FontContext::from_font_data(
+        "Test CJK",
+        chinese_font,
+        "Test Latin",
+        common::build_test_font(&['a'], 500),
+    )
+    .expect("test fonts load");
+    let document = common::sections(&[text.to_string()], &[String::new()]);
+    let pdf = common::render_pdf(&context, &document, LayoutMode::Alternating, "font_embedding");
+
+    let page_id = pdf.page_iter().next().expect("one page");
+    let type0 = pdf.get_page_fonts(page_id).unwrap()[b"chinese".as_slice()].clone();
+    let descendant = type0.get(b"DescendantFonts").and_then(Object::as_array).unwrap()[0].as_reference().unwrap();
+    let cidfont = pdf.get_dictionary(descendant).unwrap().clone();
+    let descriptor = cidfont.get(b"FontDescriptor").and_then(Object::as_reference).unwrap();
+    let descriptor = pdf.get_dictionary(descriptor).unwrap().clone();
+    (pdf, cidfont, descriptor)
+}
+
+fn base_font(cidfont: &Dictionary) -> String {
+    String::from_utf8(cidfont.get(b"BaseFont").and_then(Object::as_name).unwrap().to_vec()).unwrap()
+}
+
+/// The embedded TrueType program, if any
+fn font_file(pdf: &Document, descriptor: &Dictionary) -> Option<Vec<u8>> {
+    let id = descriptor.get(b"FontFile2").and_then(Object::as_reference).ok()?;
+    let stream = pdf.get_object(id).and_then(Object::as_stream).unwrap();
+    Some(stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()))
+}
+
+/// Width of each CID in a `/W` array, from `c [w1 w2 ...]` and `cfirst clast w` entries
+fn decode_widths(w: &[Object]) -> BTreeMap<u16, i64> {
+    let mut widths = BTreeMap::new();
+    let mut entries = w.iter();
+    while let Some(first) = entries.next() {
+        let first = first.as_i64().unwrap() as u16;
+        match entries.next().expect("entry is complete") {
+            Object::Array(run) => {
+                for (cid, width) in (first..).zip(run) {
+                    widths.insert(cid, width.as_i64().unwrap());
+                }
+            }
+            last => {
+                let width = entries.next().expect("range has a width").as_i64().unwrap();
+                for cid in first..=last.as_i64().unwrap() as u16 {
+                    widths.insert(cid, width);
+                }
+            }
+        }
+    }
+    widths
+}
+
+/// `/DW` and `/W` of a Chinese font whose glyphs 1, 2, ... have `advances`
+fn widths_for(advances: &[u16]) -> (i64, Vec<Object>) {
+    let chars = ['如', '是', '我', '聞', '一', '時', '佛', '在', '舍'];
+    let mut font = common::build_test_font(&chars[..advances.len()], 1000);
+    for (gid, &advance) in (1..).zip(advances) {
+        common::set_advance(&mut font, gid, advance);
+    }
+    let text: String = chars[..advances.len()].iter().collect();
+    let (_, cidfont, _) = chinese_font(font, &text);
+    let default_width = cidfont.get(b"DW").and_then(Object::as_i64).unwrap();
+    let w = cidfont.get(b"W").and_then(Object::as_array).unwrap().clone();
+
+    let widths = decode_widths(&w);
+    for (gid, &advance) in (1..).zip(advances) {
+        assert_eq!(widths.get(&gid).copied().unwrap_or(default_width), advance as i64, "glyph {}", gid);
+    }
+    (default_width, w)
+}
+
+#[test]
+fn subsets_keep_only_the_glyphs_used_at_their_ids() {
+    let font = common::build_test_font(&CHARS, 1000);
+    let subset = font_embedding::subset_font_program(&font, 0, &[2, 5]).unwrap();
+    assert_eq!(Face::parse(&subset, 0).unwrap().number_of_glyphs(), 7);
+    assert_eq!(outlined_glyphs(&subset), [0, 2, 5]);
+
+    // 是 and 聞 are glyphs 2 and 4; the tag names the subset
+    let (pdf, cidfont, descriptor) = chinese_font(font, "是聞是");
+    let program = font_file(&pdf, &descriptor).expect("font is embedded");
+    assert_eq!(outlined_glyphs(&program), [0, 2, 4]);
+
+    let name = base_font(&cidfont);
+    let (tag, rest) = name.split_once('+').expect("subset tag");
+    assert!(tag.len() == 6 && tag.chars().all(|ch| ch.is_ascii_uppercase()), "{}", name);
+    assert!(!rest.is_empty());
+    assert_eq!(tag, font_embedding::subset_tag(&[0, 2, 4]));
+}
+
+#[test]
+fn restricted_fonts_are_referenced_not_embedded() {
+    let mut font = common::build_test_font(&CHARS, 1000);
+    common::set_fs_type(&mut font, 0x0002);
+    assert_eq!(font_embedding::embedding_permission(&font, 0).unwrap(), EmbeddingPermission::Forbidden);
+
+    let (pdf, cidfont, descriptor) = chinese_font(font, "是聞");
+    assert!(font_file(&pdf, &descriptor).is_none());
+    assert!(!descriptor.has(b"FontFile3"));
+    assert!(!base_font(&cidfont).contains('+'));
+}
+
+#[test]
+fn collection_faces_are_extracted_with_their_tables() {
+    let first = common::build_test_font(&CHARS[..2], 1000);
+    let second = common::build_test_font(&CHARS[2..], 800);
+    let collection = common::build_test_collection(&[first, second.clone()]);
+
+    let extracted = font_embedding::extract_collection_face(&collection, 1).unwrap();
+    let face = Face::parse(&extracted, 0).expect("extracted face parses");
+    assert_eq!(face.glyph_index('聞'), Some(GlyphId(2)));
+    assert_eq!(face.glyph_index('如'), None);
+    assert_eq!(face.glyph_hor_advance(GlyphId(2)), Some(800));
+
+    let (extracted, original) = (RawFace::parse(&extracted, 0).unwrap(), RawFace::parse(&second, 0).unwrap());
+    assert_eq!(extracted.table_records.len(), original.table_records.len());
+    for record in original.table_records {
+        let (mut table, source) = (extracted.table(record.tag).unwrap().to_vec(), original.table(record.tag).unwrap());
+        if &record.tag.to_bytes() == b"head" {
+            // checkSumAdjustment is recomputed for the standalone font
+            table[8..12].copy_from_slice(&source[8..12]);
+        }
+        assert_eq!(table, source, "{}", record.tag);
+    }
+    assert!(font_embedding::extract_collection_face(&collection, 2).is_err());
+}
+
+#[test]
+fn malformed_collections_are_rejected() {
+    let collection = common::build_test_collection(&[common::build_test_font(&CHARS, 1000)]);
+    let face = 16; // offset table of the only face
+    let with = |offset: usize, bytes: &[u8]| {
+        let mut broken = collection.clone();
+        broken[offset..offset + bytes.len()].copy_from_slice(bytes);
+        font_embedding::extract_collection_face(&broken, 0)
+    };
+
+    assert!(with(face + 4, &0u16.to_be_bytes()).is_err());
+    assert!(with(face + 4, &4096u16.to_be_bytes()).is_err());
+    assert!(with(face + 4, &u16::MAX.to_be_bytes()).is_err());
+    // First table record: an offset and length that wrap around, then a face past the end
+    assert!(with(face + 12 + 8, &[0xFF; 8]).is_err());
+    assert!(with(12, &u32::MAX.to_be_bytes()).is_err());
+    assert!(with(8, &0u32.to_be_bytes()).is_err());
+}
+
+#[test]
+fn widths_are_the_font_advances() {
+    // The width most glyphs share is /DW; three or more alike are a range, others a list
+    let (default_width, w) = widths_for(&[1000, 1000, 1000, 1000, 600, 600, 600, 700, 800]);
+    assert_eq!(default_width, 1000);
+    let list = Object::Array(vec![Object::Integer(700), Object::Integer(800)]);
+    assert_eq!(w, [Object::Integer(5), Object::Integer(7), Object::Integer(600), Object::Integer(8), list]);
+
+    // Equally common widths: the narrower is /DW; two alike stay a list
+    let (default_width, w) = widths_for(&[700, 700, 900, 900]);
+    assert_eq!(default_width, 700);
+    assert_eq!(w, [Object::Integer(3), Object::Array(vec![Object::Integer(900), Object::Integer(900)])]);
+
+    let (default_width, w) = widths_for(&[500, 500, 500]);
+    assert_eq!((default_width, w), (500, Vec::new()));
+}
+
+#[test]
+fn descriptor_metrics_come_from_the_font_tables() {
+    let (_, _, descriptor) = chinese_font(common::build_test_font(&CHARS, 1000), "如是");
+    let integer = |key: &[u8]| descriptor.get(key).and_then(Object::as_i64).unwrap();
+    let bbox = descriptor.get(b"FontBBox").and_then(Object::as_array).unwrap();
+    let bbox: Vec<i64> = bbox.iter().map(|v| v.as_i64().unwrap()).collect();
+    assert_eq!(bbox, [0, -120, 1000, 880]);
+    assert_eq!((integer(b"Ascent"), integer(b"Descent")), (880, -120));
+    // A version 1 OS/2 table has no cap height: the ascender stands in without an 'H'
+    assert_eq!(integer(b"CapHeight"), 880);
+    assert_eq!(integer(b"Flags"), 4);
+
+    // With an 'H' its outline gives the cap height, and values scale to 1000 units per em
+    let mut font = common::build_test_font(&['H'], 500);
+    let head = common::table_offset(&font, b"head").unwrap();
+    font[head + 18..head + 20].copy_from_slice(&2000u16.to_be_bytes());
+    let metrics = font_embedding::font_metrics(&font, 0).unwrap();
+    assert_eq!(metrics.bbox, [0, -60, 250, 440]);
+    assert_eq!((metrics.ascent, metrics.descent, metrics.cap_height), (440, -60, 350));
+}
@@ -0,0 +1,52 @@
+//! Characters missing from the primary font are drawn with the first fallback
+//! font that covers them, each fallback as its own PDF font resource.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::FontContext;
+
+#[test]
+fn missing_glyphs_use_the_first_covering_fallback() {
+    let chinese = "如是𠀀我聞𪛖";
+    let english = "Thus";
+
+    let mut font_context = FontContext::from_font_data(
+        "Primary CJK",
+        common::build_test_font(&['如', '是', '我', '聞'], 1000),
+        "Test Latin",
+        common::build_test_font(&['T', 'h', 'u', 's', ' '], 500),
+    )
+    .expect("test fonts load");
+    // Only the second fallback covers 𪛖; 𠀀 must come from the first.
+    font_context
+        .add_fallback_font_data(true, "Ext B Part 1", common::build_test_font(&['𠀀'], 1000))
+        .unwrap();
+    font_context
+        .add_fallback_font_data(true, "Ext B Part 2", common::build_test_font(&['𠀀', '𪛖'], 1000))
+        .unwrap();
+
+    assert_eq!(
+        font_context.split_font_runs(chinese, true),
+        vec![
+            (0, "如是".to_string()),
+            (1, "𠀀".to_string()),
+            (0, "我聞".to_string()),
+            (2, "𪛖".to_string()),
+        ]
+    );
+
+    let sections = common::sections(&[chinese.to_string()], &[english.to_string()]);
+    let document = common::render_pdf(&font_context, &sections, LayoutMode::Alternating, "font_fallback");
+
+    let page_id = document.page_iter().next().expect("one page");
+    let fonts = document.get_page_fonts(page_id).expect("page fonts");
+    let mut resources: Vec<String> = fonts.keys().map(|name| String::from_utf8_lossy(name).into_owned()).collect();
+    resources.sort();
+    assert_eq!(resources, vec!["chinese", "chinese_fb1", "chinese_fb2", "english"]);
+
+    // lopdf separates TJ operators with spaces; viewers join the runs by position.
+    let text = document.extract_text(&[1]).expect("text extraction succeeds");
+    let joined: String = text.chars().filter(|ch| !ch.is_whitespace()).collect();
+    assert!(joined.contains(chinese), "extracted text was {:?}", text);
+}
@@ -0,0 +1,121 @@
+//! The font registry finds faces by family and closest style, by the scripts
+//! they cover, and in the caller's order of preference, from memory, files and
+//! directory trees.
+
+mod common;
+
+use cbeta_pdf_creator::font_registry::{FontRegistry, FontStyle, Script};
+
+const HAN: [char; 6] = ['一', '如', '是', '我', '聞', '經'];
+const LATIN: [char; 5] = ['A', 'Z', 'a', 'z', 'e'];
+
+/// Font of `family` mapping `chars`, with OS/2 weight class `weight`
+fn font(family: &str, chars: &[char], weight: u16, italic: bool) -> Vec<u8> {
+    let cmap: Vec<(char, u16)> = (1..).zip(chars).map(|(gid, &ch)| (ch, gid)).collect();
+    let mut font = common::build_test_font_with_tables(&cmap, 1000, vec![(b"name", common::name_table(family))]);
+    let os2 = common::table_offset(&font, b"OS/2").unwrap();
+    font[os2 + 4..os2 + 6].copy_from_slice(&weight.to_be_bytes());
+    font[os2 + 62..os2 + 64].copy_from_slice(&u16::from(italic).to_be_bytes()); // fsSelection
+    font
+}
+
+#[test]
+fn families_match_by_name_and_closest_style() {
+    let mut registry = FontRegistry::new();
+    registry.register_bytes("regular", font("Test Serif", &LATIN, 400, false)).unwrap();
+    registry.register_bytes("bold", font("Test Serif", &LATIN, 700, false)).unwrap();
+    registry.register_bytes("italic", font("Test Serif", &LATIN, 400, true)).unwrap();
+    assert_eq!(registry.families(), ["Test Serif"]);
+
+    let found = |family: &str, style: FontStyle| registry.find_family(family, style).map(|face| face.source_name());
+    assert_eq!(found("Test Serif", FontStyle::REGULAR).as_deref(), Some("regular"));
+    // Case, spaces, hyphens and underscores are ignored
+    assert_eq!(found("test-serif", FontStyle::BOLD).as_deref(), Some("bold"));
+    assert_eq!(found("TEST_SERIF", FontStyle::ITALIC).as_deref(), Some("italic"));
+    // Slant outweighs weight
+    let bold_italic = FontStyle { weight: 700, italic: true, ..FontStyle::REGULAR };
+    assert_eq!(found("TestSerif", bold_italic).as_deref(), Some("italic"));
+    assert_eq!(found("Test Sans", FontStyle::REGULAR), None);
+}
+
+#[test]
+fn faces_cover_scripts_they_map_every_sample_of() {
+    let mut registry = FontRegistry::new();
+    registry.register_bytes("han", font("Test Han", &HAN, 400, false)).unwrap();
+    registry.register_bytes("partial", font("Test Partial", &HAN[..5], 400, false)).unwrap();
+    registry.register_bytes("latin", font("Test Latin", &LATIN, 400, false)).unwrap();
+    let scripts: Vec<(&str, &[Script])> =
+        registry.faces().iter().map(|face| (face.family.as_str(), face.scripts.as_slice())).collect();
+    assert_eq!(
+        scripts,
+        [("Test Han", [Script::Han].as_slice()), ("Test Partial", &[]), ("Test Latin", &[Script::Latin])]
+    );
+
+    let covering = |script: Script| registry.find_for_script(script, FontStyle::REGULAR).map(|face| face.source_name());
+    assert_eq!(covering(Script::Han).as_deref(), Some("han"));
+    assert_eq!(covering(Script::Latin).as_deref(), Some("latin"));
+    assert_eq!(covering(Script::Devanagari), None);
+}
+
+#[test]
+fn preferences_come_first_and_later_registrations_win_ties() {
+    let mut registry = FontRegistry::new();
+    registry.register_bytes("ming", font("Test Ming", &HAN, 400, false)).unwrap();
+    registry.register_bytes("kai", font("Test Kai", &HAN, 400, false)).unwrap();
+    registry.register_bytes("latin", font("Test Latin", &LATIN, 400, false)).unwrap();
+    let resolved = |registry: &FontRegistry, defaults: &[&str]| {
+        registry.resolve(Script::Han, defaults, FontStyle::REGULAR).map(|face| face.source_name())
+    };
+
+    // Defaults in order, skipping families that are missing or lack the script
+    assert_eq!(resolved(&registry, &["Missing", "Test Latin", "Test Ming", "Test Kai"]).as_deref(), Some("ming"));
+    // Without a matching family, the covering face registered last
+    assert_eq!(resolved(&registry, &[]).as_deref(), Some("kai"));
+
+    registry.set_preferred_family(Script::Han, "Test Kai");
+    assert_eq!(resolved(&registry, &["Test Ming"]).as_deref(), Some("kai"));
+    registry.set_preferred_family(Script::Han, "test ming");
+    assert_eq!(registry.preferred_families(Script::Han), ["test ming", "Test Kai"]);
+    assert_eq!(resolved(&registry, &[]).as_deref(), Some("ming"));
+
+    // A font of the same family and style registered later overrides the earlier one
+    registry.register_bytes("caller ming", font("Test Ming", &HAN, 400, false)).unwrap();
+    assert_eq!(resolved(&registry, &[]).as_deref(), Some("caller ming"));
+
+    // Fallbacks keep the chain's order and leave out the primary face
+    registry.set_fallback_families(Script::Han, &["Test Ming", "Missing", "Test Kai"]);
+    let primary = registry.find_family("Test Ming", FontStyle::REGULAR).unwrap();
+    let chain: Vec<String> = registry.chinese_fallbacks(primary).iter().map(|face| face.source_name()).collect();
+    assert_eq!(chain, ["kai"]);
+}
+
+#[test]
+fn directories_are_indexed_once_and_faces_keep_their_index() {
+    let dir = std::env::temp_dir().join(format!("cbeta_font_registry_{}", std::process::id()));
+    let nested = dir.join("nested");
+    std::fs::create_dir_all(&nested).unwrap();
+    let collection =
+        common::build_test_collection(&[font("Test Ming", &HAN, 400, false), font("Test Kai", &HAN, 400, false)]);
+    std::fs::write(dir.join("serif.ttf"), font("Test Serif", &LATIN, 400, false)).unwrap();
+    std::fs::write(nested.join("han.TTC"), &collection).unwrap();
+    std::fs::write(nested.join("broken.otf"), b"not a font").unwrap();
+    std::fs::write(nested.join("notes.txt"), b"").unwrap();
+    // A link back up the tree is not followed round again
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&dir, nested.join("loop")).unwrap();
+
+    let mut registry = FontRegistry::new();
+    let added = registry.add_directory(&dir).unwrap();
+    let again = registry.add_directory(&nested).unwrap();
+    let broken = registry.register_file(&nested.join("broken.otf"));
+    let kai = registry.find_family("Test Kai", FontStyle::REGULAR).unwrap();
+    let kai_data = kai.load_data().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert_eq!((added, again), (3, 0));
+    assert!(broken.is_err());
+    let faces: Vec<(&str, u32)> = registry.faces().iter().map(|face| (face.family.as_str(), face.face_index)).collect();
+    assert_eq!(faces, [("Test Serif", 0), ("Test Ming", 0), ("Test Kai", 1)]);
+    assert!(kai.covers(Script::Han));
+    assert_eq!(kai_data, collection);
+}
@@ -0,0 +1,98 @@
+//! Layout works on grapheme clusters: a letter and its combining marks are
+//! measured, tracked and drawn together, and normalisation keeps CJK
+//! compatibility ideographs.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::{FontContext, TextNormalization};
+use cbeta_pdf_creator::typography::TextLayoutEngine;
+use lopdf::Object;
+
+fn test_context() -> FontContext {
+    let english: Vec<char> = "abnirvāṇ \u{304}\u{323}".chars().collect();
+    let mut context = common::test_context(&['如', '是', '\u{F900}', '\u{8C48}'], &english);
+    context.set_options(595.0, 842.0, 72.0, 10.0, 10.0, 1.4, 0.0, 100.0, 0.6);
+    context.normalization = TextNormalization::None;
+    context
+}
+
+fn layout_text(context: &FontContext, text: &str, is_chinese: bool) -> String {
+    let mut engine = TextLayoutEngine::new(context.clone());
+    let paragraph = engine.layout_paragraph(text, 0.0, 0.0, 400.0, is_chinese).expect("layout succeeds");
+    paragraph.lines.iter().map(|line| line.text.as_str()).collect()
+}
+
+/// TJ items of an English line, in drawing order across runs
+fn line_tj(context: &FontContext, text: &str) -> Vec<Object> {
+    let document = common::sections(&[String::new()], &[text.to_string()]);
+    common::render_pages(context, &document, LayoutMode::Alternating, "graphemes")[0]
+        .iter()
+        .filter(|op| op.operator == "TJ")
+        .flat_map(|op| op.operands[0].as_array().unwrap().clone())
+        .collect()
+}
+
+#[test]
+fn tracking_goes_between_clusters_not_before_marks() {
+    let mut context = test_context();
+    let mut untracked = context.clone();
+    untracked.tracking_english = 0.0;
+
+    // Two clusters (no precomposed b with macron): one step of tracking (0.1 em)
+    let width = untracked.calculate_text_width("b\u{304}a", false);
+    assert_eq!(context.calculate_text_width("b\u{304}a", false), width + 1.0);
+
+    // b, the macron placed over it, then a: only the step to the a is tracked.
+    let tracked = line_tj(&context, "b\u{304}a");
+    let plain = line_tj(&untracked, "b\u{304}a");
+    assert_eq!(tracked.len(), 5, "{:?}", tracked);
+    assert_eq!(tracked[1], plain[1]);
+    assert_eq!(tracked[3].as_float().unwrap(), plain[3].as_float().unwrap_or(0.0) - 100.0);
+}
+
+#[test]
+fn marks_stay_in_the_font_of_their_letter() {
+    // The fallback font draws "a" with its macron; the primary has no macron.
+    let mut context = FontContext::from_font_data(
+        "Test CJK",
+        common::build_test_font(&['如'], 1000),
+        "Test Latin",
+        common::build_test_font(&['a', 'b'], 500),
+    )
+    .unwrap();
+    context
+        .add_fallback_font_data(false, "Test Marks", common::build_test_font(&['a', '\u{304}'], 500))
+        .unwrap();
+
+    assert_eq!(
+        context.split_font_runs("ba\u{304}b", false),
+        [(0, "b".to_string()), (1, "a\u{304}".to_string()), (0, "b".to_string())]
+    );
+}
+
+#[test]
+fn normalization_is_configurable_and_keeps_compatibility_ideographs() {
+    let mut context = test_context();
+    let decomposed = "nirva\u{304}n\u{323}a";
+
+    assert_eq!(layout_text(&context, decomposed, false), decomposed);
+
+    context.normalization = TextNormalization::Nfc;
+    assert_eq!(layout_text(&context, decomposed, false), "nirvāṇa");
+    assert_eq!(layout_text(&context, "\u{F900}如是", true), "\u{F900}如是");
+
+    context.normalization = TextNormalization::Nfd;
+    assert_eq!(layout_text(&context, "nirvāṇa", false), decomposed);
+    assert_eq!(layout_text(&context, "\u{F900}如是", true), "\u{F900}如是");
+}
+
+#[test]
+fn chinese_lines_never_break_inside_a_cluster() {
+    let context = test_context();
+    let mut engine = TextLayoutEngine::new(context);
+    // Two characters to the line; the variation selector stays with its ideograph.
+    let paragraph = engine.layout_paragraph("如是\u{E0100}如是", 0.0, 0.0, 20.0, true).unwrap();
+    let lines: Vec<&str> = paragraph.lines.iter().map(|line| line.text.as_str()).collect();
+    assert_eq!(lines, ["如是\u{E0100}", "如是"]);
+}
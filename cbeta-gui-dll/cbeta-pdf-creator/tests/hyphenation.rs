@@ -0,0 +1,95 @@
+//! English words hyphenate with TeX's Liang patterns and exceptions; IAST terms
+//! use the Sanskrit patterns, split the way romanised text is.
+
+mod common;
+
+use cbeta_pdf_creator::fonts::Justification;
+use cbeta_pdf_creator::hyphenation::{Hyphenator, HyphenationSettings};
+use cbeta_pdf_creator::typography::TextLayoutEngine;
+
+fn hyphenated(settings: &HyphenationSettings, word: &str) -> String {
+    let points = settings.hyphenate(word);
+    let mut result = String::new();
+    for (i, ch) in word.chars().enumerate() {
+        if points.contains(&i) {
+            result.push('-');
+        }
+        result.push(ch);
+    }
+    result
+}
+
+#[test]
+fn english_patterns_and_exceptions() {
+    let mut settings = HyphenationSettings::default();
+    assert_eq!(hyphenated(&settings, "enlightenment"), "enlight-en-ment");
+    assert_eq!(hyphenated(&settings, "meditation"), "med-i-ta-tion");
+
+    settings.left_min = 2;
+    settings.right_min = 2;
+    assert_eq!(hyphenated(&settings, "enlightenment"), "en-light-en-ment");
+    assert_eq!(hyphenated(&settings, "compassion"), "com-pas-sion");
+    // From TeX's exception list
+    assert_eq!(hyphenated(&settings, "associates"), "as-so-ciates");
+    assert_eq!(hyphenated(&settings, "reciprocity"), "reci-procity");
+    assert_eq!(hyphenated(&settings, "project"), "project");
+}
+
+#[test]
+fn iast_terms_use_sanskrit_patterns() {
+    let mut settings = HyphenationSettings::default();
+    settings.left_min = 2;
+    settings.right_min = 2;
+
+    assert_eq!(hyphenated(&settings, "nirvāṇa"), "nir-vā-ṇa");
+    // The same word typed with combining marks
+    assert_eq!(hyphenated(&settings, "nirva\u{304}n\u{323}a"), "nir-va\u{304}-n\u{323}a");
+    assert_eq!(hyphenated(&settings, "dharmakāya"), "dhar-ma-kā-ya");
+    assert_eq!(hyphenated(&settings, "tathāgatagarbha"), "ta-thā-ga-ta-gar-bha");
+    assert_eq!(hyphenated(&settings, "saṃsāra"), "saṃ-sā-ra");
+    assert_eq!(hyphenated(&settings, "kr\u{325}ṣṇa"), "kr\u{325}ṣ-ṇa");
+}
+
+#[test]
+fn languages_load_from_pattern_files() {
+    let dir = std::env::temp_dir();
+    let patterns = dir.join("cbeta_hyph-xx.pat.txt");
+    let exceptions = dir.join("cbeta_hyph-xx.hyp.txt");
+    std::fs::write(&patterns, "% test patterns\n1ba\n1ca\n").unwrap();
+    std::fs::write(&exceptions, "ab-cab-cab\n").unwrap();
+
+    let mut settings = HyphenationSettings::default();
+    assert!(!settings.set_language("xx"));
+    settings.load_language("xx", &patterns, Some(&exceptions)).expect("patterns load");
+    let _ = std::fs::remove_file(&patterns);
+    let _ = std::fs::remove_file(&exceptions);
+
+    assert!(settings.set_language("XX"));
+    settings.left_min = 2;
+    settings.right_min = 2;
+    assert_eq!(hyphenated(&settings, "abacabaca"), "aba-ca-ba-ca");
+    assert_eq!(hyphenated(&settings, "abcabcab"), "ab-cab-cab");
+
+    let hyphenator = Hyphenator::from_patterns("1ba", "");
+    assert_eq!(hyphenator.hyphenate("ababab", 1, 1), vec![1, 3]);
+    assert!(Hyphenator::load(&dir.join("cbeta_missing.pat.txt"), None).is_err());
+}
+
+#[test]
+fn lines_break_at_hyphenation_points() {
+    let letters: Vec<char> = ('a'..='z').chain([' ', '-']).collect();
+    let mut context = common::test_context(&['如'], &letters);
+    context.justification = Justification::Justify;
+    let mut engine = TextLayoutEngine::new(context);
+
+    let text = "the long path of enlightenment leads through meditation";
+    let paragraph = engine.layout_paragraph(text, 0.0, 0.0, 60.0, false).expect("layout succeeds");
+
+    let texts: Vec<&str> = paragraph.lines.iter().map(|line| line.text.as_str()).collect();
+    assert!(texts.iter().any(|line| line.ends_with('-')), "lines were {:?}", texts);
+    let rejoined: String = texts
+        .iter()
+        .map(|line| line.strip_suffix('-').map(str::to_string).unwrap_or(format!("{} ", line)))
+        .collect();
+    assert_eq!(rejoined.trim_end(), text);
+}
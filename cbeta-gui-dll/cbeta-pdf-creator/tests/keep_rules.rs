@@ -0,0 +1,101 @@
+//! Alternating layout keeps a Chinese paragraph with the first lines of its
+//! English and moves short pairs to a new page whole, unless told not to.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::FontContext;
+
+const TEXT: &str = "如是我聞一時佛在舍衛國";
+
+fn test_context() -> FontContext {
+    let chars: Vec<char> = TEXT.chars().collect();
+    let mut context = common::test_context(&chars, &['a', ' ']);
+    // English at 9pt tells the two languages apart in the content streams
+    context.set_options(595.0, 842.0, 72.0, 10.0, 9.0, 1.4, 0.0, 0.0, 0.6);
+    context
+}
+
+/// Type size of each line, page by page, after `filler` one-line Chinese
+/// sections and a last section of `chinese` and `english`
+fn page_lines(context: &FontContext, filler: usize, chinese: &str, english: &str) -> Vec<Vec<f32>> {
+    let mut chinese_sections = vec![TEXT.to_string(); filler];
+    let mut english_sections = vec![String::new(); filler];
+    chinese_sections.push(chinese.to_string());
+    english_sections.push(english.to_string());
+
+    let document = common::sections(&chinese_sections, &english_sections);
+    common::render_pages(context, &document, LayoutMode::Alternating, "keep_rules")
+        .iter()
+        .map(|operations| {
+            let mut sizes = Vec::new();
+            let mut new_line = false;
+            for op in operations {
+                match op.operator.as_str() {
+                    "Tm" => new_line = true,
+                    "Tf" if new_line => {
+                        sizes.push(op.operands[1].as_float().unwrap());
+                        new_line = false;
+                    }
+                    _ => {}
+                }
+            }
+            sizes
+        })
+        .collect()
+}
+
+/// Page of the first line of `size` at or after line `skip` of that size
+fn page_of(pages: &[Vec<f32>], size: f32, skip: usize) -> usize {
+    pages
+        .iter()
+        .enumerate()
+        .flat_map(|(page, lines)| lines.iter().filter(|&&line| line == size).map(move |_| page))
+        .nth(skip)
+        .expect("line is set")
+}
+
+/// Whether the Chinese of the last section, after `filler` one-line sections,
+/// ends a page without its English
+fn chinese_parted(context: &FontContext, filler: usize, chinese: &str, english: &str) -> bool {
+    let pages = page_lines(context, filler, chinese, english);
+    let chinese_lines = pages.iter().flatten().filter(|&&size| size == 10.0).count();
+    page_of(&pages, 10.0, chinese_lines - 1) != page_of(&pages, 9.0, 0)
+}
+
+#[test]
+fn chinese_ends_no_page_without_its_english() {
+    let english = "aaaa ".repeat(100);
+    for filler in 15..22 {
+        for lines in 1..=3 {
+            let chinese = TEXT.repeat(4 * lines - 1);
+            assert!(!chinese_parted(&test_context(), filler, &chinese, &english), "{} sections, {} lines", filler, lines);
+        }
+    }
+}
+
+#[test]
+fn short_pairs_move_to_a_new_page_whole() {
+    let chinese = TEXT.repeat(5);
+    let english = "aaaa ".repeat(40);
+    for filler in 15..22 {
+        let pages = page_lines(&test_context(), filler, &chinese, &english);
+        let english_lines = pages.iter().flatten().filter(|&&size| size == 9.0).count();
+        assert!((2..=4).contains(&english_lines));
+        // First line of the pair's Chinese and last of its English
+        let first = page_of(&pages, 10.0, filler);
+        assert_eq!(page_of(&pages, 9.0, english_lines - 1), first, "{} sections: {:?}", filler, pages);
+    }
+}
+
+#[test]
+fn rules_can_be_turned_off() {
+    let mut context = test_context();
+    context.keep_pairs_together = false;
+    context.short_pair_lines = 0;
+    let english = "aaaa ".repeat(100);
+    let parted = (15..22).any(|filler| {
+        (1..=3).any(|lines| chinese_parted(&context, filler, &TEXT.repeat(4 * lines - 1), &english))
+    });
+    assert!(parted, "some page ends with the Chinese alone");
+}
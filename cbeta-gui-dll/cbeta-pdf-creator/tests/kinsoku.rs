@@ -0,0 +1,57 @@
+//! Chinese lines follow JIS X 4051 / CLREQ kinsoku: no closing punctuation at a
+//! line start, no opening bracket at a line end, no split "……" or numbers.
+
+mod common;
+
+use cbeta_pdf_creator::typography::TextLayoutEngine;
+
+/// Lines of a Chinese paragraph set ten characters to the line
+fn chinese_lines(text: &str) -> Vec<String> {
+    let paragraph = engine().layout_paragraph(text, 0.0, 0.0, 100.0, true).expect("layout succeeds");
+    paragraph.lines.into_iter().map(|line| line.text).collect()
+}
+
+fn engine() -> TextLayoutEngine {
+    let chars: Vec<char> = "如是我聞一時佛在舍衛國祇樹給孤獨園，。「」…12345".chars().collect();
+    TextLayoutEngine::new(common::test_context(&chars, &['a']))
+}
+
+#[test]
+fn closing_punctuation_is_pushed_out_or_in() {
+    // A comma that cannot fit is carried over with the character before it.
+    assert_eq!(chinese_lines("如是我聞一時佛在舍衛，國祇"), ["如是我聞一時佛在舍", "衛，國祇"]);
+
+    // With enough punctuation to squeeze, the full stop is pushed in instead.
+    let mut engine = engine();
+    let paragraph = engine.layout_paragraph("如是，我聞，一時佛在。國祇", 0.0, 0.0, 100.0, true).unwrap();
+    assert_eq!(paragraph.lines[0].text, "如是，我聞，一時佛在。");
+    // The full stop hangs into its blank half; the second comma gives up the rest.
+    let squeezed: Vec<(usize, f32)> = paragraph.lines[0]
+        .space_adjustments
+        .iter()
+        .map(|adjustment| (adjustment.position, adjustment.adjusted_width - adjustment.base_width))
+        .collect();
+    assert_eq!(squeezed, [(5, -5.0)]);
+}
+
+#[test]
+fn opening_brackets_ellipses_and_numbers_stay_together() {
+    assert_eq!(chinese_lines("如是我聞一時佛在舍「衛國」"), ["如是我聞一時佛在舍", "「衛國」"]);
+    assert_eq!(chinese_lines("如是我聞一時佛在舍……衛"), ["如是我聞一時佛在舍", "……衛"]);
+    assert_eq!(chinese_lines("如是我聞一時佛在12345"), ["如是我聞一時佛在", "12345"]);
+}
+
+#[test]
+fn no_line_starts_with_closing_punctuation() {
+    let text = "如是我聞，一時佛在舍衛國祇樹給孤獨園。「如是」，我聞。".repeat(8);
+    let lines = chinese_lines(&text);
+    assert!(lines.len() > 5);
+    for line in &lines[1..] {
+        let first = line.chars().next().unwrap();
+        assert!(!"，。」".contains(first), "line starts with {:?}: {:?}", first, lines);
+    }
+    for line in &lines {
+        assert!(!line.ends_with('「'), "line ends with an opening bracket: {:?}", lines);
+    }
+    assert_eq!(lines.concat(), text);
+}
@@ -0,0 +1,52 @@
+//! Justified English is broken with the Knuth–Plass total-fit composer: lines are
+//! chosen together, so a tight line early on can save a widow at the end.
+
+mod common;
+
+use cbeta_pdf_creator::fonts::Justification;
+use cbeta_pdf_creator::typography::TextLayoutEngine;
+
+fn justified_engine() -> TextLayoutEngine {
+    // Monospaced: every letter, the hyphen and the space are 5 pt wide at 10 pt.
+    let letters: Vec<char> = ('a'..='z').chain([' ', '-']).collect();
+    let mut context = common::test_context(&['如'], &letters);
+    context.justification = Justification::Justify;
+    TextLayoutEngine::new(context)
+}
+
+#[test]
+fn tight_line_avoids_widow() {
+    let mut engine = justified_engine();
+    let paragraph = engine
+        .layout_paragraph("iahc aeg ajib eedhg efaagac dcgifi eac ah ahhijig icec ci gf", 0.0, 0.0, 100.0, false)
+        .expect("layout succeeds");
+
+    // Filling each line greedily would leave "gf" alone on a fourth line.
+    let texts: Vec<&str> = paragraph.lines.iter().map(|line| line.text.as_str()).collect();
+    assert_eq!(texts, ["iahc aeg ajib eedhg", "efaagac dcgifi eac ah", "ahhijig icec ci gf"]);
+
+    // The second line is 105 pt naturally; its spaces shrink to fit the measure.
+    let tight = &paragraph.lines[1];
+    assert!(tight.is_justified);
+    let set_width = tight.width
+        + tight
+            .space_adjustments
+            .iter()
+            .map(|space| space.adjusted_width - space.base_width)
+            .sum::<f32>();
+    assert!((set_width - 100.0).abs() < 1e-3, "set width {}", set_width);
+    assert!(!paragraph.lines[2].is_justified, "last line is set ragged");
+}
+
+#[test]
+fn soft_hyphens_show_only_at_line_ends() {
+    let mut engine = justified_engine();
+    let paragraph = engine
+        .layout_paragraph("aaaa bbbb cccccccc\u{AD}dddddddd ee\u{AD}ee ffff", 0.0, 0.0, 100.0, false)
+        .expect("layout succeeds");
+
+    let texts: Vec<&str> = paragraph.lines.iter().map(|line| line.text.as_str()).collect();
+    assert_eq!(texts, ["aaaa bbbb cccccccc-", "dddddddd eeee ffff"]);
+    assert!(paragraph.lines[0].hyphenated);
+    assert!(!paragraph.lines[1].hyphenated);
+}
@@ -0,0 +1,99 @@
+//! Text widths come from a glyph cache keyed by font and glyph, so they stay
+//! consistent when font sizes change between layout runs.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::FontContext;
+use cbeta_pdf_creator::metrics_cache::GlyphMetricsCache;
+
+fn test_context() -> FontContext {
+    // The space exists in both fonts with different advances.
+    let mut context = common::test_context(&['如', '是', ' '], &['T', 'h', 'u', 's', ' ']);
+    context.set_metrics_cache(GlyphMetricsCache::new());
+    context
+}
+
+fn set_sizes(context: &mut FontContext, chinese: f32, english: f32) {
+    context.set_options(595.0, 842.0, 72.0, chinese, english, 1.4, 0.0, 0.0, 0.6);
+}
+
+#[test]
+fn widths_stay_consistent_across_size_changes() {
+    let mut context = test_context();
+
+    set_sizes(&mut context, 12.0, 12.0);
+    let zh_12 = context.calculate_text_width("如是 如是", true);
+    let en_12 = context.calculate_text_width("Thus thus", false);
+    assert_eq!(zh_12, 5.0 * 12.0);
+
+    // Every auto-sizing step changes the sizes; nothing measured earlier may leak through.
+    for step in 0..=30 {
+        let size = 7.0 + step as f32 * 0.5;
+        set_sizes(&mut context, size, size - 1.0);
+        let zh = context.calculate_text_width("如是 如是", true);
+        let en = context.calculate_text_width("Thus thus", false);
+        assert!((zh - zh_12 * size / 12.0).abs() < 1e-3, "size {}: {} vs {}", size, zh, zh_12);
+        assert!((en - en_12 * (size - 1.0) / 12.0).abs() < 1e-3, "size {}: {} vs {}", size, en, en_12);
+    }
+
+    set_sizes(&mut context, 12.0, 12.0);
+    assert_eq!(context.calculate_text_width("如是 如是", true), zh_12);
+    assert_eq!(context.calculate_text_width("Thus thus", false), en_12);
+}
+
+#[test]
+fn chinese_and_english_metrics_do_not_overwrite_each_other() {
+    let mut context = test_context();
+    set_sizes(&mut context, 13.0, 12.0);
+
+    assert_eq!(context.calculate_text_width(" ", true), 13.0);
+    assert_eq!(context.calculate_text_width(" ", false), 6.0);
+    assert_eq!(context.calculate_text_width(" ", true), 13.0);
+}
+
+#[test]
+fn cache_is_shared_between_threads() {
+    let cache = GlyphMetricsCache::new();
+    let mut context = test_context();
+    context.set_metrics_cache(cache.clone());
+    set_sizes(&mut context, 12.0, 12.0);
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let mut context = context.clone();
+            std::thread::spawn(move || {
+                set_sizes(&mut context, 10.0 + i as f32, 12.0);
+                context.calculate_text_width("如是 如是", true)
+            })
+        })
+        .collect();
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.join().unwrap(), 5.0 * (10.0 + i as f32));
+    }
+
+    // "如是 如是" is one font run, shaped once for all sizes and threads
+    assert_eq!(cache.shaped_len(), 1);
+}
+
+#[test]
+fn shaped_runs_stop_growing_across_exports() {
+    let cache = GlyphMetricsCache::with_shaped_capacity(64);
+    let mut context = test_context();
+    context.set_metrics_cache(cache.clone());
+    set_sizes(&mut context, 12.0, 12.0);
+
+    // Every export brings text not shaped before, as a long-running process would
+    let mut sizes = Vec::new();
+    for export in 0..20 {
+        let chinese: Vec<String> = (1..=8).map(|i| "如是".repeat(export * 8 + i)).collect();
+        let english: Vec<String> = (1..=8).map(|i| "Thus ".repeat(export * 8 + i)).collect();
+        common::render_pdf(&context, &common::sections(&chinese, &english), LayoutMode::Alternating, "metrics_cache");
+        sizes.push(cache.shaped_len());
+    }
+
+    assert!(sizes.iter().all(|&len| len <= cache.shaped_capacity()), "{:?}", sizes);
+    assert!(sizes.iter().any(|&len| len > cache.shaped_capacity() / 2), "{:?}", sizes);
+    // Glyph metrics are not evicted with the runs
+    assert_eq!(cache.len(), 7);
+}
@@ -0,0 +1,128 @@
+//! Paragraphs longer than the space left on a page are broken between lines,
+//! within the widow and orphan limits, and continue justified on the next page.
+//! Side-by-side rows break both columns at the same share of their lines.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::FontContext;
+use cbeta_pdf_creator::typography::TextLayoutEngine;
+use lopdf::content::Operation;
+
+const TEXT: &str = "如是我聞一時佛在舍衛國";
+
+fn test_context() -> FontContext {
+    let chars: Vec<char> = TEXT.chars().collect();
+    let mut context = common::test_context(&chars, &['a']);
+    // 180pt of content per page (four lines at a 40pt leading), 120pt measure
+    context.set_options(194.0, 254.0, 27.0, 10.0, 10.0, 4.0, 0.0, 0.0, 0.6);
+    context
+}
+
+/// Baselines of the lines drawn on each page, top down
+fn page_baselines(context: &FontContext, chinese: &[String]) -> Vec<Vec<f32>> {
+    let english = vec![String::new(); chinese.len()];
+    let document = common::sections(chinese, &english);
+    let pages = common::render_pages(context, &document, LayoutMode::Alternating, "page_breaking");
+    line_origins(&pages).into_iter().map(|page| page.into_iter().map(|(_, y)| y).collect()).collect()
+}
+
+/// Origins of the lines of one side-by-side row on each page
+fn side_by_side_lines(context: &FontContext, chinese: String, english: String) -> Vec<Vec<(f32, f32)>> {
+    let document = common::sections(&[chinese], &[english]);
+    line_origins(&common::render_pages(context, &document, LayoutMode::SideBySide, "page_breaking_rows"))
+}
+
+/// Text matrix origin (x, y) of each line drawn on each page
+fn line_origins(pages: &[Vec<Operation>]) -> Vec<Vec<(f32, f32)>> {
+    pages
+        .iter()
+        .map(|operations| {
+            operations
+                .iter()
+                .filter(|op| op.operator == "Tm")
+                .map(|op| (op.operands[4].as_float().unwrap(), op.operands[5].as_float().unwrap()))
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn long_paragraph_runs_on_over_several_pages() {
+    let context = test_context();
+    // Twelve characters a line: nine lines, the last page keeping two
+    let pages = page_baselines(&context, &[TEXT.repeat(10).chars().take(108).collect()]);
+    let lines: Vec<usize> = pages.iter().map(|page| page.len()).collect();
+    assert_eq!(lines, vec![4, 3, 2], "{:?}", pages);
+
+    // Each page starts at the top of the content area.
+    assert!(pages.iter().all(|page| (page[0] - pages[0][0]).abs() < 1e-3), "{:?}", pages);
+}
+
+#[test]
+fn widow_and_orphan_limits_move_whole_paragraphs() {
+    let mut context = test_context();
+    let short: String = TEXT.repeat(2).chars().take(12).collect();
+    let long: String = TEXT.repeat(4).chars().take(36).collect();
+
+    // One line, then three: two fit below it but would leave a widow of one.
+    let pages = page_baselines(&context, &[short.clone(), long.clone()]);
+    let lines: Vec<usize> = pages.iter().map(|page| page.len()).collect();
+    assert_eq!(lines, vec![1, 3], "{:?}", pages);
+
+    context.widow_lines = 1;
+    let pages = page_baselines(&context, &[short, long]);
+    let lines: Vec<usize> = pages.iter().map(|page| page.len()).collect();
+    assert_eq!(lines, vec![3, 1], "{:?}", pages);
+}
+
+#[test]
+fn continuation_keeps_its_justification() {
+    let mut engine = TextLayoutEngine::new(test_context());
+    let text = format!("{}，{}", TEXT, TEXT.repeat(4));
+    let mut paragraph = engine.layout_paragraph(&text, 0.0, 0.0, 120.0, true).unwrap();
+    let justified: Vec<bool> = paragraph.lines.iter().map(|line| line.is_justified).collect();
+    let leading = paragraph.leading;
+
+    let rest = paragraph.split_off(2);
+    assert_eq!(paragraph.lines.len(), 2);
+    assert!((paragraph.height - 2.0 * leading).abs() < 1e-3);
+    assert_eq!(rest.lines[0].baseline, 0.0);
+    assert!((rest.height - rest.lines.len() as f32 * leading).abs() < 1e-3);
+
+    let after: Vec<bool> = paragraph.lines.iter().chain(&rest.lines).map(|line| line.is_justified).collect();
+    assert_eq!(justified, after);
+}
+
+#[test]
+fn side_by_side_row_continues_in_both_columns() {
+    let context = test_context();
+    // Nine Chinese lines beside a longer translation; the columns start at x 37 and 181.
+    let chinese: String = TEXT.repeat(10).chars().take(108).collect();
+    let english = "aaaa aaaaaa aa aaa aaaaa ".repeat(12);
+    let pages = side_by_side_lines(&context, chinese, english);
+    assert!(pages.len() >= 3, "{:?}", pages);
+
+    for page in &pages {
+        let chinese_lines = page.iter().filter(|&&(x, _)| x < 100.0).count();
+        let english_lines = page.len() - chinese_lines;
+        assert!(chinese_lines >= 2 && english_lines >= 2, "{:?}", pages);
+        // Nothing runs below the content area.
+        assert!(page.iter().all(|&(_, y)| y >= 37.0), "{:?}", pages);
+    }
+}
+
+#[test]
+fn broken_columns_can_carry_a_continued_marker() {
+    let mut context = test_context();
+    context.continued_marker = Some(("舍衛".to_string(), String::new()));
+    let chinese: String = TEXT.repeat(10).chars().take(108).collect();
+    let pages = side_by_side_lines(&context, chinese, String::new());
+
+    // Set flush right under the column on every page but the last
+    let markers: Vec<usize> = pages
+        .iter()
+        .map(|page| page.iter().filter(|&&(x, _)| (x - 137.0).abs() < 1e-3).count())
+        .collect();
+    assert_eq!(markers, vec![1, 1, 0], "{:?}", pages);
+}
@@ -0,0 +1,134 @@
+//! Named paragraph styles: indents, alignment, space before and after, and type
+//! sizes, selected by sections with `<p class="name">`.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::{FontContext, Justification};
+use cbeta_pdf_creator::styles::{self, ParagraphStyle};
+use cbeta_pdf_creator::typography::TextLayoutEngine;
+
+const TEXT: &str = "如是我聞一時佛在舍衛國";
+
+fn test_context() -> FontContext {
+    let chars: Vec<char> = TEXT.chars().collect();
+    common::test_context(&chars, &['a', ' '])
+}
+
+fn style(spec: &str) -> ParagraphStyle {
+    ParagraphStyle::parse(spec).expect("style parses")
+}
+
+#[test]
+fn styles_are_read_from_a_specification() {
+    let parsed = style("first-line-indent: 2em; align: centre; space-before: 6pt; chinese-size: 12");
+    assert_eq!(parsed.first_line_indent, 2.0);
+    assert_eq!(parsed.alignment, Some(Justification::Center));
+    assert_eq!(parsed.space_before, 6.0);
+    assert_eq!(parsed.font_size(true), Some(12.0));
+    assert_eq!(parsed.font_size(false), None);
+
+    assert!(ParagraphStyle::parse("indent 2").is_err());
+    assert!(ParagraphStyle::parse("align: middle").is_err());
+
+    assert_eq!(styles::parse_markup("<p class=\"verse\">如是</p>"), (Some("verse"), "如是"));
+    assert_eq!(styles::parse_markup("如是"), (None, "如是"));
+}
+
+#[test]
+fn values_that_cannot_be_set_are_rejected() {
+    for spec in [
+        "chinese-size: 0",
+        "english-size: -9",
+        "english-size: NaN",
+        "chinese-size: inf",
+        "left-indent: -1e9",
+        "first-line-indent: -2em",
+        "space-after: infinity",
+        "right-indent: 1e39",
+    ] {
+        assert!(ParagraphStyle::parse(spec).is_err(), "{}", spec);
+    }
+    assert_eq!(style("left-indent: 0; space-before: 0pt").left_indent, 0.0);
+}
+
+#[test]
+fn first_line_is_indented_by_two_characters() {
+    let mut context = test_context();
+    context.paragraph_styles.set("body", style("first-line-indent: 2"));
+    let mut engine = TextLayoutEngine::new(context);
+    let text = format!("<p class=\"body\">{}</p>", TEXT.repeat(2));
+    let paragraph = engine.layout_paragraph(&text, 0.0, 0.0, 100.0, true).unwrap();
+
+    let lines: Vec<(usize, f32)> = paragraph.lines.iter().map(|line| (line.text.chars().count(), line.indent)).collect();
+    assert_eq!(lines, [(8, 20.0), (10, 0.0), (4, 0.0)]);
+}
+
+#[test]
+fn hanging_and_side_indents_narrow_the_lines() {
+    let mut engine = TextLayoutEngine::new(test_context());
+    let hanging = style("left-indent: 1; right-indent: 1; hanging-indent: 2");
+    let paragraph = engine.layout_styled_paragraph(&TEXT.repeat(2), 0.0, 0.0, 100.0, true, &hanging).unwrap();
+
+    // 80pt for the first line, 60pt for the rest
+    let lines: Vec<(usize, f32)> = paragraph.lines.iter().map(|line| (line.text.chars().count(), line.indent)).collect();
+    assert_eq!(lines, [(8, 10.0), (6, 30.0), (6, 30.0), (2, 30.0)]);
+}
+
+#[test]
+fn lines_can_be_centred_or_set_flush_right() {
+    let mut engine = TextLayoutEngine::new(test_context());
+    let text = TEXT.repeat(2);
+    let right = engine.layout_styled_paragraph(&text, 0.0, 0.0, 100.0, true, &style("align: right")).unwrap();
+    let centred = engine.layout_styled_paragraph(&text, 0.0, 0.0, 100.0, true, &style("align: center")).unwrap();
+
+    // Full lines are not spread out, and the short last line sits at the right or in the middle.
+    assert!(right.lines.iter().all(|line| line.space_adjustments.is_empty()));
+    assert_eq!(right.lines[2].indent, 80.0);
+    assert_eq!(centred.lines[2].indent, 40.0);
+    assert_eq!(centred.lines[0].indent, 0.0);
+
+    // English lines are measured without the space they break at.
+    let english = engine
+        .layout_styled_paragraph("aaaa aaaa aaaa aaaa aaaa aaaa", 0.0, 0.0, 60.0, false, &style("align: right"))
+        .unwrap();
+    assert!(english.lines.len() > 1);
+    assert_eq!(english.lines[0].text.trim_end(), "aaaa aaaa");
+    assert!((english.lines[0].indent - 15.0).abs() < 1e-3, "{}", english.lines[0].indent);
+}
+
+#[test]
+fn style_sizes_replace_the_document_size() {
+    let mut engine = TextLayoutEngine::new(test_context());
+    let large = style("chinese-size: 20");
+    let paragraph = engine.layout_styled_paragraph(TEXT, 0.0, 0.0, 100.0, true, &large).unwrap();
+    assert_eq!(paragraph.font_size, 20.0);
+    assert_eq!(paragraph.lines[0].text.chars().count(), 5);
+    assert!(paragraph.lines[0].runs.iter().all(|run| run.font_size == 20.0));
+
+    // The next paragraph is back at the document size.
+    let plain = engine.layout_paragraph(TEXT, 0.0, 0.0, 100.0, true).unwrap();
+    assert_eq!(plain.font_size, 10.0);
+}
+
+#[test]
+fn space_before_and_after_separate_paragraphs() {
+    let baselines = |context: &FontContext| -> Vec<f32> {
+        let chinese = vec!["如是".to_string(), "<p class=\"spaced\">我聞</p>".to_string(), "一時".to_string()];
+        let document = common::sections(&chinese, &vec![String::new(); 3]);
+        common::render_pages(context, &document, LayoutMode::Alternating, "paragraph_styles")[0]
+            .iter()
+            .filter(|op| op.operator == "Tm")
+            .map(|op| op.operands[5].as_float().unwrap())
+            .collect()
+    };
+
+    let mut context = test_context();
+    let plain = baselines(&context);
+    context.paragraph_styles.set("spaced", style("space-before: 12; space-after: 30"));
+    let spaced = baselines(&context);
+
+    assert_eq!(spaced[0], plain[0]);
+    assert!((plain[1] - spaced[1] - 12.0).abs() < 1e-3, "{:?} {:?}", plain, spaced);
+    assert!((plain[2] - spaced[2] - 42.0).abs() < 1e-3, "{:?} {:?}", plain, spaced);
+}
@@ -0,0 +1,94 @@
+//! Ruby annotations: readings from markup or a dictionary are set centred over
+//! their base characters, with taller lines and room made for wide readings.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::FontContext;
+use cbeta_pdf_creator::ruby::{self, DictionaryReading, RubySegment, RubySettings};
+use cbeta_pdf_creator::typography::TextLayoutEngine;
+
+fn test_context() -> FontContext {
+    let latin: Vec<char> = "abcdefghijklmnopqrstuvwxyzúìóàǒ ".chars().collect();
+    common::test_context(&['如', '是', '我', '聞', '佛', '狀'], &latin)
+}
+
+fn ruby(base: &str, text: &str) -> RubySegment {
+    RubySegment::Ruby { base: base.to_string(), text: text.to_string() }
+}
+
+#[test]
+fn markup_is_split_into_bases_and_readings() {
+    let segments = ruby::parse_markup("<ruby>如是<rt>rú shì</rt></ruby>我<ruby>聞<rp>(</rp><rt>wén</rt><rp>)</rp></ruby>");
+    assert_eq!(segments, [ruby("如是", "rú shì"), RubySegment::Text("我".to_string()), ruby("聞", "wén")]);
+    assert_eq!(ruby::plain_text(&segments), "如是我聞");
+}
+
+#[test]
+fn dictionary_readings_take_the_longest_word() {
+    let mut settings = RubySettings::default();
+    let dictionary = "# CC-CEDICT\n\
+        如是 如是 [ru2 shi4] /thus/\n\
+        如 如 [ru2] /as/\n\
+        綠 绿 [lu:4] /green/\n";
+    assert_eq!(settings.add_dictionary(dictionary, DictionaryReading::Pinyin), 3);
+
+    let segments = settings.annotate(vec![RubySegment::Text("如是綠如".to_string())]);
+    assert_eq!(segments, [ruby("如是", "rú shì"), ruby("綠", "lǜ"), ruby("如", "rú")]);
+
+    assert_eq!(ruby::pinyin_tone_marks("gou3"), "gǒu");
+    assert_eq!(ruby::pinyin_tone_marks("hui4"), "huì");
+    assert_eq!(ruby::pinyin_tone_marks("ma5"), "ma");
+}
+
+#[test]
+fn readings_go_over_each_character_and_widen_the_line() {
+    let mut engine = TextLayoutEngine::new(test_context());
+    let plain = engine.layout_paragraph("如是我聞", 0.0, 0.0, 400.0, true).unwrap();
+    let annotated = engine
+        .layout_paragraph("<ruby>如是<rt>rú shì</rt></ruby>我<ruby>狀<rt>zhuàng</rt></ruby>", 0.0, 0.0, 400.0, true)
+        .unwrap();
+
+    let line = &annotated.lines[0];
+    assert_eq!(line.text, "如是我狀");
+    let placed: Vec<(usize, usize, &str)> = line.ruby.iter().map(|r| (r.start, r.end, r.text.as_str())).collect();
+    assert_eq!(placed, [(0, 1, "rú"), (1, 2, "shì"), (3, 4, "zhuàng")]);
+
+    // Readings are set at half size, and the lines make room for them.
+    assert!(line.ruby.iter().all(|r| r.runs.iter().all(|run| run.font_size == 5.0)));
+    assert!((annotated.leading - plain.leading - 5.0).abs() < 1e-3);
+
+    // "zhuàng" is six letters of 2.5pt over a 10pt character.
+    assert!((line.ruby[2].width - 15.0).abs() < 1e-3);
+    assert!((line.width - plain.lines[0].width - 5.0).abs() < 1e-3, "{} vs {}", line.width, plain.lines[0].width);
+}
+
+#[test]
+fn pdf_centres_readings_over_their_bases() {
+    let context = test_context();
+    let chinese = "我<ruby>狀<rt>zhuàng</rt></ruby>如".to_string();
+    let document = common::sections(&[chinese], &[String::new()]);
+    let operations = &common::render_pages(&context, &document, LayoutMode::Alternating, "ruby")[0];
+
+    let origins: Vec<(f32, f32)> = operations
+        .iter()
+        .filter(|op| op.operator == "Tm")
+        .map(|op| (op.operands[4].as_float().unwrap(), op.operands[5].as_float().unwrap()))
+        .collect();
+    assert_eq!(origins.len(), 2, "{:?}", origins);
+    let (line, reading) = (origins[0], origins[1]);
+
+    // 狀 is set after 我 and half the reading's overhang; the reading, 15pt wide,
+    // starts 2.5pt before it and sits above the line.
+    assert!((reading.0 - (line.0 + 10.0)).abs() < 1e-3, "{:?}", origins);
+    assert!(reading.1 > line.1 + 8.0, "{:?}", origins);
+
+    // The line spaces its characters apart with TJ offsets
+    let spaced = operations
+        .iter()
+        .filter(|op| op.operator == "TJ")
+        .flat_map(|op| op.operands[0].as_array().unwrap().iter())
+        .filter(|item| item.as_float().is_ok_and(|value| (value + 250.0).abs() < 1e-3))
+        .count();
+    assert_eq!(spaced, 2);
+}
@@ -0,0 +1,68 @@
+//! Paragraphs are itemised into script runs: Chinese quoted in English (and
+//! Latin in Chinese) is set in its own fonts, with CJK–Latin spacing.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::FontContext;
+use cbeta_pdf_creator::typography::{FormattedLine, ScriptRun, TextLayoutEngine};
+
+fn test_context() -> FontContext {
+    let english: Vec<char> = "emptinsBudh (,)".chars().collect();
+    let mut context = common::test_context(&['空', '佛', '說', '經'], &english);
+    context.set_options(595.0, 842.0, 72.0, 12.0, 10.0, 1.4, 0.0, 0.0, 0.6);
+    context
+}
+
+fn first_line(context: &FontContext, text: &str, is_chinese: bool) -> FormattedLine {
+    let mut engine = TextLayoutEngine::new(context.clone());
+    let paragraph = engine.layout_paragraph(text, 0.0, 0.0, 400.0, is_chinese).expect("layout succeeds");
+    paragraph.lines.into_iter().next().expect("one line")
+}
+
+fn run(start: usize, end: usize, is_chinese: bool, font_size: f32) -> ScriptRun {
+    ScriptRun { start, end, is_chinese, font_size }
+}
+
+#[test]
+fn chinese_quoted_in_english_gets_its_own_run() {
+    let context = test_context();
+    let line = first_line(&context, "emptiness (空)", false);
+    assert_eq!(line.runs, [run(0, 11, false, 10.0), run(11, 12, true, 12.0), run(12, 13, false, 10.0)]);
+    // Twelve English characters at 5pt, the ideograph at 12pt
+    assert_eq!(line.width, 12.0 * 5.0 + 12.0);
+}
+
+#[test]
+fn latin_in_chinese_is_spaced_from_ideographs() {
+    let mut context = test_context();
+    let line = first_line(&context, "佛說Buddha經", true);
+    assert_eq!(line.runs, [run(0, 2, true, 12.0), run(2, 8, false, 10.0), run(8, 9, true, 12.0)]);
+    // A quarter em of the Chinese size on each side of "Buddha"
+    assert_eq!(line.width, 3.0 * 12.0 + 6.0 * 5.0 + 2.0 * 3.0);
+
+    context.cjk_latin_spacing = 0.0;
+    assert_eq!(first_line(&context, "佛說Buddha經", true).width, 3.0 * 12.0 + 6.0 * 5.0);
+}
+
+#[test]
+fn pdf_switches_fonts_and_sizes_within_a_line() {
+    let context = test_context();
+    let document = common::sections(&[String::new()], &["emptiness (空)".to_string()]);
+    let pdf = common::render_pdf(&context, &document, LayoutMode::Alternating, "script_runs");
+    let fonts: Vec<(String, f32)> = common::page_operations(&pdf)[0]
+        .iter()
+        .filter(|op| op.operator == "Tf")
+        .map(|op| {
+            let name = String::from_utf8(op.operands[0].as_name().unwrap().to_vec()).unwrap();
+            (name, op.operands[1].as_float().unwrap())
+        })
+        .collect();
+    assert_eq!(
+        fonts,
+        [("english".to_string(), 10.0), ("chinese".to_string(), 12.0), ("english".to_string(), 10.0)]
+    );
+
+    let text = pdf.extract_text(&[1]).expect("text extraction succeeds");
+    assert!(text.contains('空'), "extracted text was {:?}", text);
+}
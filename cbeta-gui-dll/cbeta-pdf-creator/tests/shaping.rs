@@ -0,0 +1,75 @@
+//! Runs are shaped with the font's OpenType features: widths used for line
+//! breaking and the glyphs written to the PDF both come from the shaper.
+
+mod common;
+
+use cbeta_pdf_creator::font_registry::Script;
+use cbeta_pdf_creator::metrics_cache::GlyphMetricsCache;
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::FontContext;
+use lopdf::Object;
+
+fn test_context() -> FontContext {
+    // English: f i T o space, the "fi" ligature (glyph 6), T-o kerned by -100 units.
+    let english = common::build_test_font_with_tables(
+        &[('f', 1), ('i', 2), ('T', 3), ('o', 4), (' ', 5), ('\u{FB01}', 6)],
+        500,
+        vec![(b"GSUB", common::gsub_ligature(&[1, 2], 6)), (b"GPOS", common::gpos_pair_kerning(3, 4, -100))],
+    );
+    // Chinese: the full-width comma (glyph 3) is halved by `palt`.
+    let chinese = common::build_test_font_with_tables(
+        &[('如', 1), ('是', 2), ('，', 3)],
+        1000,
+        vec![(b"GPOS", common::gpos_single_advance(b"palt", 3, -500))],
+    );
+    let mut context = FontContext::from_font_data("Test CJK", chinese, "Test Latin", english).expect("test fonts load");
+    context.set_metrics_cache(GlyphMetricsCache::new());
+    context.set_options(595.0, 842.0, 72.0, 10.0, 10.0, 1.4, 0.0, 0.0, 0.6);
+    context
+}
+
+#[test]
+fn widths_include_ligatures_kerning_and_script_features() {
+    let mut context = test_context();
+
+    assert_eq!(context.calculate_text_width("fi", false), 5.0);
+    assert_eq!(context.calculate_text_width("To", false), 9.0);
+    // `palt` is off unless requested for the script.
+    assert_eq!(context.calculate_text_width("如，", true), 20.0);
+
+    assert!(context.font_features.set_from_str(Script::Latin, "-liga"));
+    assert!(context.font_features.set_from_str(Script::Han, "palt"));
+    assert_eq!(context.calculate_text_width("fi", false), 10.0);
+    assert_eq!(context.calculate_text_width("To", false), 9.0);
+    assert_eq!(context.calculate_text_width("如，", true), 15.0);
+
+    assert!(!context.font_features.set_from_str(Script::Latin, "liga, not-a-feature"));
+}
+
+#[test]
+fn pdf_shows_shaped_glyphs_with_exact_advances() {
+    let context = test_context();
+
+    let document = common::sections(&["如是".to_string()], &["To fit".to_string()]);
+    let pdf = common::render_pdf(&context, &document, LayoutMode::Alternating, "shaping");
+    let english_tj: Vec<Object> = common::page_operations(&pdf)[0]
+        .windows(2)
+        .filter(|ops| ops[0].operator == "Tf" && ops[0].operands[0].as_name().unwrap() == b"english")
+        .flat_map(|ops| ops[1].operands[0].as_array().unwrap().clone())
+        .collect();
+
+    // Glyph IDs as CIDs: T, o, space, then the fi ligature in place of f + i
+    let codes: Vec<u16> = english_tj
+        .iter()
+        .filter_map(|item| item.as_str().ok())
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .collect();
+    assert_eq!(&codes[..3], &[3, 4, 5]);
+    assert!(codes.contains(&6), "ligature glyph shown: {:?}", codes);
+    assert!(!codes.contains(&2), "no separate i: {:?}", codes);
+    // The kerning after T moves the o 100 units (0.1 em) left.
+    assert_eq!(english_tj[1].as_float().unwrap(), 100.0);
+
+    let text = pdf.extract_text(&[1]).expect("text extraction succeeds");
+    assert!(text.contains("fi"), "extracted text was {:?}", text);
+}
@@ -0,0 +1,24 @@
+//! Regression test: CJK Extension B+ ideographs must render and extract as
+//! single characters, not as pairs of surrogate CIDs.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+
+#[test]
+fn supplementary_plane_ideographs_round_trip_through_extraction() {
+    // 𠀀 𠀁 (Ext. B start), 𪛖 (Ext. B end), 𫝀 (Ext. D), 𰀀 (Ext. G) next to BMP text
+    let chinese = "如是𠀀𠀁𪛖𫝀𰀀。";
+    let english = "Thus";
+
+    let zh_chars: Vec<char> = chinese.chars().collect();
+    let en_chars: Vec<char> = english.chars().chain([' ']).collect();
+    let font_context = common::test_context(&zh_chars, &en_chars);
+
+    let sections = common::sections(&[chinese.to_string()], &[english.to_string()]);
+    let document = common::render_pdf(&font_context, &sections, LayoutMode::Alternating, "supplementary_plane");
+    let text = document.extract_text(&[1]).expect("text extraction succeeds");
+
+    assert!(text.contains(chinese), "extracted text was {:?}", text);
+    assert!(!text.contains('\u{FFFD}'), "replacement characters in {:?}", text);
+}
@@ -0,0 +1,55 @@
+//! ToUnicode CMaps are built from the glyphs actually drawn, so copied text
+//! matches the source exactly.
+
+mod common;
+
+use cbeta_pdf_creator::font_embedding::{FontUsage, GlyphCode};
+use cbeta_pdf_creator::document::LayoutMode;
+use cbeta_pdf_creator::fonts::FontContext;
+
+#[test]
+fn glyphs_shared_by_several_texts_get_alias_cids() {
+    let mut usage = FontUsage::with_cid_aliases(10);
+
+    assert_eq!(usage.record_glyph(4, "ffi"), GlyphCode { cid: 4, actual_text: None });
+    assert_eq!(usage.record_glyph(4, "ffi").cid, 4);
+    // Trailing glyphs of a cluster carry no text of their own.
+    assert_eq!(usage.record_glyph(4, "").cid, 4);
+    assert_eq!(usage.record_glyph(4, "f").cid, 10);
+    assert_eq!(usage.record_glyph(4, "f").cid, 10);
+
+    assert_eq!(usage.cids[&4], (4, "ffi".to_string()));
+    assert_eq!(usage.cids[&10], (4, "f".to_string()));
+    assert_eq!(usage.glyph_ids(), vec![0, 4]);
+
+    // CFF fonts cannot remap CIDs; the text is returned for /ActualText instead.
+    let mut cff_usage = FontUsage::default();
+    cff_usage.record_glyph(4, "ffi");
+    assert_eq!(
+        cff_usage.record_glyph(4, "f"),
+        GlyphCode { cid: 4, actual_text: Some("f".to_string()) }
+    );
+}
+
+#[test]
+fn copied_text_matches_the_source_exactly() {
+    // U+F900 is a compatibility ideograph drawn with the same glyph as U+8C48.
+    let chinese = "豈\u{F900}如是𠀀\u{F900}";
+    let font = common::build_test_font_with_cmap(
+        &[('豈', 1), ('\u{F900}', 1), ('如', 2), ('是', 3), ('𠀀', 4)],
+        1000,
+    );
+    let font_context = FontContext::from_font_data(
+        "Test CJK",
+        font,
+        "Test Latin",
+        common::build_test_font(&['T', 'h', 'u', 's', ' '], 500),
+    )
+    .expect("test fonts load");
+
+    let sections = common::sections(&[chinese.to_string()], &["Thus".to_string()]);
+    let document = common::render_pdf(&font_context, &sections, LayoutMode::Alternating, "tounicode");
+    let text = document.extract_text(&[1]).expect("text extraction succeeds");
+
+    assert!(text.contains(chinese), "extracted text was {:?}", text);
+}
@@ -0,0 +1,122 @@
+//! Verse (gāthā): each line kept whole and never justified, Chinese lines in one
+//! or two aligned columns of 句, English lines paired with them.
+
+mod common;
+
+use cbeta_pdf_creator::document::{self, Block, DocumentBuilder, LayoutMode};
+use cbeta_pdf_creator::fonts::{FontContext, Justification, WritingMode};
+use cbeta_pdf_creator::styles::ParagraphStyle;
+use cbeta_pdf_creator::typography::{FormattedParagraph, TextLayoutEngine};
+
+const GATHA: [&str; 4] = ["如是我聞時", "佛在舍衛國", "一時佛在國", "舍衛如是聞"];
+
+fn test_context() -> FontContext {
+    let chars: Vec<char> = "如是我聞一時佛在舍衛國".chars().collect();
+    common::test_context(&chars, &['a', ' '])
+}
+
+fn verse(context: FontContext, lines: &[&str], width: f32, style: &ParagraphStyle) -> Vec<FormattedParagraph> {
+    let mut engine = TextLayoutEngine::new(context);
+    engine.layout_verse(lines, 0.0, 0.0, width, true, style).expect("layout succeeds")
+}
+
+/// (text, x, baseline) of each line of each row
+fn rows(paragraphs: &[FormattedParagraph]) -> Vec<Vec<(String, f32, f32)>> {
+    paragraphs
+        .iter()
+        .map(|row| row.lines.iter().map(|line| (line.text.clone(), line.x + line.indent, line.baseline)).collect())
+        .collect()
+}
+
+#[test]
+fn lines_are_read_from_verse_markup() {
+    assert_eq!(
+        document::parse_verse("<lg type=\"regular\"><l>如是我聞時</l> <l n=\"2\">佛在舍衛國</l></lg>"),
+        Some(vec!["如是我聞時", "佛在舍衛國"])
+    );
+    assert_eq!(document::parse_verse("<lg>如是我聞時\n佛在舍衛國\n</lg>"), Some(vec!["如是我聞時", "佛在舍衛國"]));
+    assert_eq!(document::parse_verse("如是我聞時"), None);
+
+    // The translation's lines pair with the verse's; prose keeps its line breaks as spaces
+    let chinese = vec!["<lg><l>如是我聞時</l><l>佛在舍衛國</l></lg>".to_string(), "如是\n我聞".to_string()];
+    let english = vec!["Thus have I heard\nThe Buddha dwelt".to_string(), "Thus\nI heard".to_string()];
+    let builder = DocumentBuilder::from_sections(&chinese, &english).unwrap();
+    assert_eq!(
+        builder.blocks(),
+        [
+            Block::Verse {
+                chinese: vec!["如是我聞時".into(), "佛在舍衛國".into()],
+                english: vec!["Thus have I heard".into(), "The Buddha dwelt".into()],
+            },
+            Block::BilingualPair { chinese: "如是 我聞".into(), english: "Thus I heard".into() },
+        ]
+    );
+}
+
+#[test]
+fn lines_go_two_to_a_row_in_aligned_columns() {
+    let uneven = ["如是我聞時", "佛在", "舍衛", "一時佛在國"];
+    let rows = rows(&verse(test_context(), &uneven, 200.0, &ParagraphStyle::default()));
+    // The second column starts two characters after the widest first-column line
+    assert_eq!(
+        rows,
+        [
+            vec![("如是我聞時".to_string(), 0.0, 0.0), ("佛在".to_string(), 70.0, 0.0)],
+            vec![("舍衛".to_string(), 0.0, 0.0), ("一時佛在國".to_string(), 70.0, 0.0)],
+        ]
+    );
+
+    // Centred as a block of two columns
+    let centred = ParagraphStyle { alignment: Some(Justification::Center), ..ParagraphStyle::default() };
+    let rows = self::rows(&verse(test_context(), &GATHA, 200.0, &centred));
+    assert_eq!((rows[0][0].1, rows[0][1].1), (40.0, 110.0));
+}
+
+#[test]
+fn narrow_measures_and_vertical_text_take_one_column() {
+    let one_column = |paragraphs: &[FormattedParagraph]| paragraphs.len() == 4 && paragraphs.iter().all(|p| p.lines.len() == 1);
+    assert!(one_column(&verse(test_context(), &GATHA, 100.0, &ParagraphStyle::default())));
+
+    let mut context = test_context();
+    context.verse_columns = 1;
+    assert!(one_column(&verse(context, &GATHA, 200.0, &ParagraphStyle::default())));
+
+    let mut context = test_context();
+    context.writing_mode = WritingMode::Vertical;
+    assert!(one_column(&verse(context, &GATHA, 200.0, &ParagraphStyle::default())));
+}
+
+#[test]
+fn verse_is_never_justified() {
+    let justified = ParagraphStyle {
+        alignment: Some(Justification::Justify),
+        hanging_indent: 2.0,
+        ..ParagraphStyle::default()
+    };
+    // Longer than the measure: the line turns over, indented, and neither part is spread out
+    let paragraphs = verse(test_context(), &["如是我聞一時佛在舍衛國如是我聞"], 100.0, &justified);
+    assert_eq!(paragraphs.len(), 1);
+    let lines = &paragraphs[0].lines;
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|line| !line.is_justified && line.space_adjustments.is_empty()));
+    assert_eq!(lines[1].indent, 20.0);
+}
+
+#[test]
+fn verse_sections_keep_their_lines_in_the_pdf() {
+    let chinese = vec![format!("<lg>{}</lg>", GATHA.join("\n"))];
+    let document = common::sections(&chinese, &[String::new()]);
+    let pages = common::render_pages(&test_context(), &document, LayoutMode::Alternating, "verse");
+    let positions: Vec<(f32, f32)> = pages[0]
+        .iter()
+        .filter(|op| op.operator == "Tm")
+        .map(|op| (op.operands[4].as_float().unwrap(), op.operands[5].as_float().unwrap()))
+        .collect();
+    // Two rows of two lines, each row on one baseline, rows a line apart
+    assert_eq!(positions.len(), 4);
+    assert_eq!(positions[0].1, positions[1].1);
+    assert_eq!(positions[2].1, positions[3].1);
+    assert_eq!(positions[0].1 - positions[2].1, 14.0);
+    assert_eq!(positions[0].0, positions[2].0);
+    assert_eq!(positions[1].0, positions[3].0);
+}
@@ -0,0 +1,61 @@
+//! Vertical layout sets Chinese in top-to-bottom columns with Identity-V fonts,
+//! running right to left on pages bound on the right.
+
+mod common;
+
+use cbeta_pdf_creator::document::LayoutMode;
+use lopdf::Object;
+
+#[test]
+fn chinese_runs_in_right_to_left_columns_with_vertical_forms() {
+    let chinese = "如是我聞，".repeat(60);
+    // Glyph 6 is the vertical comma (U+FE10); the font has no `vert` lookup.
+    let mut font_context = common::test_context(&['如', '是', '我', '聞', '，', '︐'], &['T', 'h', 'u', 's', ' ']);
+    font_context.tracking_chinese = 0.0;
+
+    let sections = common::sections(std::slice::from_ref(&chinese), &[String::new()]);
+    let document = common::render_pdf(&font_context, &sections, LayoutMode::Vertical, "vertical_layout");
+
+    let catalog = document.catalog().expect("catalog");
+    let preferences = catalog.get(b"ViewerPreferences").and_then(Object::as_dict).expect("viewer preferences");
+    assert_eq!(preferences.get(b"Direction").and_then(Object::as_name).unwrap(), b"R2L");
+
+    let page_id = document.page_iter().next().expect("one page");
+    let fonts = document.get_page_fonts(page_id).expect("page fonts");
+    let vertical_font = fonts.get(b"chinese_v".as_slice()).expect("vertical Chinese font resource");
+    assert_eq!(vertical_font.get(b"Encoding").and_then(Object::as_name).unwrap(), b"Identity-V");
+    let descendant_id = vertical_font.get(b"DescendantFonts").and_then(Object::as_array).unwrap()[0]
+        .as_reference()
+        .unwrap();
+    let descendant = document.get_dictionary(descendant_id).unwrap();
+    let default_vertical = descendant.get(b"DW2").and_then(Object::as_array).expect("DW2");
+    assert_eq!(default_vertical[1].as_i64().unwrap(), -1000);
+
+    // Each column starts at the top of the page, left of the one before it.
+    let operations = &common::page_operations(&document)[0];
+    let column_origins: Vec<(f32, f32)> = operations
+        .iter()
+        .filter(|op| op.operator == "Tm")
+        .map(|op| (op.operands[4].as_float().unwrap(), op.operands[5].as_float().unwrap()))
+        .collect();
+    assert!(column_origins.len() > 1, "text fills several columns");
+    for pair in column_origins.windows(2) {
+        assert!(pair[1].0 < pair[0].0, "columns run right to left: {:?}", column_origins);
+        assert_eq!(pair[1].1, pair[0].1);
+    }
+
+    // The comma is drawn with its vertical form but still copies as a comma.
+    let shown: Vec<u8> = operations
+        .iter()
+        .filter(|op| op.operator == "TJ")
+        .flat_map(|op| op.operands[0].as_array().unwrap().iter())
+        .filter_map(|item| item.as_str().ok())
+        .flat_map(|bytes| bytes.to_vec())
+        .collect();
+    assert!(shown.chunks(2).any(|code| code == [0, 6]));
+    assert!(!shown.chunks(2).any(|code| code == [0, 5]));
+
+    let text = document.extract_text(&[1]).expect("text extraction succeeds");
+    let joined: String = text.chars().filter(|ch| !ch.is_whitespace()).collect();
+    assert!(joined.starts_with("如是我聞，如是我聞，"), "extracted text was {:?}", text);
+}
@@ -2,18 +2,89 @@
 //!
 //! PDF-only FFI bridge for CBETA GUI applications.
 
+use cbeta_pdf_creator::document::{DocumentBuilder, LayoutMode};
+use cbeta_pdf_creator::font_registry::{self, Script};
+use cbeta_pdf_creator::fonts::WritingMode;
+use cbeta_pdf_creator::hyphenation::HyphenationSettings;
+use cbeta_pdf_creator::ruby::{DictionaryReading, RubySettings};
+use cbeta_pdf_creator::shaping::FeatureSettings;
+use cbeta_pdf_creator::styles::{ParagraphStyle, ParagraphStyles};
 use std::ffi::CStr;
 use std::os::raw::{c_char, c_int};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
 
 /// PDF layout mode.
 const LAYOUT_ALTERNATING: c_int = 0;
 const LAYOUT_SIDE_BY_SIDE: c_int = 1;
+const LAYOUT_VERTICAL: c_int = 2;
+
+/// OpenType features applied to every export, set through `set_pdf_font_features`.
+fn font_features() -> &'static Mutex<FeatureSettings> {
+    static FEATURES: OnceLock<Mutex<FeatureSettings>> = OnceLock::new();
+    FEATURES.get_or_init(|| Mutex::new(FeatureSettings::default()))
+}
+
+/// Hyphenation applied to every export, set through `set_pdf_hyphenation` and
+/// `load_pdf_hyphenation_patterns`.
+fn hyphenation() -> &'static Mutex<HyphenationSettings> {
+    static HYPHENATION: OnceLock<Mutex<HyphenationSettings>> = OnceLock::new();
+    HYPHENATION.get_or_init(|| Mutex::new(HyphenationSettings::default()))
+}
+
+/// Ruby annotations applied to every export, set through `set_pdf_ruby` and
+/// `load_pdf_ruby_dictionary`.
+fn ruby() -> &'static Mutex<RubySettings> {
+    static RUBY: OnceLock<Mutex<RubySettings>> = OnceLock::new();
+    RUBY.get_or_init(|| Mutex::new(RubySettings::default()))
+}
+
+/// Dictionary format for `load_pdf_ruby_dictionary`.
+const RUBY_DICTIONARY_PINYIN: c_int = 0;
+const RUBY_DICTIONARY_NUMBERED_PINYIN: c_int = 1;
+const RUBY_DICTIONARY_JYUTPING: c_int = 2;
+const RUBY_DICTIONARY_TAB_SEPARATED: c_int = 3;
+
+/// "Continued" marker (Chinese, English) for side-by-side rows broken by a page,
+/// set through `set_pdf_continued_marker`.
+fn continued_marker() -> &'static Mutex<Option<(String, String)>> {
+    static MARKER: OnceLock<Mutex<Option<(String, String)>>> = OnceLock::new();
+    MARKER.get_or_init(|| Mutex::new(None))
+}
+
+/// Paragraph styles applied to every export, set through `set_pdf_paragraph_style`.
+fn paragraph_styles() -> &'static Mutex<ParagraphStyles> {
+    static STYLES: OnceLock<Mutex<ParagraphStyles>> = OnceLock::new();
+    STYLES.get_or_init(|| Mutex::new(ParagraphStyles::default()))
+}
+
+/// Columns of 句 in horizontal Chinese verse, set through `set_pdf_verse_columns`.
+fn verse_columns() -> &'static Mutex<usize> {
+    static COLUMNS: OnceLock<Mutex<usize>> = OnceLock::new();
+    COLUMNS.get_or_init(|| Mutex::new(2))
+}
+
+/// Page break rules for alternating layout (keep headings with what follows,
+/// keep Chinese with its English, lines of a short pair), set through
+/// `set_pdf_keep_rules`.
+fn keep_rules() -> &'static Mutex<(bool, bool, usize)> {
+    static RULES: OnceLock<Mutex<(bool, bool, usize)>> = OnceLock::new();
+    RULES.get_or_init(|| Mutex::new((true, true, 6)))
+}
+
+/// Font role for `set_pdf_font_family`.
+const FONT_ROLE_CHINESE: c_int = 0;
+const FONT_ROLE_ENGLISH: c_int = 1;
+const FONT_ROLE_DEVANAGARI: c_int = 2;
+const FONT_ROLE_SIDDHAM: c_int = 3;
+const FONT_ROLE_TIBETAN: c_int = 4;
 
 /// Generate a PDF from Chinese/English paragraph arrays.
 ///
 /// layout_mode:
 /// - 0: alternating Chinese then English paragraphs
 /// - 1: side-by-side rows (combined into one row paragraph)
+/// - 2: vertical Chinese columns, right to left (English lines rotated into columns)
 #[no_mangle]
 pub extern "C" fn generate_pdf_output_ffi(
     chinese_sections: *const *const c_char,
@@ -69,6 +140,33 @@ pub extern "C" fn generate_pdf_output_ffi(
         }
     };
 
+    if let Ok(features) = font_features().lock() {
+        font_context.font_features = features.clone();
+    }
+    if let Ok(settings) = hyphenation().lock() {
+        font_context.hyphenation = settings.clone();
+    }
+    if let Ok(marker) = continued_marker().lock() {
+        font_context.continued_marker = marker.clone();
+    }
+    if let Ok(settings) = ruby().lock() {
+        font_context.ruby = settings.clone();
+    }
+    if let Ok(styles) = paragraph_styles().lock() {
+        font_context.paragraph_styles = styles.clone();
+    }
+    if let Ok(columns) = verse_columns().lock() {
+        font_context.verse_columns = *columns;
+    }
+    if let Ok(rules) = keep_rules().lock() {
+        (font_context.keep_headings_with_next, font_context.keep_pairs_together, font_context.short_pair_lines) = *rules;
+    }
+
+    if layout_mode == LAYOUT_VERTICAL {
+        // Auto-sizing measures Chinese lines down the column.
+        font_context.writing_mode = WritingMode::Vertical;
+    }
+
     let include_english = english_sections.iter().any(|s| !s.trim().is_empty());
     let force_same_size = lock_bilingual_font_size != 0;
 
@@ -114,20 +212,14 @@ pub extern "C" fn generate_pdf_output_ffi(
         paragraph_spacing,
     );
 
-    let result = match layout_mode {
-        LAYOUT_SIDE_BY_SIDE => cbeta_pdf_creator::bilingual_generator::create_bilingual_pdf_side_by_side_with_context(
-            &chinese_sections,
-            &english_sections,
-            &output_path,
-            &font_context,
-        ),
-        LAYOUT_ALTERNATING | _ => cbeta_pdf_creator::bilingual_generator::create_bilingual_pdf_with_context(
-            &chinese_sections,
-            &english_sections,
-            &output_path,
-            &font_context,
-        ),
+    let layout = match layout_mode {
+        LAYOUT_SIDE_BY_SIDE => LayoutMode::SideBySide,
+        LAYOUT_VERTICAL => LayoutMode::Vertical,
+        LAYOUT_ALTERNATING | _ => LayoutMode::Alternating,
     };
+    // Sections that are a whole <h1>…<h6> element become headings
+    let result = DocumentBuilder::from_sections(&chinese_sections, &english_sections)
+        .and_then(|document| document.write_pdf(layout, &output_path, &font_context));
 
     match result {
         Ok(_) => 0,
@@ -138,8 +230,397 @@ pub extern "C" fn generate_pdf_output_ffi(
     }
 }
 
+/// Index every face of a font file (.ttf/.otf/.ttc/.otc) for later PDF exports.
+///
+/// Returns the number of faces registered, or -1 on error.
+#[no_mangle]
+pub extern "C" fn register_font_file(path: *const c_char) -> c_int {
+    if path.is_null() {
+        return -1;
+    }
+    let path = unsafe { CStr::from_ptr(path).to_string_lossy().into_owned() };
+
+    let mut registry = match font_registry::global_registry().write() {
+        Ok(registry) => registry,
+        Err(_) => return -1,
+    };
+    match registry.register_file(Path::new(&path)) {
+        Ok(count) => count as c_int,
+        Err(e) => {
+            eprintln!("Font registration failed: {}", e);
+            -1
+        }
+    }
+}
+
+/// Index every face of an in-memory font file. The bytes are copied.
+///
+/// `name` labels the font in logs; registering the same name again replaces it.
+/// Returns the number of faces registered, or -1 on error.
+#[no_mangle]
+pub extern "C" fn register_font_bytes(name: *const c_char, data: *const u8, length: usize) -> c_int {
+    if name.is_null() || data.is_null() || length == 0 {
+        return -1;
+    }
+    let name = unsafe { CStr::from_ptr(name).to_string_lossy().into_owned() };
+    let data = unsafe { std::slice::from_raw_parts(data, length) }.to_vec();
+
+    let mut registry = match font_registry::global_registry().write() {
+        Ok(registry) => registry,
+        Err(_) => return -1,
+    };
+    match registry.register_bytes(&name, data) {
+        Ok(count) => count as c_int,
+        Err(e) => {
+            eprintln!("Font registration failed: {}", e);
+            -1
+        }
+    }
+}
+
+/// Recursively index every font file below a directory.
+///
+/// Returns the number of faces registered, or -1 on error.
+#[no_mangle]
+pub extern "C" fn register_font_directory(path: *const c_char) -> c_int {
+    if path.is_null() {
+        return -1;
+    }
+    let path = unsafe { CStr::from_ptr(path).to_string_lossy().into_owned() };
+
+    let mut registry = match font_registry::global_registry().write() {
+        Ok(registry) => registry,
+        Err(_) => return -1,
+    };
+    match registry.add_directory(Path::new(&path)) {
+        Ok(count) => count as c_int,
+        Err(e) => {
+            eprintln!("Font directory registration failed: {}", e);
+            -1
+        }
+    }
+}
+
+/// Choose the font family used for Chinese (`role` 0), English (1), Devanagari (2),
+/// Siddham (3) or Tibetan (4) text.
+///
+/// The family must be registered (or installed) when a PDF is generated; otherwise
+/// the default families are used. Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn set_pdf_font_family(role: c_int, family: *const c_char) -> c_int {
+    if family.is_null() {
+        return -1;
+    }
+    let family = unsafe { CStr::from_ptr(family).to_string_lossy().into_owned() };
+    let script = match role {
+        FONT_ROLE_CHINESE => Script::Han,
+        FONT_ROLE_ENGLISH => Script::Latin,
+        FONT_ROLE_DEVANAGARI => Script::Devanagari,
+        FONT_ROLE_SIDDHAM => Script::Siddham,
+        FONT_ROLE_TIBETAN => Script::Tibetan,
+        _ => return -1,
+    };
+
+    match font_registry::global_registry().write() {
+        Ok(mut registry) => {
+            registry.set_preferred_family(script, family.trim());
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Set the ordered fallback chain for Chinese (`role` 0) or English (`role` 1) text.
+///
+/// Characters the primary font lacks are drawn with the first family in the chain
+/// that has them. Unregistered families are skipped. Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn set_pdf_font_fallbacks(role: c_int, families: *const *const c_char, family_count: usize) -> c_int {
+    if families.is_null() && family_count > 0 {
+        return -1;
+    }
+    let script = match role {
+        FONT_ROLE_CHINESE => Script::Han,
+        FONT_ROLE_ENGLISH => Script::Latin,
+        _ => return -1,
+    };
+    let families = if family_count == 0 {
+        Vec::new()
+    } else {
+        unsafe {
+            std::slice::from_raw_parts(families, family_count)
+                .iter()
+                .filter(|ptr| !ptr.is_null())
+                .map(|&ptr| CStr::from_ptr(ptr).to_string_lossy().trim().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    match font_registry::global_registry().write() {
+        Ok(mut registry) => {
+            let families: Vec<&str> = families.iter().map(String::as_str).collect();
+            registry.set_fallback_families(script, &families);
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Set the OpenType features applied to text of one script, on top of the
+/// shaper's defaults (ligatures, kerning, ...).
+///
+/// `script` is an ISO 15924 code such as `Hani` or `Latn` (`Zyyy` for digits and
+/// punctuation); `features` is a comma-separated list such as `palt` or `-liga, +onum`.
+/// An empty list restores the defaults. Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn set_pdf_font_features(script: *const c_char, features: *const c_char) -> c_int {
+    if script.is_null() || features.is_null() {
+        return -1;
+    }
+    let script = unsafe { CStr::from_ptr(script).to_string_lossy().trim().to_string() };
+    let features = unsafe { CStr::from_ptr(features).to_string_lossy().into_owned() };
+    let Some(script) = Script::from_short_name(&script).or_else(|| Script::from_full_name(&script)) else {
+        return -1;
+    };
+
+    match font_features().lock() {
+        Ok(mut settings) => {
+            if settings.set_from_str(script, &features) {
+                0
+            } else {
+                -1
+            }
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Select the hyphenation patterns for English text and the fewest letters kept
+/// before (`left_min`) and carried after (`right_min`) a hyphen.
+///
+/// `language` is `en-us` (built in) or a language loaded with
+/// `load_pdf_hyphenation_patterns`; an empty string turns hyphenation off. Words
+/// with IAST letters always use the built-in Sanskrit patterns.
+/// Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn set_pdf_hyphenation(language: *const c_char, left_min: c_int, right_min: c_int) -> c_int {
+    if language.is_null() || left_min < 1 || right_min < 1 {
+        return -1;
+    }
+    let language = unsafe { CStr::from_ptr(language).to_string_lossy().trim().to_string() };
+
+    match hyphenation().lock() {
+        Ok(mut settings) => {
+            if language.is_empty() {
+                settings.enabled = false;
+                return 0;
+            }
+            if !settings.set_language(&language) {
+                return -1;
+            }
+            settings.enabled = true;
+            settings.left_min = left_min as usize;
+            settings.right_min = right_min as usize;
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Load hyph-utf8 style hyphenation patterns (`hyph-de-1996.pat.txt`) for
+/// `language`, with an optional exception list (`.hyp.txt`, may be null).
+/// Select the language with `set_pdf_hyphenation`. Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn load_pdf_hyphenation_patterns(
+    language: *const c_char,
+    patterns_path: *const c_char,
+    exceptions_path: *const c_char,
+) -> c_int {
+    if language.is_null() || patterns_path.is_null() {
+        return -1;
+    }
+    let language = unsafe { CStr::from_ptr(language).to_string_lossy().trim().to_string() };
+    let patterns_path = unsafe { CStr::from_ptr(patterns_path).to_string_lossy().into_owned() };
+    let exceptions_path = if exceptions_path.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(exceptions_path).to_string_lossy().into_owned() })
+    };
+    if language.is_empty() {
+        return -1;
+    }
+
+    let Ok(mut settings) = hyphenation().lock() else {
+        return -1;
+    };
+    match settings.load_language(&language, Path::new(&patterns_path), exceptions_path.as_deref().map(Path::new)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Hyphenation pattern loading failed: {}", e);
+            -1
+        }
+    }
+}
+
+/// Set the marker placed under each column of a side-by-side row that continues
+/// on the next page, such as `（續）` and `(continued)`. Either text may be empty
+/// to mark only the other column; two nulls remove the marker.
+/// Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn set_pdf_continued_marker(chinese: *const c_char, english: *const c_char) -> c_int {
+    let text = |ptr: *const c_char| {
+        if ptr.is_null() {
+            String::new()
+        } else {
+            unsafe { CStr::from_ptr(ptr).to_string_lossy().trim().to_string() }
+        }
+    };
+    let marker = if chinese.is_null() && english.is_null() {
+        None
+    } else {
+        Some((text(chinese), text(english)))
+    };
+
+    match continued_marker().lock() {
+        Ok(mut settings) => {
+            *settings = marker;
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Turn ruby annotations over Chinese text on or off and set their size in em of
+/// the Chinese text (0.5 when `scale` is 0). Readings come from `<ruby>` markup in
+/// the sections and from dictionaries loaded with `load_pdf_ruby_dictionary`.
+/// Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn set_pdf_ruby(enabled: c_int, scale: f32) -> c_int {
+    if !(0.0..=1.0).contains(&scale) {
+        return -1;
+    }
+    match ruby().lock() {
+        Ok(mut settings) => {
+            settings.enabled = enabled != 0;
+            settings.scale = if scale > 0.0 { scale } else { 0.5 };
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Load ruby readings from a dictionary file: CC-CEDICT or CC-Canto lines read as
+/// pinyin with tone marks (0), numbered pinyin (1) or Jyutping (2), or
+/// `word<TAB>reading` lines (3) for zhuyin and other readings. A null path clears
+/// the readings loaded so far. Returns the number of entries read, -1 on error.
+#[no_mangle]
+pub extern "C" fn load_pdf_ruby_dictionary(path: *const c_char, format: c_int) -> c_int {
+    let Ok(mut settings) = ruby().lock() else {
+        return -1;
+    };
+    if path.is_null() {
+        settings.clear_readings();
+        return 0;
+    }
+    let path = unsafe { CStr::from_ptr(path).to_string_lossy().into_owned() };
+    let reading = match format {
+        RUBY_DICTIONARY_PINYIN => Some(DictionaryReading::Pinyin),
+        RUBY_DICTIONARY_NUMBERED_PINYIN => Some(DictionaryReading::NumberedPinyin),
+        RUBY_DICTIONARY_JYUTPING => Some(DictionaryReading::Jyutping),
+        RUBY_DICTIONARY_TAB_SEPARATED => None,
+        _ => return -1,
+    };
+
+    match settings.load_dictionary(Path::new(&path), reading) {
+        Ok(entries) => entries.min(c_int::MAX as usize) as c_int,
+        Err(e) => {
+            eprintln!("Ruby dictionary loading failed: {}", e);
+            -1
+        }
+    }
+}
+
+/// Set horizontal Chinese verse (sections that are a whole `<lg>` element) in one
+/// column of 句, or two side by side when they fit. Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn set_pdf_verse_columns(columns: c_int) -> c_int {
+    if !(1..=2).contains(&columns) {
+        return -1;
+    }
+    match verse_columns().lock() {
+        Ok(mut setting) => {
+            *setting = columns as usize;
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Set how pages break between paragraphs: `keep_headings` keeps a heading on a
+/// page with the first lines of what follows it, `keep_pairs` keeps a Chinese
+/// paragraph with the first lines of its English, and pairs of at most
+/// `short_pair_lines` lines in all (0 for none) move to a new page whole.
+/// Defaults are 1, 1 and 6. Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn set_pdf_keep_rules(keep_headings: c_int, keep_pairs: c_int, short_pair_lines: c_int) -> c_int {
+    if short_pair_lines < 0 {
+        return -1;
+    }
+    match keep_rules().lock() {
+        Ok(mut rules) => {
+            *rules = (keep_headings != 0, keep_pairs != 0, short_pair_lines as usize);
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Define the paragraph style `name`, selected by sections that start with
+/// `<p class="name">`. `spec` lists `property: value` pairs separated by semicolons,
+/// such as `first-line-indent: 2; align: justify; space-after: 6`; a null or empty
+/// `spec` removes the style. Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn set_pdf_paragraph_style(name: *const c_char, spec: *const c_char) -> c_int {
+    if name.is_null() {
+        return -1;
+    }
+    let name = unsafe { CStr::from_ptr(name).to_string_lossy().trim().to_string() };
+    let spec = if spec.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(spec).to_string_lossy().into_owned() }
+    };
+    if name.is_empty() {
+        return -1;
+    }
+
+    let Ok(mut styles) = paragraph_styles().lock() else {
+        return -1;
+    };
+    if spec.trim().is_empty() {
+        styles.remove(&name);
+        return 0;
+    }
+    match ParagraphStyle::parse(&spec) {
+        Ok(style) => {
+            styles.set(&name, style);
+            0
+        }
+        Err(e) => {
+            eprintln!("Paragraph style definition failed: {}", e);
+            -1
+        }
+    }
+}
+
+/// Collapse runs of whitespace within each line of a section. Line breaks are
+/// kept so verse (`<lg>`) keeps its lines; other sections join them with spaces.
 fn normalize_pdf_section_text(text: &str) -> String {
-    text.split_whitespace().collect::<Vec<_>>().join(" ")
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 fn choose_auto_font_size(
@@ -218,7 +699,15 @@ fn estimate_fill_ratio(
         let zh = chinese_sections.get(i).map_or("", |s| s.as_str());
         let en = english_sections.get(i).map_or("", |s| s.as_str());
 
-        if layout_mode == LAYOUT_SIDE_BY_SIDE {
+        if layout_mode == LAYOUT_VERTICAL {
+            // Columns run across the page, so the page width fills up instead of the height.
+            if !zh.trim().is_empty() {
+                total_height += estimate_paragraph_height(font_context, zh, content_height, true) + row_gap;
+            }
+            if !en.trim().is_empty() {
+                total_height += estimate_paragraph_height(font_context, en, content_height, false) + row_gap;
+            }
+        } else if layout_mode == LAYOUT_SIDE_BY_SIDE {
             let zh_h = estimate_paragraph_height(font_context, zh, left_col_width, true);
             let en_h = estimate_paragraph_height(font_context, en, left_col_width, false);
             total_height += zh_h.max(en_h).max(font_context.get_line_height(true)) + row_gap;
@@ -237,8 +726,9 @@ fn estimate_fill_ratio(
         return 1.0;
     }
 
-    let pages = (total_height / content_height).ceil().max(1.0);
-    (total_height / (pages * content_height)).clamp(0.0, 1.0)
+    let page_extent = if layout_mode == LAYOUT_VERTICAL { content_width } else { content_height };
+    let pages = (total_height / page_extent).ceil().max(1.0);
+    (total_height / (pages * page_extent)).clamp(0.0, 1.0)
 }
 
 fn estimate_paragraph_height(